@@ -0,0 +1,69 @@
+//! Per-Execution Overhead Benchmark
+//!
+//! This benchmark measures the fixed cost of running a tiny script against an
+//! orchestrator with many registered tools. The baseline rebuilds a full Rhai
+//! engine and re-registers every tool on each run, which is what
+//! `ToolOrchestrator::execute` used to do. The orchestrator registers its
+//! tools once, in a module shared by every execution, so its overhead should
+//! barely grow with the number of tools.
+//!
+//! Run with: `cargo bench --bench engine_overhead`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rhai::{Dynamic, Engine};
+use tool_orchestrator::{ExecutionLimits, ToolOrchestrator};
+
+const TOOL_COUNTS: [usize; 2] = [50, 100];
+
+fn tool_names(count: usize) -> impl Iterator<Item = String> {
+    (0..count).map(|i| format!("tool_{i}"))
+}
+
+/// A script calling the first and last of `count` tools
+fn script(count: usize) -> String {
+    format!(r#"let a = tool_0("x"); let b = tool_{}("y"); a + b"#, count - 1)
+}
+
+/// Old approach: a fresh `Engine::new()` with every tool registered per run
+fn fresh_engine_execute(script: &str, count: usize) -> String {
+    let mut engine = Engine::new();
+    for name in tool_names(count) {
+        let tool_name = name.clone();
+        engine.register_fn(&name, move |input: Dynamic| format!("{tool_name}:{input}"));
+    }
+    engine.eval::<String>(script).unwrap()
+}
+
+fn benchmark_per_execution_overhead(c: &mut Criterion) {
+    for count in TOOL_COUNTS {
+        let mut orchestrator = ToolOrchestrator::new();
+        for name in tool_names(count) {
+            let tool_name = name.clone();
+            orchestrator.register_executor(name, move |input| {
+                Ok(format!("{tool_name}:{}", input.as_str().unwrap_or_default()))
+            });
+        }
+        let limits = ExecutionLimits::default();
+        let script = script(count);
+
+        let mut group = c.benchmark_group(format!("per_execution_overhead_{count}_tools"));
+
+        group.bench_function("fresh_engine_baseline", |b| {
+            b.iter(|| fresh_engine_execute(black_box(&script), count));
+        });
+
+        group.bench_function("orchestrator_execute", |b| {
+            b.iter(|| orchestrator.execute(black_box(&script), limits).unwrap());
+        });
+
+        // No tool calls: what every execution pays before the script does anything
+        group.bench_function("orchestrator_trivial_script", |b| {
+            b.iter(|| orchestrator.execute(black_box("40 + 2"), limits).unwrap());
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, benchmark_per_execution_overhead);
+criterion_main!(benches);
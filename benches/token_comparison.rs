@@ -6,7 +6,7 @@
 //! Run with: `cargo bench`
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use tool_orchestrator::{ExecutionLimits, ToolOrchestrator};
+use tool_orchestrator::{estimate_tokens, ExecutionLimits, ToolOrchestrator};
 
 /// Simulates traditional approach: each tool result would go back to LLM
 fn traditional_approach_simulation(employee_count: usize) -> TraditionalMetrics {
@@ -70,7 +70,7 @@ fn programmatic_approach(employee_count: usize) -> ProgrammaticMetrics {
     ProgrammaticMetrics {
         tool_calls: result.tool_calls.len(),
         output_chars: result.output.len(),
-        estimated_tokens: result.output.len() / 4,
+        estimated_tokens: estimate_tokens(&result.output),
         execution_time_ms: result.execution_time_ms,
     }
 }
@@ -0,0 +1,80 @@
+//! Adaptive Budget Example
+//!
+//! This example demonstrates a script that adapts to its own limits: it
+//! enriches records with a slow, optional API call while there is time, and
+//! switches to a cheap fallback once little time or few tool calls remain.
+//!
+//! ## Scenario
+//!
+//! We have two "APIs":
+//! - Order service: Get the basic order record (fast)
+//! - Enrichment service: Add customer history to an order (slow, optional)
+//!
+//! The script reads `remaining_time_ms()` and `remaining_tool_calls()` before
+//! each enrichment, so the run finishes with a complete, if partly plain,
+//! report instead of hitting the timeout halfway through.
+//!
+//! Run with: `cargo run --example adaptive_budget`
+
+use std::time::Duration;
+
+use tool_orchestrator::{ExecutionLimits, ToolOrchestrator};
+
+fn main() {
+    println!("=== Adaptive Budget Example ===\n");
+
+    let mut orchestrator = ToolOrchestrator::new();
+
+    // Simulated Order Service API
+    orchestrator.register_executor("get_order", |input| {
+        let id = input.as_i64().unwrap_or(0);
+        Ok(format!(r#"{{"id":{id},"total":{}}}"#, 20 + id * 15))
+    });
+
+    // Simulated Enrichment Service API, slow enough to matter
+    orchestrator.register_executor("enrich_order", |input| {
+        std::thread::sleep(Duration::from_millis(400));
+        let id = input.as_i64().unwrap_or(0);
+        Ok(format!("customer since {}, {} earlier orders", 2015 + id % 8, id * 3))
+    });
+
+    let script = r#"
+        let lines = [];
+        let enriched = 0;
+        for id in 1..=8 {
+            let order = parse_json(get_order(id));
+            // Keep 2s and two tool calls in reserve for the remaining orders
+            let detail = if remaining_time_ms() < 2000 || remaining_tool_calls() < 2 {
+                "history skipped"
+            } else {
+                enriched += 1;
+                enrich_order(id)
+            };
+            lines.push(`#${order.id} $${order.total} (${detail})`);
+        }
+        lines.push(`${enriched} of 8 orders enriched`);
+        lines
+    "#;
+
+    // Far too little time to enrich all eight orders
+    let limits = ExecutionLimits::default().with_timeout_ms(4_000);
+
+    println!("Executing script with a 4s timeout...\n");
+    match orchestrator.execute(script, limits) {
+        Ok(result) => {
+            println!("Success: {}", result.success);
+            println!("Tool calls: {}", result.tool_calls.len());
+            println!("Time: {}ms\n", result.execution_time_ms);
+            if let Some(serde_json::Value::Array(lines)) = result.structured_output {
+                for line in lines {
+                    println!("  {}", line.as_str().unwrap_or_default());
+                }
+            }
+        }
+        Err(e) => {
+            println!("Error: {e}");
+        }
+    }
+
+    println!("\n=== Example Complete ===");
+}
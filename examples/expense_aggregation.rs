@@ -21,7 +21,7 @@
 //!
 //! Run with: `cargo run --example expense_aggregation`
 
-use tool_orchestrator::{ExecutionLimits, ToolOrchestrator};
+use tool_orchestrator::{ExecutionLimits, ExecutionOptions, ToolOrchestrator};
 
 fn main() {
     println!("=== Expense Aggregation Example ===\n");
@@ -153,8 +153,10 @@ fn main() {
 
     println!("Executing expense aggregation script...\n");
 
+    // Write amounts as dollars and cents
+    let options = ExecutionOptions::new().with_float_precision(Some(2));
     let result = orchestrator
-        .execute(script, ExecutionLimits::default())
+        .execute_with_options(script, ExecutionLimits::default(), &options)
         .expect("Script execution failed");
 
     println!("=== Result ===");
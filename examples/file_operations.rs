@@ -3,6 +3,7 @@
 //! This example shows how to register file system tools and use them
 //! in orchestrated scripts. It demonstrates:
 //!
+//! - Implementing tools as types with the `Tool` trait
 //! - Registering multiple related tools
 //! - Filtering and processing results in Rhai
 //! - Conditional logic based on file types
@@ -11,15 +12,22 @@
 //! Run with: `cargo run --example file_operations`
 
 use std::fs;
-use tool_orchestrator::{ExecutionLimits, ToolOrchestrator};
+use std::sync::Arc;
+use tool_orchestrator::{ExecutionLimits, Tool, ToolOrchestrator};
 
-fn main() {
-    println!("=== File Operations Example ===\n");
+/// Lists the entries of a directory with their sizes.
+struct ListFiles;
 
-    let mut orchestrator = ToolOrchestrator::new();
+impl Tool for ListFiles {
+    fn name(&self) -> &str {
+        "list_files"
+    }
 
-    // Register file listing tool
-    orchestrator.register_executor("list_files", |input| {
+    fn description(&self) -> &str {
+        "list_files(path) lists a directory, one `name (123b)` or `name/ (dir)` per line"
+    }
+
+    fn call(&self, input: serde_json::Value) -> Result<String, String> {
         let path = input.as_str().unwrap_or(".");
 
         match fs::read_dir(path) {
@@ -42,17 +50,32 @@ fn main() {
             }
             Err(e) => Err(format!("Failed to list directory: {}", e)),
         }
-    });
+    }
+}
 
-    // Register file reading tool
-    orchestrator.register_executor("read_file", |input| {
+/// Reads a text file, keeping at most `max_chars` characters.
+struct ReadFile {
+    max_chars: usize,
+}
+
+impl Tool for ReadFile {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "read_file(path) returns the start of a text file"
+    }
+
+    fn call(&self, input: serde_json::Value) -> Result<String, String> {
         let path = input.as_str().unwrap_or("");
 
         match fs::read_to_string(path) {
             Ok(content) => {
-                // Limit content to first 1000 chars for safety
-                let truncated = if content.len() > 1000 {
-                    format!("{}... (truncated)", &content[..1000])
+                // Limit content for safety
+                let truncated = if content.chars().count() > self.max_chars {
+                    let head: String = content.chars().take(self.max_chars).collect();
+                    format!("{}... (truncated)", head)
                 } else {
                     content
                 };
@@ -60,10 +83,22 @@ fn main() {
             }
             Err(e) => Err(format!("Failed to read file: {}", e)),
         }
-    });
+    }
+}
+
+/// Reports the size, kind and permissions of a path as JSON.
+struct FileInfo;
 
-    // Register file info tool
-    orchestrator.register_executor("file_info", |input| {
+impl Tool for FileInfo {
+    fn name(&self) -> &str {
+        "file_info"
+    }
+
+    fn description(&self) -> &str {
+        "file_info(path) returns {size, is_file, is_dir, readonly} as JSON"
+    }
+
+    fn call(&self, input: serde_json::Value) -> Result<String, String> {
         let path = input.as_str().unwrap_or("");
 
         match fs::metadata(path) {
@@ -79,7 +114,24 @@ fn main() {
             }
             Err(e) => Err(format!("Failed to get file info: {}", e)),
         }
-    });
+    }
+}
+
+fn main() {
+    println!("=== File Operations Example ===\n");
+
+    let mut orchestrator = ToolOrchestrator::new();
+
+    // Register the file system tools
+    orchestrator.register_tool(Arc::new(ListFiles));
+    orchestrator.register_tool(Arc::new(ReadFile { max_chars: 1000 }));
+    orchestrator.register_tool(Arc::new(FileInfo));
+
+    println!("Available tools:");
+    for name in ["list_files", "read_file", "file_info"] {
+        println!("- {}", orchestrator.tool_description(name).unwrap_or(name));
+    }
+    println!();
 
     // Script that analyzes the current directory
     let script = r#"
@@ -14,10 +14,20 @@
 //! The script fetches user preferences, checks the weather for their
 //! location, and suggests appropriate activities.
 //!
+//! The user and weather services are slow, so the script starts the lookups
+//! for all three users with `spawn_tool` and waits for them together with
+//! `join_all`, paying one round of latency instead of three.
+//!
 //! Run with: `cargo run --example multi_api`
 
+use std::thread;
+use std::time::Duration;
+
 use tool_orchestrator::{ExecutionLimits, ToolOrchestrator};
 
+/// Simulated network latency of the user and weather services
+const API_LATENCY: Duration = Duration::from_millis(100);
+
 fn main() {
     println!("=== Multi-API Orchestration Example ===\n");
 
@@ -25,6 +35,7 @@ fn main() {
 
     // Simulated User Service API
     orchestrator.register_executor("get_user_preferences", |input| {
+        thread::sleep(API_LATENCY);
         let user_id = input.as_str().unwrap_or("unknown");
 
         let prefs = match user_id {
@@ -39,6 +50,7 @@ fn main() {
 
     // Simulated Weather Service API
     orchestrator.register_executor("get_weather", |input| {
+        thread::sleep(API_LATENCY);
         let location = input.as_str().unwrap_or("Unknown");
 
         let weather = match location {
@@ -99,11 +111,16 @@ fn main() {
         let users = ["alice", "bob", "carol"];
         let results = [];
 
+        // Step 1: Get every user's preferences at once
+        let pref_tasks = [];
         for user in users {
-            // Step 1: Get user preferences
-            let prefs_json = get_user_preferences(user);
+            pref_tasks.push(spawn_tool("get_user_preferences", user));
+        }
+        let all_prefs = join_all(pref_tasks);
 
-            // Extract location (simple parsing)
+        // Extract locations (simple parsing)
+        let locations = [];
+        for prefs_json in all_prefs {
             let location = "";
             if prefs_json.contains("Seattle") {
                 location = "Seattle";
@@ -112,9 +129,20 @@ fn main() {
             } else if prefs_json.contains("Denver") {
                 location = "Denver";
             }
+            locations.push(location);
+        }
+
+        // Step 2: Get the weather for every location at once
+        let weather_tasks = [];
+        for location in locations {
+            weather_tasks.push(spawn_tool("get_weather", location));
+        }
+        let all_weather = join_all(weather_tasks);
 
-            // Step 2: Get weather for their location
-            let weather_json = get_weather(location);
+        for i in 0..users.len() {
+            let user = users[i];
+            let location = locations[i];
+            let weather_json = all_weather[i];
 
             // Extract condition
             let condition = "unknown";
@@ -189,5 +217,6 @@ Processed ${users.len()} users with conditional notifications.`
     println!("\n=== Why This Matters ===");
     println!("Traditional approach: 12+ round trips to the LLM");
     println!("PTC approach: 1 round trip with orchestrated logic");
+    println!("The six slow lookups ran in two concurrent rounds instead of one after another.");
     println!("The conditional notifications happened automatically based on weather!");
 }
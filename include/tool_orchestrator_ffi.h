@@ -0,0 +1,311 @@
+#ifndef TOOL_ORCHESTRATOR_FFI_H
+#define TOOL_ORCHESTRATOR_FFI_H
+
+/* Generated with cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The call succeeded.
+ */
+#define TO_OK 0
+
+/**
+ * A required pointer argument was `NULL`.
+ */
+#define TO_ERROR_NULL_POINTER 1
+
+/**
+ * A string argument was not valid UTF-8.
+ */
+#define TO_ERROR_INVALID_UTF8 2
+
+/**
+ * An argument was rejected, e.g. an unknown limits profile.
+ */
+#define TO_ERROR_INVALID_ARGUMENT 3
+
+/**
+ * The orchestrator panicked; the panic was caught at the boundary.
+ */
+#define TO_ERROR_PANIC 4
+
+/**
+ * An orchestrator created by [`to_orchestrator_new`].
+ */
+typedef struct ToOrchestrator ToOrchestrator;
+
+/**
+ * The result of [`to_orchestrator_execute`].
+ */
+typedef struct ToResult ToResult;
+
+/**
+ * Execution limits passed to [`to_orchestrator_execute`].
+ *
+ * Covers the numeric limits of [`ExecutionLimits`]; the rest keep their
+ * defaults. Start from [`to_limits_default`] or [`to_limits_from_profile`]
+ * and adjust the fields you need.
+ *
+ * `struct_size` versions the layout. Callers set it to `sizeof(ToLimits)`
+ * before handing the struct to any function; new fields are only ever
+ * appended, so a caller built against an older header keeps working and the
+ * fields it doesn't know about keep their defaults.
+ */
+typedef struct {
+  /**
+   * `sizeof(ToLimits)` as the caller was compiled
+   */
+  size_t struct_size;
+  /**
+   * Maximum number of operations (prevents infinite loops)
+   */
+  uint64_t max_operations;
+  /**
+   * Maximum number of tool calls allowed
+   */
+  size_t max_tool_calls;
+  /**
+   * Timeout in milliseconds
+   */
+  uint64_t timeout_ms;
+  /**
+   * Maximum string size in bytes
+   */
+  size_t max_string_size;
+  /**
+   * Maximum array size
+   */
+  size_t max_array_size;
+  /**
+   * Maximum map size
+   */
+  size_t max_map_size;
+  /**
+   * Maximum bytes a single tool call may return
+   */
+  size_t max_tool_output_bytes;
+  /**
+   * Maximum size of a single tool call's input, serialized as JSON
+   */
+  size_t max_tool_input_bytes;
+  /**
+   * Maximum cumulative bytes returned by all tool calls in one execution
+   */
+  size_t max_total_tool_output_bytes;
+  /**
+   * Maximum size of the script's final output in bytes
+   */
+  size_t max_output_bytes;
+  /**
+   * Maximum summed cost of the tool calls in one execution (negative for
+   * no budget)
+   */
+  double max_total_cost;
+  /**
+   * Produce reproducible results (timing fields are reported as zero)
+   */
+  bool deterministic;
+  /**
+   * Maximum script source size in bytes, checked before compilation
+   */
+  size_t max_script_bytes;
+  /**
+   * Maximum number of lines in the script source, checked before compilation
+   */
+  size_t max_script_lines;
+  /**
+   * Maximum number of functions the script may define
+   */
+  size_t max_functions;
+  /**
+   * Maximum number of statements in the compiled script, including function bodies
+   */
+  size_t max_statements;
+  /**
+   * Refuse to start a tool call with less than this much of `timeout_ms` left (0 to disable)
+   */
+  uint64_t min_remaining_ms_for_tool_call;
+  /**
+   * Maximum number of distinct names the script may `emit`
+   */
+  size_t max_outputs;
+  /**
+   * Maximum serialized size of all emitted outputs in bytes, names included
+   */
+  size_t max_outputs_bytes;
+  /**
+   * Maximum number of keys in the key-value store scripts write with `kv_set`
+   */
+  size_t max_kv_entries;
+  /**
+   * Maximum serialized size of the key-value store in bytes, keys included
+   */
+  size_t max_kv_bytes;
+  /**
+   * Maximum bytes of state the orchestrator holds for one execution
+   */
+  size_t max_total_state_bytes;
+  /**
+   * Maximum number of tool calls running at once on their own threads
+   */
+  size_t max_concurrent_tool_calls;
+  /**
+   * Operations charged against `max_operations` for each counted tool call
+   */
+  uint64_t tool_call_operation_cost;
+} ToLimits;
+
+/**
+ * A tool implemented in C.
+ *
+ * Receives the script's argument as JSON in `json_input`, which is only
+ * valid during the call. On success it returns 0 and may set `*out` to the
+ * tool's output (an empty string if left `NULL`); any other return value
+ * fails the call, with `*err` as the message if set. Both strings must be
+ * allocated with `malloc` and are freed by the orchestrator. Callbacks may
+ * run on other threads (`call_parallel`, `spawn_tool`) and must not unwind.
+ */
+typedef int (*ToToolCallback)(const char *json_input, char **out, char **err);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Write the default execution limits to `out`.
+ *
+ * Set `out->struct_size` to `sizeof(ToLimits)` first.
+ *
+ * # Safety
+ *
+ * `out` must be valid for reads and writes of its `struct_size` bytes.
+ */
+int to_limits_default(ToLimits *out);
+
+/**
+ * Write the limits of a preset profile (`"quick"`, `"default"`,
+ * `"extended"` or `"unlimited"`) to `out`.
+ *
+ * Set `out->struct_size` to `sizeof(ToLimits)` first. Returns
+ * [`TO_ERROR_INVALID_ARGUMENT`] for an unknown profile.
+ *
+ * # Safety
+ *
+ * `name` must be a NUL-terminated string and `out` must be valid for reads
+ * and writes of its `struct_size` bytes.
+ */
+int to_limits_from_profile(const char *name, ToLimits *out);
+
+/**
+ * Create an orchestrator with no tools, to be released with
+ * [`to_orchestrator_free`].
+ */
+ToOrchestrator *to_orchestrator_new(void);
+
+/**
+ * Destroy an orchestrator. `NULL` is ignored.
+ *
+ * # Safety
+ *
+ * `orchestrator` must be `NULL` or come from [`to_orchestrator_new`], and
+ * must not be used afterwards.
+ */
+void to_orchestrator_free(ToOrchestrator *orchestrator);
+
+/**
+ * Register `callback` as the tool `name`, replacing any tool already
+ * registered under that name.
+ *
+ * # Safety
+ *
+ * `orchestrator` must come from [`to_orchestrator_new`] and `name` must be
+ * a NUL-terminated string. Neither may be in use on another thread.
+ */
+int to_orchestrator_register_tool(ToOrchestrator *orchestrator,
+                                  const char *name,
+                                  ToToolCallback callback);
+
+/**
+ * Execute a script with the registered tools, storing the result in
+ * `*result` for release with [`to_result_free`].
+ *
+ * Uses the default limits when `limits` is `NULL`, and returns
+ * [`TO_ERROR_INVALID_ARGUMENT`] when its `struct_size` isn't one this
+ * library knows. Returns [`TO_OK`] even when the script fails; check
+ * [`to_result_success`].
+ *
+ * # Safety
+ *
+ * `orchestrator` must come from [`to_orchestrator_new`], `script` must be a
+ * NUL-terminated string, `limits` must be `NULL` or valid for reads of its
+ * `struct_size` bytes and `result` must be valid for writes.
+ */
+int to_orchestrator_execute(const ToOrchestrator *orchestrator,
+                            const char *script,
+                            const ToLimits *limits,
+                            ToResult **result);
+
+/**
+ * Whether the script ran to completion. `false` for `NULL`.
+ *
+ * # Safety
+ *
+ * `result` must be `NULL` or come from [`to_orchestrator_execute`].
+ */
+bool to_result_success(const ToResult *result);
+
+/**
+ * The script's output, to be released with [`to_free`].
+ *
+ * # Safety
+ *
+ * `result` must come from [`to_orchestrator_execute`].
+ */
+char *to_result_output(const ToResult *result);
+
+/**
+ * The whole result serialized as JSON, with the same fields as
+ * `OrchestratorResult`, to be released with [`to_free`].
+ *
+ * # Safety
+ *
+ * `result` must come from [`to_orchestrator_execute`].
+ */
+char *to_result_json(const ToResult *result);
+
+/**
+ * Destroy a result. `NULL` is ignored.
+ *
+ * # Safety
+ *
+ * `result` must be `NULL` or come from [`to_orchestrator_execute`], and
+ * must not be used afterwards.
+ */
+void to_result_free(ToResult *result);
+
+/**
+ * The message of the last failed call on this thread, to be released with
+ * [`to_free`], or `NULL` if none has failed.
+ */
+char *to_last_error(void);
+
+/**
+ * Release a string returned by one of these functions. `NULL` is ignored.
+ *
+ * # Safety
+ *
+ * `ptr` must be `NULL` or a string returned by this library, and must not
+ * be used afterwards.
+ */
+void to_free(char *ptr);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TOOL_ORCHESTRATOR_FFI_H */
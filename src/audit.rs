@@ -0,0 +1,505 @@
+//! JSONL audit logs of execution results (`audit` feature).
+//!
+//! [`AuditWriter`] appends each [`OrchestratorResult`] as one line of JSON,
+//! optionally gzip-compressed, and starts a new file when its
+//! [`RotationPolicy`] says so. [`AuditReader`] reads the results back for
+//! offline analysis or replay.
+//!
+//! Rotated files keep the log's name with a number appended, oldest first:
+//! `audit.jsonl.1`, `audit.jsonl.2`, and so on, while new results always go
+//! to `audit.jsonl`. [`AuditReader::files`] lists them in order.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use tool_orchestrator::audit::{AuditReader, AuditWriter, RotationPolicy};
+//!
+//! let policy = RotationPolicy::new().with_max_bytes(64 << 20).with_daily(true);
+//! let audit = AuditWriter::jsonl("logs/audit.jsonl", policy)?;
+//! audit.write(&orchestrator.execute(script, limits)?)?;
+//!
+//! for file in AuditReader::files("logs/audit.jsonl")? {
+//!     for result in AuditReader::iter(&file)? {
+//!         let result = result?;
+//!         println!("{} made {} calls", result.execution_id, result.tool_calls.len());
+//!     }
+//! }
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::bufread::GzDecoder;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::types::OrchestratorResult;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The first bytes of every gzip member
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// ============================================================================
+// Writing
+// ============================================================================
+
+/// When an [`AuditWriter`] moves on to a new file.
+///
+/// The default never rotates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Rotate before a record would take the file past this many bytes
+    pub max_bytes: Option<u64>,
+    /// Rotate before the first record of each UTC day
+    pub daily: bool,
+}
+
+impl RotationPolicy {
+    /// A policy that never rotates.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { max_bytes: None, daily: false }
+    }
+
+    /// Rotate before a record would take the file past `bytes` (builder pattern).
+    ///
+    /// A single record larger than `bytes` still gets a file of its own.
+    #[must_use]
+    pub const fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Rotate before the first record of each UTC day (builder pattern).
+    #[must_use]
+    pub const fn with_daily(mut self, daily: bool) -> Self {
+        self.daily = daily;
+        self
+    }
+}
+
+/// The file an [`AuditWriter`] is appending to.
+#[derive(Debug)]
+struct CurrentFile {
+    file: File,
+    bytes: u64,
+    /// UTC day, counted from the Unix epoch, of the file's first record
+    day: u64,
+}
+
+/// Appends execution results to a JSONL audit log.
+///
+/// Each record is written with a single `write` to a file opened for
+/// appending, so records from concurrent writers don't interleave on local
+/// filesystems. Within a process, share one writer; it is `Send + Sync`.
+///
+/// Opening a plain log whose last line was cut short, e.g. by a crash, ends
+/// that line first so the next record starts on a line of its own.
+#[derive(Debug)]
+pub struct AuditWriter {
+    path: PathBuf,
+    policy: RotationPolicy,
+    gzip: bool,
+    current: Mutex<CurrentFile>,
+}
+
+impl AuditWriter {
+    /// Append to the plain JSONL log at `path`, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the file can't be opened.
+    pub fn jsonl(path: impl Into<PathBuf>, policy: RotationPolicy) -> io::Result<Self> {
+        Self::open(path.into(), policy, false)
+    }
+
+    /// Append to the gzip-compressed JSONL log at `path`, creating it if needed.
+    ///
+    /// Every record is a gzip member of its own, so the file stays valid
+    /// after each write and can be read with `zcat` as well as
+    /// [`AuditReader`]. A record cut short by a crash is removed when the
+    /// log is opened again, so the records after it stay readable.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the file can't be opened.
+    pub fn jsonl_gz(path: impl Into<PathBuf>, policy: RotationPolicy) -> io::Result<Self> {
+        Self::open(path.into(), policy, true)
+    }
+
+    fn open(path: PathBuf, policy: RotationPolicy, gzip: bool) -> io::Result<Self> {
+        let current = open_current(&path, gzip)?;
+        Ok(Self { path, policy, gzip, current: Mutex::new(current) })
+    }
+
+    /// The path new records are written to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `result` to the log, rotating first if the policy says so.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if rotating or writing fails.
+    pub fn write(&self, result: &OrchestratorResult) -> io::Result<()> {
+        self.write_on(result, current_day())
+    }
+
+    fn write_on(&self, result: &OrchestratorResult, day: u64) -> io::Result<()> {
+        let mut record = serde_json::to_vec(result).map_err(io::Error::other)?;
+        record.push(b'\n');
+        if self.gzip {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&record)?;
+            record = encoder.finish()?;
+        }
+        let len = record.len() as u64;
+
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        if current.bytes > 0 && self.must_rotate(&current, len, day) {
+            *current = self.rotate()?;
+        }
+        if current.bytes == 0 {
+            current.day = day;
+        }
+        current.file.write_all(&record)?;
+        current.bytes += len;
+        Ok(())
+    }
+
+    fn must_rotate(&self, current: &CurrentFile, len: u64, day: u64) -> bool {
+        let too_big = self.policy.max_bytes.is_some_and(|max| current.bytes + len > max);
+        let new_day = self.policy.daily && day != current.day;
+        too_big || new_day
+    }
+
+    /// Move the log to the next free rotated name and start a new one.
+    fn rotate(&self) -> io::Result<CurrentFile> {
+        let mut number = 1;
+        while rotated_path(&self.path, number).exists() {
+            number += 1;
+        }
+        fs::rename(&self.path, rotated_path(&self.path, number))?;
+        open_current(&self.path, self.gzip)
+    }
+}
+
+/// Open the log at `path` for appending, ending a cut-off last line or
+/// dropping a cut-off last gzip member.
+fn open_current(path: &Path, gzip: bool) -> io::Result<CurrentFile> {
+    let mut file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let mut bytes = metadata.len();
+    if bytes > 0 && gzip {
+        // Members appended after a cut-off one couldn't be read back
+        if let Some(complete) = complete_members_len(&file)? {
+            file.set_len(complete)?;
+            bytes = complete;
+        }
+    }
+    if bytes > 0 && !gzip {
+        let mut last = [0];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")?;
+            bytes += 1;
+        }
+    }
+    let day = metadata.modified().map_or_else(|_| current_day(), day_of);
+    Ok(CurrentFile { file, bytes, day })
+}
+
+/// The length of the complete gzip members of `file` if it ends with one cut
+/// short.
+fn complete_members_len(file: &File) -> io::Result<Option<u64>> {
+    let mut reader = BufReader::new(file);
+    let mut complete = 0;
+    while !reader.fill_buf()?.is_empty() {
+        let mut member = GzDecoder::new(reader);
+        match io::copy(&mut member, &mut io::sink()) {
+            Ok(_) => reader = member.into_inner(),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(Some(complete)),
+            // Not a cut-off member; leave the file as it is
+            Err(_) => return Ok(None),
+        }
+        complete = reader.stream_position()?;
+    }
+    Ok(None)
+}
+
+/// `path` with `.{number}` appended to its file name.
+fn rotated_path(path: &Path, number: u64) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{number}"));
+    path.with_file_name(name)
+}
+
+fn current_day() -> u64 {
+    day_of(SystemTime::now())
+}
+
+/// The UTC day of `time`, counted from the Unix epoch.
+fn day_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() / SECONDS_PER_DAY)
+}
+
+// ============================================================================
+// Reading
+// ============================================================================
+
+/// Reads the execution results of an audit log.
+#[derive(Debug, Clone, Copy)]
+pub struct AuditReader;
+
+impl AuditReader {
+    /// Iterate over the results in the log file at `path`.
+    ///
+    /// Plain and gzip-compressed files are both accepted. A line that can't
+    /// be decoded, typically the last one after a crash, is skipped with a
+    /// warning, and so is a cut-off gzip record at the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the file can't be opened.
+    pub fn iter(path: impl AsRef<Path>) -> io::Result<AuditRecords> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = BufReader::new(File::open(&path)?);
+        let source: Box<dyn BufRead + Send> = if file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(file)
+        };
+        Ok(AuditRecords { source, path, line: 0, done: false })
+    }
+
+    /// The files of the log at `path`, oldest first: the rotated files,
+    /// then `path` itself if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the log's directory can't be read.
+    pub fn files(path: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let prefix = format!("{name}.");
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut rotated = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let file_name = entry?.file_name();
+            let number = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix(&prefix))
+                .and_then(|number| number.parse::<u64>().ok());
+            if let Some(number) = number {
+                rotated.push(number);
+            }
+        }
+        rotated.sort_unstable();
+
+        let mut files: Vec<PathBuf> =
+            rotated.into_iter().map(|number| rotated_path(path, number)).collect();
+        if path.exists() {
+            files.push(path.to_path_buf());
+        }
+        Ok(files)
+    }
+}
+
+/// The results of one audit log file, from [`AuditReader::iter`].
+pub struct AuditRecords {
+    source: Box<dyn BufRead + Send>,
+    path: PathBuf,
+    /// Lines read so far, for warnings
+    line: usize,
+    done: bool,
+}
+
+impl std::fmt::Debug for AuditRecords {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditRecords")
+            .field("path", &self.path)
+            .field("line", &self.line)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Iterator for AuditRecords {
+    type Item = io::Result<OrchestratorResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let path = self.path.display();
+        let mut buffer = Vec::new();
+        while !self.done {
+            buffer.clear();
+            match self.source.read_until(b'\n', &mut buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    if buffer.trim_ascii().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_slice(&buffer) {
+                        Ok(result) => return Some(Ok(result)),
+                        Err(e) => {
+                            let line = self.line;
+                            tracing::warn!(%path, line, "skipping unreadable audit record: {e}");
+                        }
+                    }
+                }
+                // A gzip member cut short by a crash
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    tracing::warn!(%path, "skipping truncated compressed audit record");
+                    self.done = true;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory for one test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("tool-orchestrator-audit-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn result(output: &str) -> OrchestratorResult {
+        OrchestratorResult::success(output.to_string(), Vec::new(), 1)
+    }
+
+    fn outputs(path: &Path) -> Vec<String> {
+        AuditReader::iter(path).unwrap().map(|result| result.unwrap().output).collect()
+    }
+
+    fn record_len(output: &str) -> u64 {
+        serde_json::to_vec(&result(output)).unwrap().len() as u64 + 1
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let dir = test_dir("roundtrip");
+        for (name, writer) in [
+            ("plain.jsonl", AuditWriter::jsonl as fn(PathBuf, RotationPolicy) -> _),
+            ("compressed.jsonl.gz", AuditWriter::jsonl_gz),
+        ] {
+            let path = dir.join(name);
+            let audit = writer(path.clone(), RotationPolicy::new()).unwrap();
+            audit.write(&result("a")).unwrap();
+            audit.write(&result("b")).unwrap();
+            drop(audit);
+
+            // Reopening appends
+            let audit = writer(path.clone(), RotationPolicy::new()).unwrap();
+            audit.write(&result("c")).unwrap();
+            assert_eq!(outputs(&path), ["a", "b", "c"], "{name}");
+        }
+    }
+
+    #[test]
+    fn test_rotation_by_size() {
+        let dir = test_dir("size");
+        let path = dir.join("audit.jsonl");
+        // Room for exactly two records per file
+        let policy = RotationPolicy::new().with_max_bytes(2 * record_len("x"));
+        let audit = AuditWriter::jsonl(&path, policy).unwrap();
+        for output in ["1", "2", "3", "4", "5"] {
+            audit.write(&result(output)).unwrap();
+        }
+
+        let files = AuditReader::files(&path).unwrap();
+        assert_eq!(files, [dir.join("audit.jsonl.1"), dir.join("audit.jsonl.2"), path]);
+        let per_file: Vec<Vec<String>> = files.iter().map(|file| outputs(file)).collect();
+        assert_eq!(per_file, [vec!["1", "2"], vec!["3", "4"], vec!["5"]]);
+
+        // A record bigger than the limit still gets written
+        let policy = RotationPolicy::new().with_max_bytes(1);
+        let path = dir.join("tiny.jsonl");
+        let audit = AuditWriter::jsonl(&path, policy).unwrap();
+        audit.write(&result("big")).unwrap();
+        audit.write(&result("bigger")).unwrap();
+        assert_eq!(AuditReader::files(&path).unwrap().len(), 2);
+        assert_eq!(outputs(&path), ["bigger"]);
+    }
+
+    #[test]
+    fn test_rotation_by_day() {
+        let dir = test_dir("daily");
+        let path = dir.join("audit.jsonl");
+        let audit = AuditWriter::jsonl(&path, RotationPolicy::new().with_daily(true)).unwrap();
+        audit.write_on(&result("mon-1"), 100).unwrap();
+        audit.write_on(&result("mon-2"), 100).unwrap();
+        audit.write_on(&result("tue"), 101).unwrap();
+
+        let files = AuditReader::files(&path).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(outputs(&files[0]), ["mon-1", "mon-2"]);
+        assert_eq!(outputs(&files[1]), ["tue"]);
+    }
+
+    #[test]
+    fn test_truncated_final_line_is_skipped() {
+        let dir = test_dir("truncated");
+        let path = dir.join("audit.jsonl");
+        let audit = AuditWriter::jsonl(&path, RotationPolicy::new()).unwrap();
+        audit.write(&result("a")).unwrap();
+        audit.write(&result("b")).unwrap();
+        drop(audit);
+
+        // Crash halfway through a record
+        let partial = &serde_json::to_string(&result("c")).unwrap()[..20];
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(partial.as_bytes()).unwrap();
+        assert_eq!(outputs(&path), ["a", "b"]);
+
+        // The next writer starts on a new line
+        let audit = AuditWriter::jsonl(&path, RotationPolicy::new()).unwrap();
+        audit.write(&result("d")).unwrap();
+        assert_eq!(outputs(&path), ["a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_truncated_compressed_record_is_skipped() {
+        let dir = test_dir("truncated-gz");
+        let path = dir.join("audit.jsonl.gz");
+        let audit = AuditWriter::jsonl_gz(&path, RotationPolicy::new()).unwrap();
+        audit.write(&result("a")).unwrap();
+        audit.write(&result("b")).unwrap();
+        let complete = fs::metadata(&path).unwrap().len();
+        audit.write(&result("c")).unwrap();
+        drop(audit);
+
+        let bytes = fs::read(&path).unwrap();
+        let cut = usize::try_from(complete).unwrap() + 15;
+        fs::write(&path, &bytes[..cut]).unwrap();
+        assert_eq!(outputs(&path), ["a", "b"]);
+
+        // The next writer drops the cut-off member, so later records are read
+        let audit = AuditWriter::jsonl_gz(&path, RotationPolicy::new()).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), complete);
+        audit.write(&result("d")).unwrap();
+        audit.write(&result("e")).unwrap();
+        assert_eq!(outputs(&path), ["a", "b", "d", "e"]);
+    }
+}
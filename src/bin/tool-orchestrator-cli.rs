@@ -0,0 +1,721 @@
+//! Command-line runner for orchestration scripts.
+//!
+//! Runs a Rhai script against shell-command tools, so LLM-generated scripts
+//! can be debugged without writing a Rust harness:
+//!
+//! ```text
+//! tool-orchestrator-cli run script.rhai --tool upper='tr a-z A-Z' --limits quick
+//! tool-orchestrator-cli dry-run script.rhai --stub delete_user=ok --format json
+//! tool-orchestrator-cli validate script.rhai --tool upper='tr a-z A-Z'
+//! ```
+//!
+//! Each `--tool name=command` is run through the shell with the tool input as
+//! JSON on stdin. Its stdout (minus one trailing newline) is the tool result,
+//! and a non-zero exit status is reported to the script as a tool error.
+//! Stdout that isn't valid UTF-8 reaches the script unchanged as a blob and
+//! is logged base64-encoded. A
+//! command that runs longer than `--tool-timeout-ms` is killed, and one that
+//! prints more than `--tool-max-output-bytes` is killed and rejected; both
+//! are tool errors too.
+//!
+//! With `--tool-structured-output`, every tool instead returns a JSON object
+//! `{ "exit_code", "stdout", "stderr", "timed_out" }` for the script to
+//! `parse_json`, including for a command that fails or times out.
+//!
+//! Tool commands don't see the CLI's environment, which may hold credentials:
+//! only `PATH` is passed on, plus each `--tool-env NAME=VALUE`.
+//! `--inherit-env` passes the whole environment instead, minus the names
+//! matched by `--deny-env` (`AWS_SECRET_ACCESS_KEY`, or a prefix such as
+//! `AWS_*`), which no tool command ever gets. `--tool-cwd` sets the
+//! directory the commands run in.
+//!
+//! `run --test-tool NAME=INPUT` runs a tool once with `INPUT` (JSON, or else
+//! a plain string) before the script starts, with at most a 5s timeout and
+//! 64 KiB of output. A command with a typo then fails there, and the script
+//! never runs, instead of failing halfway through.
+//!
+//! `validate` checks a script without running it or any tool command: it
+//! prints the `ScriptValidation` JSON (`valid`, `errors`, `referenced_tools`,
+//! `unknown_tools`, ...) for the tools named by `--tool`.
+//!
+//! The process exits with a non-zero status when the script fails to compile,
+//! fails at runtime, or produces a result with `success: false`, and when
+//! `validate` finds the script invalid.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command as Process, ExitCode, ExitStatus, Stdio};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use tool_orchestrator::{
+    ExecutionLimits, ExecutionOptions, OrchestratorResult, ToolOrchestrator, ToolOutput,
+};
+
+fn main() -> ExitCode {
+    let matches = cli().get_matches();
+    let (subcommand, args) = matches.subcommand().expect("subcommand is required");
+    if subcommand == "validate" {
+        return validate(args);
+    }
+
+    let result = match run(subcommand, args) {
+        Ok(result) => result,
+        Err(message) => {
+            eprintln!("error: {message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.get_one::<String>("format").map(String::as_str) == Some("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&result).expect("result is serializable")
+        );
+    } else {
+        print!("{}", render_text(&result));
+    }
+
+    if result.success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Build the command-line interface.
+fn cli() -> Command {
+    let script_args = [
+        Arg::new("script")
+            .required(true)
+            .help("Path to the Rhai script, or - to read it from stdin"),
+        Arg::new("tool")
+            .long("tool")
+            .value_name("NAME=COMMAND")
+            .action(ArgAction::Append)
+            .value_parser(parse_tool)
+            .help("Register a tool backed by a shell command (input is JSON on stdin)"),
+        Arg::new("tool-timeout-ms")
+            .long("tool-timeout-ms")
+            .value_name("MS")
+            .value_parser(clap::value_parser!(u64).range(1..))
+            .default_value("30000")
+            .help("Kill a tool command that runs longer than this"),
+        Arg::new("tool-max-output-bytes")
+            .long("tool-max-output-bytes")
+            .value_name("BYTES")
+            .value_parser(clap::value_parser!(usize))
+            .default_value("1000000")
+            .help("Kill and reject a tool command that prints more than this to stdout"),
+        Arg::new("tool-structured-output")
+            .long("tool-structured-output")
+            .action(ArgAction::SetTrue)
+            .help("Return each tool command's exit code, stdout and stderr as a JSON object"),
+        Arg::new("tool-env")
+            .long("tool-env")
+            .value_name("NAME=VALUE")
+            .action(ArgAction::Append)
+            .value_parser(parse_env_var)
+            .help("Set an environment variable for tool commands"),
+        Arg::new("inherit-env")
+            .long("inherit-env")
+            .action(ArgAction::SetTrue)
+            .help("Pass this process's environment to tool commands (otherwise only PATH)"),
+        Arg::new("deny-env")
+            .long("deny-env")
+            .value_name("NAME")
+            .action(ArgAction::Append)
+            .help("Never pass this variable to tool commands; a trailing * matches a prefix"),
+        Arg::new("tool-cwd")
+            .long("tool-cwd")
+            .value_name("DIR")
+            .value_parser(clap::value_parser!(PathBuf))
+            .help("Working directory for tool commands"),
+        Arg::new("limits")
+            .long("limits")
+            .value_parser(ExecutionLimits::PROFILES)
+            .default_value("default")
+            .help("Execution limits preset"),
+        Arg::new("vars")
+            .long("vars")
+            .value_name("JSON")
+            .value_parser(parse_vars)
+            .help("JSON object whose entries are bound as script variables"),
+        Arg::new("format")
+            .long("format")
+            .value_parser(["text", "json"])
+            .default_value("text")
+            .help("Print a human summary or the full result as JSON"),
+    ];
+
+    Command::new("tool-orchestrator-cli")
+        .about("Run Rhai orchestration scripts against shell-command tools")
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("run")
+                .about("Execute a script, invoking the registered tools")
+                .args(script_args.clone())
+                .arg(
+                    Arg::new("test-tool")
+                        .long("test-tool")
+                        .value_name("NAME=INPUT")
+                        .action(ArgAction::Append)
+                        .value_parser(parse_assignment)
+                        .help("Run a tool once with this input first; stop if it fails"),
+                ),
+        )
+        .subcommand(
+            Command::new("dry-run")
+                .about("Execute a script with every tool call answered by a stub")
+                .args(script_args.clone())
+                .arg(
+                    Arg::new("stub")
+                        .long("stub")
+                        .value_name("NAME=RESPONSE")
+                        .action(ArgAction::Append)
+                        .value_parser(parse_assignment)
+                        .help("Canned response for a tool (tools without a stub return \"\")"),
+                ),
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("Check a script against the registered tools without running anything")
+                .args(
+                    script_args
+                        .into_iter()
+                        .filter(|arg| matches!(arg.get_id().as_str(), "script" | "tool")),
+                ),
+        )
+}
+
+/// Print the validation of the script described by a `validate` invocation.
+///
+/// The tools' commands are never run; they only tell the validation which
+/// names are tools.
+fn validate(args: &ArgMatches) -> ExitCode {
+    let script = match read_script(args.get_one::<String>("script").expect("script is required")) {
+        Ok(script) => script,
+        Err(message) => {
+            eprintln!("error: {message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut orchestrator = ToolOrchestrator::new();
+    for (name, _) in assignments(args, "tool") {
+        let never_run = |_| Err("validate does not run tools".to_string());
+        match name.split_once("::") {
+            Some((namespace, tool)) => orchestrator.register_namespaced(namespace, tool, never_run),
+            None => orchestrator.register_executor(name, never_run),
+        }
+    }
+
+    let validation = orchestrator.validate_script(&script);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&validation).expect("validation is serializable")
+    );
+    if validation.valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Execute the script described by a `run` or `dry-run` invocation.
+fn run(subcommand: &str, args: &ArgMatches) -> Result<OrchestratorResult, String> {
+    let script = read_script(args.get_one::<String>("script").expect("script is required"))?;
+    let mut options = ExecutionOptions::new();
+    if let Some(vars) = args.get_one::<serde_json::Map<String, serde_json::Value>>("vars") {
+        for (name, value) in vars {
+            options = options.with_variable(name.clone(), value.clone());
+        }
+    }
+
+    let limits = args
+        .get_one::<String>("limits")
+        .and_then(|name| ExecutionLimits::from_profile(name))
+        .unwrap_or_default();
+
+    let shell_limits = ShellLimits {
+        timeout: Duration::from_millis(*args.get_one::<u64>("tool-timeout-ms").expect("defaulted")),
+        max_output_bytes: *args.get_one::<usize>("tool-max-output-bytes").expect("defaulted"),
+    };
+
+    let environment = Arc::new(ToolEnvironment::from_args(args)?);
+    let structured = args.get_flag("tool-structured-output");
+
+    let tools: HashMap<String, String> = assignments(args, "tool").collect();
+    for (name, command) in &tools {
+        if command.contains("$input") {
+            eprintln!("warning: tool {name} uses $input, but tool input arrives as JSON on stdin");
+        }
+    }
+    if subcommand == "run" {
+        test_tools(args, &tools, shell_limits, &environment)?;
+    }
+
+    let mut orchestrator = ToolOrchestrator::new();
+    for (name, command) in tools {
+        let environment = Arc::clone(&environment);
+        let executor = move |input: serde_json::Value| {
+            let run = run_shell(&command, &input.to_string(), shell_limits, &environment)?;
+            if structured {
+                structured_output(&command, run, shell_limits)
+            } else {
+                tool_output(&command, run, shell_limits)
+            }
+        };
+        match name.split_once("::") {
+            Some((namespace, tool)) => {
+                orchestrator.register_namespaced_binary(namespace, tool, executor);
+            }
+            None => orchestrator.register_binary_executor(name, executor),
+        }
+    }
+
+    if subcommand == "dry-run" {
+        let stubs: HashMap<String, String> = assignments(args, "stub").collect();
+        for name in stubs.keys() {
+            if !orchestrator.registered_tools().contains(&name.as_str()) {
+                let stub = |_| Ok(String::new());
+                match name.split_once("::") {
+                    Some((namespace, tool)) => orchestrator.register_namespaced(namespace, tool, stub),
+                    None => orchestrator.register_executor(name.clone(), stub),
+                }
+            }
+        }
+        options = options.with_dry_run(stubs);
+    }
+
+    let outcome = orchestrator.execute_with_options(&script, limits, &options);
+    Ok(outcome.unwrap_or_else(|e| OrchestratorResult::error(e.to_string(), Vec::new(), 0)))
+}
+
+/// Longest a `--test-tool` run may take
+const TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most output a `--test-tool` run may print
+const TEST_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Run each `--test-tool NAME=INPUT` once, failing on the first tool whose
+/// command fails.
+fn test_tools(
+    args: &ArgMatches,
+    tools: &HashMap<String, String>,
+    limits: ShellLimits,
+    environment: &ToolEnvironment,
+) -> Result<(), String> {
+    let limits = ShellLimits {
+        timeout: limits.timeout.min(TEST_TIMEOUT),
+        max_output_bytes: limits.max_output_bytes.min(TEST_MAX_OUTPUT_BYTES),
+    };
+    for (name, input) in assignments(args, "test-tool") {
+        let command = tools
+            .get(&name)
+            .ok_or_else(|| format!("--test-tool {name}: no --tool {name}=COMMAND"))?;
+        let input = serde_json::from_str(&input).unwrap_or(serde_json::Value::String(input));
+        let run = run_shell(command, &input.to_string(), limits, environment);
+        match run.and_then(|run| tool_output(command, run, limits)) {
+            Ok(ToolOutput::Text(output)) => {
+                let first_line = output.lines().next().unwrap_or_default();
+                eprintln!("tool {name} passed its test: {first_line}");
+            }
+            Ok(ToolOutput::Bytes(output)) => {
+                eprintln!("tool {name} passed its test: {} bytes of binary output", output.len());
+            }
+            Err(e) => return Err(format!("tool {name} failed its test: {e}")),
+        }
+    }
+    Ok(())
+}
+
+/// Collect the `NAME=VALUE` pairs given for a repeatable option.
+fn assignments<'a>(
+    args: &'a ArgMatches,
+    id: &str,
+) -> impl Iterator<Item = (String, String)> + 'a {
+    args.try_get_many::<(String, String)>(id)
+        .ok()
+        .flatten()
+        .into_iter()
+        .flatten()
+        .cloned()
+}
+
+fn read_script(path: &str) -> Result<String, String> {
+    if path == "-" {
+        let mut script = String::new();
+        std::io::stdin()
+            .read_to_string(&mut script)
+            .map_err(|e| format!("failed to read script from stdin: {e}"))?;
+        Ok(script)
+    } else {
+        std::fs::read_to_string(path).map_err(|e| format!("failed to read {path}: {e}"))
+    }
+}
+
+/// Bounds on a single run of a tool command.
+#[derive(Debug, Clone, Copy)]
+struct ShellLimits {
+    timeout: Duration,
+    /// Largest stdout accepted; the command is killed once it prints more
+    max_output_bytes: usize,
+}
+
+/// Variables passed on to tool commands without `--inherit-env`, so they
+/// can find programs (and, on Windows, start at all)
+#[cfg(not(windows))]
+const PASSED_ENV_VARS: [&str; 1] = ["PATH"];
+#[cfg(windows)]
+const PASSED_ENV_VARS: [&str; 3] = ["PATH", "SystemRoot", "ComSpec"];
+
+/// The environment and working directory tool commands run in.
+#[derive(Debug, Default)]
+struct ToolEnvironment {
+    /// Set on top of whatever is passed on
+    vars: Vec<(String, String)>,
+    /// Pass on the CLI's whole environment rather than just `PASSED_ENV_VARS`
+    inherit: bool,
+    /// Names, or `PREFIX*` patterns, never passed on
+    deny: Vec<String>,
+    /// The CLI's own working directory when `None`
+    working_dir: Option<PathBuf>,
+}
+
+impl ToolEnvironment {
+    fn from_args(args: &ArgMatches) -> Result<Self, String> {
+        let environment = Self {
+            vars: args
+                .get_many::<(String, String)>("tool-env")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect(),
+            inherit: args.get_flag("inherit-env"),
+            deny: args.get_many::<String>("deny-env").into_iter().flatten().cloned().collect(),
+            working_dir: args.get_one::<PathBuf>("tool-cwd").cloned(),
+        };
+        let denied = environment.vars.iter().find(|(name, _)| environment.denies(name));
+        if let Some((name, _)) = denied {
+            return Err(format!("--tool-env {name} is denied by --deny-env"));
+        }
+        if let Some(dir) = &environment.working_dir {
+            if !dir.is_dir() {
+                return Err(format!("--tool-cwd {} is not a directory", dir.display()));
+            }
+        }
+        Ok(environment)
+    }
+
+    fn denies(&self, name: &str) -> bool {
+        self.deny.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => pattern == name,
+        })
+    }
+
+    /// Give `process` this environment in place of the CLI's.
+    fn apply(&self, process: &mut Process) {
+        process.env_clear();
+        for (name, value) in std::env::vars_os() {
+            let name_str = name.to_string_lossy();
+            let passed = self.inherit
+                || PASSED_ENV_VARS.iter().any(|kept| name_str.eq_ignore_ascii_case(kept));
+            if passed && !self.denies(&name_str) {
+                process.env(&name, value);
+            }
+        }
+        process.envs(self.vars.iter().map(|(name, value)| (name, value)));
+        if let Some(dir) = &self.working_dir {
+            process.current_dir(dir);
+        }
+    }
+}
+
+/// How long to wait for a command's output pipes to close once it has been
+/// killed or has exited
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// How a run of a tool command ended.
+struct ShellRun {
+    /// `None` when the command was killed for running too long
+    status: Option<ExitStatus>,
+    stdout: Vec<u8>,
+    /// At most one byte more than the output cap
+    stderr: Vec<u8>,
+}
+
+/// Run `command` through the shell, feeding `input` on stdin.
+///
+/// The command is killed if it outlives `limits.timeout`, keeping what it
+/// printed until then, or if it prints more than `limits.max_output_bytes`
+/// to stdout, which is an error.
+fn run_shell(
+    command: &str,
+    input: &str,
+    limits: ShellLimits,
+    environment: &ToolEnvironment,
+) -> Result<ShellRun, String> {
+    #[cfg(windows)]
+    let mut process = Process::new("cmd");
+    #[cfg(windows)]
+    process.args(["/C", command]);
+    #[cfg(not(windows))]
+    let mut process = Process::new("sh");
+    #[cfg(not(windows))]
+    process.args(["-c", command]);
+    // A process group of its own, so `kill` also reaches what the shell starts
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    environment.apply(&mut process);
+
+    let deadline = Instant::now() + limits.timeout;
+    let mut child = process
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to start `{command}`: {e}"))?;
+
+    // Feed stdin from a separate thread so a command that writes before
+    // reading cannot deadlock against us; commands that ignore stdin are fine
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_string();
+    std::thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+
+    // Read one byte past the cap, so an oversized output is detected without
+    // buffering all of it
+    let cap = u64::try_from(limits.max_output_bytes).unwrap_or(u64::MAX).saturating_add(1);
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stdout.take(cap).read_to_end(&mut buffer);
+        let _ = sender.send(buffer);
+    });
+    // Stderr past the cap is drained, so the command never blocks writing it
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let (stderr_sender, stderr_receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stderr.by_ref().take(cap).read_to_end(&mut buffer);
+        let _ = stderr_sender.send(buffer);
+        let _ = std::io::copy(&mut stderr, &mut std::io::sink());
+    });
+
+    let (stdout, mut timed_out) =
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(stdout) => (stdout, false),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                // Killing the command closes stdout, ending the read
+                kill(&mut child);
+                (receiver.recv_timeout(KILL_GRACE).unwrap_or_default(), true)
+            }
+        };
+    if stdout.len() > limits.max_output_bytes {
+        kill(&mut child);
+        return Err(format!(
+            "`{command}` printed more than {} bytes",
+            limits.max_output_bytes
+        ));
+    }
+
+    // Stdout is closed, but the command may still be running
+    let status = loop {
+        if timed_out {
+            break None;
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() >= deadline => {
+                kill(&mut child);
+                timed_out = true;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(5)),
+            Err(e) => return Err(format!("failed to run `{command}`: {e}")),
+        }
+    };
+    // A process the command left in the background may hold stderr open
+    // long after the command exits; don't wait for it
+    let stderr = stderr_receiver.recv_timeout(KILL_GRACE).unwrap_or_default();
+    Ok(ShellRun { status, stdout, stderr })
+}
+
+/// The tool result of `run`: its stdout (minus one trailing newline) if the
+/// command succeeded, and otherwise an error with its stderr.
+///
+/// Stdout that isn't valid UTF-8 is returned as bytes, unchanged.
+fn tool_output(command: &str, run: ShellRun, limits: ShellLimits) -> Result<ToolOutput, String> {
+    let Some(status) = run.status else {
+        return Err(format!("`{command}` timed out after {}ms", limits.timeout.as_millis()));
+    };
+    if status.success() {
+        match String::from_utf8(run.stdout) {
+            Ok(stdout) => Ok(ToolOutput::Text(strip_newline(&stdout).to_string())),
+            Err(e) => Ok(ToolOutput::Bytes(e.into_bytes())),
+        }
+    } else {
+        let stderr = String::from_utf8_lossy(&run.stderr);
+        let stderr = stderr.trim();
+        if stderr.is_empty() {
+            Err(format!("`{command}` exited with {status}"))
+        } else {
+            Err(stderr.to_string())
+        }
+    }
+}
+
+/// The tool result of `run` as a JSON object with its exit code (`null`
+/// when it was killed), stdout, stderr and whether it timed out, whether or
+/// not the command succeeded.
+///
+/// Stderr is held to the same cap as stdout.
+fn structured_output(
+    command: &str,
+    run: ShellRun,
+    limits: ShellLimits,
+) -> Result<ToolOutput, String> {
+    if run.stderr.len() > limits.max_output_bytes {
+        return Err(format!(
+            "`{command}` printed more than {} bytes to stderr",
+            limits.max_output_bytes
+        ));
+    }
+    let output = serde_json::json!({
+        "exit_code": run.status.and_then(|status| status.code()),
+        "stdout": strip_newline(&String::from_utf8_lossy(&run.stdout)),
+        "stderr": strip_newline(&String::from_utf8_lossy(&run.stderr)),
+        "timed_out": run.status.is_none(),
+    });
+    Ok(ToolOutput::Text(output.to_string()))
+}
+
+/// `output` without one trailing newline, as a shell's `$(...)` would drop.
+fn strip_newline(output: &str) -> &str {
+    let output = output.strip_suffix('\n').unwrap_or(output);
+    output.strip_suffix('\r').unwrap_or(output)
+}
+
+/// Kill `child` and reap it, ignoring a child that already exited.
+///
+/// On Unix the whole process group goes, since the shell may be waiting on
+/// a program it started rather than running the command itself.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    let _ = Process::new("sh")
+        .args(["-c", &format!("kill -KILL -{}", child.id())])
+        .status();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Parse a `NAME=VALUE` option, where `NAME` is a tool name optionally
+/// qualified by a namespace (`fs::read`).
+fn parse_assignment(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{s}`"))?;
+    let valid = match name.split_once("::") {
+        Some((namespace, tool)) => is_identifier(namespace) && is_identifier(tool),
+        None => is_identifier(name),
+    };
+    if !valid {
+        return Err(format!("`{name}` is not a valid tool name"));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Parse a `--tool NAME=COMMAND` option, rejecting commands that can't run.
+fn parse_tool(s: &str) -> Result<(String, String), String> {
+    let (name, command) = parse_assignment(s)?;
+    if command.trim().is_empty() {
+        return Err(format!("tool `{name}` has an empty command"));
+    }
+    if command.contains('\0') {
+        return Err(format!("tool `{name}` has a NUL byte in its command"));
+    }
+    Ok((name, command))
+}
+
+/// Parse a `--tool-env NAME=VALUE` option.
+fn parse_env_var(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{s}`"))?;
+    if name.is_empty() || name.contains('\0') || value.contains('\0') {
+        return Err(format!("`{s}` is not a valid environment variable"));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Parse `--vars`, which must be a JSON object keyed by identifiers.
+fn parse_vars(s: &str) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(s).map_err(|e| format!("invalid JSON: {e}"))?;
+    let serde_json::Value::Object(vars) = value else {
+        return Err("expected a JSON object".to_string());
+    };
+    if let Some(name) = vars.keys().find(|name| !is_identifier(name)) {
+        return Err(format!("`{name}` is not a valid variable name"));
+    }
+    Ok(vars)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Human-readable summary of a result.
+fn render_text(result: &OrchestratorResult) -> String {
+    let mut out = String::new();
+    let status = match (result.success, result.dry_run) {
+        (true, false) => "success",
+        (true, true) => "success (dry run)",
+        (false, _) => "failed",
+    };
+    out.push_str(&format!("status: {status}\n"));
+    if let Some(error) = &result.error {
+        out.push_str(&format!("error: {error}\n"));
+    }
+    if !result.output.is_empty() {
+        out.push_str(&format!("output: {}\n", result.output));
+    }
+    out.push_str(&format!("tool calls: {}\n", result.tool_calls.len()));
+    for call in &result.tool_calls {
+        out.push_str(&format!(
+            "  {}({}) {} in {}ms\n",
+            call.tool_name,
+            call.input,
+            call.status.as_str(),
+            call.duration_ms
+        ));
+    }
+    let timing = &result.timing;
+    out.push_str(&format!(
+        "time: {}ms (tools {}ms, script {}ms, compile {}ms)\n",
+        timing.total_ms, timing.tool_time_ms, timing.script_time_ms, timing.compile_ms
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        cli().debug_assert();
+    }
+}
@@ -0,0 +1,298 @@
+//! Date and time functions for scripts (`datetime` feature).
+//!
+//! Registers `now_ms`, `parse_datetime`, `format_datetime`,
+//! `duration_between`, `add_days` and `add_hours`, so scripts can compare
+//! timestamps from tool output, compute durations and format dates.
+//!
+//! An instant is passed around as one of:
+//!
+//! - milliseconds since the Unix epoch (an integer, always UTC)
+//! - an RFC 3339 / ISO 8601 string such as `2024-03-10T12:00:00+02:00`; a
+//!   date or time without an offset is taken as UTC
+//! - a map from `parse_datetime`, whose `ms` and `offset_minutes` are used
+//!
+//! `add_days` and `add_hours` return the same kind they were given, keeping
+//! the offset. Malformed input raises a Rhai runtime error the script can
+//! `catch`. `now_ms` raises one in deterministic mode, since a script that
+//! reads the clock cannot produce the same result twice.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let order = parse_json(get_order(42));
+//! let age = duration_between(order.created_at, now_ms());
+//! let due = add_days(order.created_at, 14);
+//! `${age / 3_600_000}h old, due ${format_datetime(due, "%Y-%m-%d")}`
+//! ```
+
+use std::fmt::Write;
+
+#[cfg(feature = "native")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta,
+    Timelike,
+};
+use rhai::{Dynamic, EvalAltResult, FuncRegistration, ImmutableString, Map, Module, INT};
+
+/// Names of the functions registered by [`datetime_module`]
+pub(crate) const FUNCTION_NAMES: [&str; 6] =
+    ["now_ms", "parse_datetime", "format_datetime", "duration_between", "add_days", "add_hours"];
+
+/// Accepted forms without an offset, taken as UTC
+const NAIVE_FORMATS: [&str; 4] =
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"];
+
+type DateTimeResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Parse an RFC 3339 / ISO 8601 string.
+fn parse(text: &str) -> DateTimeResult<DateTime<FixedOffset>> {
+    let text = text.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+        return Ok(datetime);
+    }
+    // ISO 8601 also allows offsets without a colon
+    for format in ["%Y-%m-%dT%H:%M:%S%.f%z", "%Y-%m-%d %H:%M:%S%.f%z"] {
+        if let Ok(datetime) = DateTime::parse_from_str(text, format) {
+            return Ok(datetime);
+        }
+    }
+    let naive = NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0));
+    naive.map(|naive| naive.and_utc().fixed_offset()).ok_or_else(|| {
+        format!("invalid datetime `{text}`: expected RFC 3339, e.g. 2024-03-10T12:00:00Z").into()
+    })
+}
+
+/// The instant a script value stands for.
+fn to_datetime(value: &Dynamic) -> DateTimeResult<DateTime<FixedOffset>> {
+    if let Some(ms) = value.clone().try_cast::<INT>() {
+        return from_ms(ms, 0);
+    }
+    if let Some(text) = value.read_lock::<ImmutableString>() {
+        return parse(&text);
+    }
+    if let Some(map) = value.read_lock::<Map>() {
+        let field = |name: &str| map.get(name).and_then(|v| v.as_int().ok());
+        let ms = field("ms").ok_or("datetime map has no integer `ms` field")?;
+        return from_ms(ms, field("offset_minutes").unwrap_or(0));
+    }
+    Err(format!(
+        "expected a datetime as milliseconds, a string or a map, got {}",
+        value.type_name()
+    )
+    .into())
+}
+
+fn from_ms(ms: INT, offset_minutes: INT) -> DateTimeResult<DateTime<FixedOffset>> {
+    let offset = i32::try_from(offset_minutes)
+        .ok()
+        .and_then(|minutes| minutes.checked_mul(60))
+        .and_then(FixedOffset::east_opt)
+        .ok_or_else(|| format!("invalid offset of {offset_minutes} minutes"))?;
+    let utc = DateTime::from_timestamp_millis(ms).ok_or_else(|| format!("{ms}ms is out of range"))?;
+    Ok(utc.with_timezone(&offset))
+}
+
+/// The map `parse_datetime` returns, in the instant's own offset.
+fn to_map(datetime: &DateTime<FixedOffset>) -> Map {
+    let fields = [
+        ("ms", datetime.timestamp_millis()),
+        ("year", INT::from(datetime.year())),
+        ("month", INT::from(datetime.month())),
+        ("day", INT::from(datetime.day())),
+        ("hour", INT::from(datetime.hour())),
+        ("minute", INT::from(datetime.minute())),
+        ("second", INT::from(datetime.second())),
+        ("millisecond", INT::from(datetime.timestamp_subsec_millis())),
+        ("weekday", INT::from(datetime.weekday().number_from_monday())),
+        ("offset_minutes", INT::from(datetime.offset().local_minus_utc() / 60)),
+    ];
+    let mut map: Map =
+        fields.into_iter().map(|(name, value)| (name.into(), value.into())).collect();
+    map.insert("iso".into(), to_rfc3339(datetime).into());
+    map
+}
+
+fn to_rfc3339(datetime: &DateTime<FixedOffset>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Format with a `strftime`-style pattern, rejecting unknown specifiers.
+fn format(datetime: &DateTime<FixedOffset>, pattern: &str) -> DateTimeResult<String> {
+    let items: Vec<Item<'_>> = StrftimeItems::new(pattern).collect();
+    let mut out = String::new();
+    if items.contains(&Item::Error)
+        || write!(out, "{}", datetime.format_with_items(items.into_iter())).is_err()
+    {
+        return Err(format!("invalid datetime format `{pattern}`").into());
+    }
+    Ok(out)
+}
+
+/// `value` moved by `delta`, as the same kind of value.
+fn shift(value: &Dynamic, delta: Option<TimeDelta>) -> DateTimeResult<Dynamic> {
+    let datetime = to_datetime(value)?;
+    let shifted = delta
+        .and_then(|delta| datetime.checked_add_signed(delta))
+        .ok_or("datetime out of range")?;
+    Ok(if value.is_int() {
+        shifted.timestamp_millis().into()
+    } else if value.is_string() {
+        to_rfc3339(&shifted).into()
+    } else {
+        to_map(&shifted).into()
+    })
+}
+
+/// Build a module with the datetime functions.
+///
+/// Registered on the engine of every execution; in `deterministic` mode
+/// `now_ms` fails instead of reading the clock.
+pub(crate) fn datetime_module(deterministic: bool) -> Module {
+    let mut module = Module::new();
+
+    FuncRegistration::new("now_ms").set_into_module(&mut module, move || -> DateTimeResult<INT> {
+        if deterministic {
+            return Err("now_ms() is disabled in deterministic mode".into());
+        }
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        Ok(INT::try_from(since_epoch.as_millis()).unwrap_or(INT::MAX))
+    });
+
+    FuncRegistration::new("parse_datetime").set_into_module(
+        &mut module,
+        |text: ImmutableString| -> DateTimeResult<Map> { Ok(to_map(&parse(&text)?)) },
+    );
+
+    FuncRegistration::new("format_datetime").set_into_module(
+        &mut module,
+        |value: Dynamic| -> DateTimeResult<String> { Ok(to_rfc3339(&to_datetime(&value)?)) },
+    );
+    FuncRegistration::new("format_datetime").set_into_module(
+        &mut module,
+        |value: Dynamic, pattern: ImmutableString| -> DateTimeResult<String> {
+            format(&to_datetime(&value)?, &pattern)
+        },
+    );
+
+    FuncRegistration::new("duration_between").set_into_module(
+        &mut module,
+        |from: Dynamic, to: Dynamic| -> DateTimeResult<INT> {
+            let (from, to) = (to_datetime(&from)?, to_datetime(&to)?);
+            Ok(to.signed_duration_since(from).num_milliseconds())
+        },
+    );
+
+    FuncRegistration::new("add_days").set_into_module(
+        &mut module,
+        |value: Dynamic, days: INT| shift(&value, TimeDelta::try_days(days)),
+    );
+    FuncRegistration::new("add_hours").set_into_module(
+        &mut module,
+        |value: Dynamic, hours: INT| shift(&value, TimeDelta::try_hours(hours)),
+    );
+
+    module
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use crate::{ExecutionLimits, ToolOrchestrator};
+
+    fn run(script: &str) -> String {
+        run_with(script, ExecutionLimits::default())
+    }
+
+    fn run_with(script: &str, limits: ExecutionLimits) -> String {
+        ToolOrchestrator::new().execute(script, limits).unwrap().output
+    }
+
+    /// The message of the error `call` raises, caught inside the script.
+    fn caught(call: &str) -> String {
+        run(&format!(r#"let out = "no error"; try {{ {call}; }} catch (e) {{ out = e; }} out"#))
+    }
+
+    #[test]
+    fn test_parse_keeps_the_offset() {
+        let output = run(
+            r#"
+            let d = parse_datetime("2024-03-10T23:30:15.250+02:00");
+            [d.ms, d.year, d.month, d.day, d.hour, d.minute, d.second, d.millisecond,
+             d.weekday, d.offset_minutes, d.iso]
+            "#,
+        );
+        assert_eq!(
+            output,
+            r#"[1710106215250,2024,3,10,23,30,15,250,7,120,"2024-03-10T23:30:15.250+02:00"]"#
+        );
+        // The same instant, whatever the offset or notation
+        assert_eq!(run(r#"parse_datetime("2024-03-10T21:30:15.25Z").ms"#), "1710106215250");
+        assert_eq!(run(r#"parse_datetime("2024-03-10 16:30:15.25-0500").ms"#), "1710106215250");
+        assert_eq!(run(r#"parse_datetime("2024-03-10").iso"#), "2024-03-10T00:00:00Z");
+        assert_eq!(run(r#"parse_datetime("2024-03-10T08:15").hour"#), "8");
+    }
+
+    #[test]
+    fn test_format_in_the_instants_offset() {
+        assert_eq!(run("format_datetime(0)"), "1970-01-01T00:00:00Z");
+        assert_eq!(run(r#"format_datetime(86_400_000, "%Y-%m-%d %H:%M")"#), "1970-01-02 00:00");
+        let output = run(
+            r#"
+            let d = parse_datetime("2024-03-10T23:30:00-08:00");
+            [format_datetime(d, "%d %b %H:%M %z"), format_datetime(d.ms, "%d %b %H:%M"),
+             format_datetime("2024-03-10T23:30:00-08:00")]
+            "#,
+        );
+        assert_eq!(output, r#"["10 Mar 23:30 -0800","11 Mar 07:30","2024-03-10T23:30:00-08:00"]"#);
+    }
+
+    #[test]
+    fn test_durations_and_arithmetic() {
+        let output = run(
+            r#"
+            [duration_between("2024-03-10T12:00:00+01:00", "2024-03-10T12:00:00Z"),
+             duration_between(1000, 400),
+             add_hours("2024-02-28T22:00:00+05:30", 26),
+             add_days(0, -1),
+             add_days(parse_datetime("2024-03-10T10:00:00+02:00"), 1).iso]
+            "#,
+        );
+        assert_eq!(
+            output,
+            r#"[3600000,-600,"2024-03-01T00:00:00+05:30",-86400000,"2024-03-11T10:00:00+02:00"]"#
+        );
+    }
+
+    #[test]
+    fn test_bad_input_is_catchable() {
+        assert!(caught(r#"parse_datetime("next tuesday")"#)
+            .starts_with("invalid datetime `next tuesday`"));
+        assert!(caught(r#"parse_datetime("2024-02-30")"#).starts_with("invalid datetime"));
+        assert_eq!(caught(r#"format_datetime(0, "%Q")"#), "invalid datetime format `%Q`");
+        let no_ms = caught("add_days(#{ year: 2024 }, 1)");
+        assert_eq!(no_ms, "datetime map has no integer `ms` field");
+        assert!(caught("add_days([], 1)").starts_with("expected a datetime"));
+        assert_eq!(caught("add_days(0, 9_223_372_036_854_775_807)"), "datetime out of range");
+    }
+
+    #[test]
+    fn test_now_ms_is_disabled_in_deterministic_mode() {
+        let now: i64 = run("now_ms()").parse().unwrap();
+        assert!(now > 1_700_000_000_000, "{now}");
+
+        let limits = ExecutionLimits::default().with_deterministic(true);
+        let script = r#"let out = ""; try { now_ms(); } catch (e) { out = e; } out"#;
+        assert_eq!(run_with(script, limits), "now_ms() is disabled in deterministic mode");
+        // Everything else works without the clock
+        assert_eq!(run_with(r#"parse_datetime("2024-03-10").weekday"#, limits), "7");
+        assert!(ToolOrchestrator::new().validate_script("now_ms() + add_days(0, 1)").valid);
+    }
+}
@@ -0,0 +1,398 @@
+//! Structured comparison of two execution results.
+//!
+//! Intended for regression testing orchestrations against golden
+//! [`OrchestratorResult`] files: [`OrchestratorResult::diff`] reports exactly
+//! what changed between two runs, such as a different output line, an extra
+//! tool call or a changed input to call #3.
+//!
+//! Durations are ignored unless [`DiffOptions::include_timing`] is set, and
+//! execution ids are never compared, since both differ on every run.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let golden: OrchestratorResult = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+//! let result = orchestrator.execute(script, limits)?;
+//!
+//! let diff = golden.diff(&result);
+//! assert!(diff.is_empty(), "orchestration changed:\n{diff}");
+//! ```
+
+use std::fmt;
+
+use crate::types::{OrchestratorResult, ToolCall};
+
+/// Options for [`OrchestratorResult::diff_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiffOptions {
+    /// Also compare `execution_time_ms` and each call's `duration_ms`
+    pub include_timing: bool,
+}
+
+impl DiffOptions {
+    /// Create options with every setting at its default.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            include_timing: false,
+        }
+    }
+
+    /// Compare timing fields too (builder pattern).
+    #[must_use]
+    pub const fn with_include_timing(mut self, include: bool) -> Self {
+        self.include_timing = include;
+        self
+    }
+}
+
+/// One line of a line-level output diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// Present in both outputs
+    Same(String),
+    /// Only in the first (expected) output
+    Removed(String),
+    /// Only in the second (actual) output
+    Added(String),
+}
+
+/// A field whose value differs between two results or tool calls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// Name of the field, e.g. `"input"`
+    pub field: &'static str,
+    /// Value in the first (expected) result
+    pub before: serde_json::Value,
+    /// Value in the second (actual) result
+    pub after: serde_json::Value,
+}
+
+/// How the tool call at one index differs between two results.
+#[derive(Debug, Clone)]
+pub enum ToolCallDiff {
+    /// Both results have a call at `index`, but some fields differ
+    Changed {
+        /// Position in the call log
+        index: usize,
+        /// Differing fields, in declaration order
+        changes: Vec<FieldChange>,
+    },
+    /// Only the second result has a call at `index`
+    Added {
+        /// Position in the call log
+        index: usize,
+        /// The extra call
+        call: ToolCall,
+    },
+    /// Only the first result has a call at `index`
+    Removed {
+        /// Position in the call log
+        index: usize,
+        /// The missing call
+        call: ToolCall,
+    },
+}
+
+/// Differences between two [`OrchestratorResult`]s.
+///
+/// Produced by [`OrchestratorResult::diff`]; empty when the results are
+/// equivalent. `Display` renders a readable report.
+#[derive(Debug, Clone, Default)]
+pub struct ResultDiff {
+    /// Top-level fields that differ, other than the output and call log
+    pub fields: Vec<FieldChange>,
+    /// Line-level diff of the outputs; empty if they are identical
+    pub output: Vec<DiffLine>,
+    /// Per-index differences between the call logs
+    pub tool_calls: Vec<ToolCallDiff>,
+}
+
+impl ResultDiff {
+    /// Whether the two results are equivalent.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.output.is_empty() && self.tool_calls.is_empty()
+    }
+}
+
+impl OrchestratorResult {
+    /// Compare this (expected) result with `other` (actual), ignoring timing.
+    ///
+    /// Equivalent to [`diff_with_options`](Self::diff_with_options) with
+    /// default [`DiffOptions`].
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> ResultDiff {
+        self.diff_with_options(other, DiffOptions::default())
+    }
+
+    /// Compare this (expected) result with `other` (actual).
+    ///
+    /// Tool calls are compared by position, so a reordered call log shows
+    /// up as changes at each moved index. Execution ids are never compared.
+    #[must_use]
+    pub fn diff_with_options(&self, other: &Self, options: DiffOptions) -> ResultDiff {
+        let mut fields = Vec::new();
+        compare(&mut fields, "success", &self.success, &other.success);
+        compare(&mut fields, "error", &self.error, &other.error);
+        compare(&mut fields, "dry_run", &self.dry_run, &other.dry_run);
+        compare(&mut fields, "total_cost", &self.total_cost, &other.total_cost);
+        compare(&mut fields, "outputs", &self.outputs, &other.outputs);
+        if options.include_timing {
+            compare(
+                &mut fields,
+                "execution_time_ms",
+                &self.execution_time_ms,
+                &other.execution_time_ms,
+            );
+        }
+
+        let output = if self.output == other.output {
+            Vec::new()
+        } else {
+            diff_lines(&self.output, &other.output)
+        };
+
+        let count = self.tool_calls.len().max(other.tool_calls.len());
+        let tool_calls = (0..count)
+            .filter_map(|index| {
+                match (self.tool_calls.get(index), other.tool_calls.get(index)) {
+                    (Some(before), Some(after)) => {
+                        let changes = diff_calls(before, after, options);
+                        (!changes.is_empty()).then_some(ToolCallDiff::Changed { index, changes })
+                    }
+                    (None, Some(call)) => Some(ToolCallDiff::Added { index, call: call.clone() }),
+                    (Some(call), None) => Some(ToolCallDiff::Removed { index, call: call.clone() }),
+                    (None, None) => None,
+                }
+            })
+            .collect();
+
+        ResultDiff { fields, output, tool_calls }
+    }
+
+    /// Whether `other` is equivalent to this result, ignoring durations and
+    /// execution ids.
+    #[must_use]
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        self.diff(other).is_empty()
+    }
+}
+
+/// Record a [`FieldChange`] if `before` and `after` differ.
+fn compare<T: PartialEq + serde::Serialize>(
+    changes: &mut Vec<FieldChange>,
+    field: &'static str,
+    before: &T,
+    after: &T,
+) {
+    if before != after {
+        changes.push(FieldChange {
+            field,
+            before: serde_json::to_value(before).unwrap_or_default(),
+            after: serde_json::to_value(after).unwrap_or_default(),
+        });
+    }
+}
+
+fn diff_calls(before: &ToolCall, after: &ToolCall, options: DiffOptions) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    compare(&mut changes, "tool_name", &before.tool_name, &after.tool_name);
+    compare(&mut changes, "input", &before.input, &after.input);
+    compare(&mut changes, "output", &before.output, &after.output);
+    compare(&mut changes, "success", &before.success, &after.success);
+    compare(&mut changes, "status", &before.status, &after.status);
+    compare(&mut changes, "refused", &before.refused, &after.refused);
+    compare(&mut changes, "cached", &before.cached, &after.cached);
+    compare(&mut changes, "cost", &before.cost, &after.cost);
+    if options.include_timing {
+        compare(&mut changes, "duration_ms", &before.duration_ms, &after.duration_ms);
+    }
+    changes
+}
+
+/// Line-level diff of `before` and `after` via their longest common subsequence.
+fn diff_lines(before: &str, after: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+
+    // Only the middle between the common prefix and suffix needs the LCS table
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    // lcs[i][j] = length of the LCS of mid_a[i..] and mid_b[j..]
+    let mut lcs = vec![vec![0_usize; mid_b.len() + 1]; mid_a.len() + 1];
+    for i in (0..mid_a.len()).rev() {
+        for j in (0..mid_b.len()).rev() {
+            lcs[i][j] = if mid_a[i] == mid_b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<DiffLine> =
+        a[..prefix].iter().map(|line| DiffLine::Same((*line).to_string())).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < mid_a.len() || j < mid_b.len() {
+        if i < mid_a.len() && j < mid_b.len() && mid_a[i] == mid_b[j] {
+            lines.push(DiffLine::Same(mid_a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j < mid_b.len() && (i == mid_a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            lines.push(DiffLine::Added(mid_b[j].to_string()));
+            j += 1;
+        } else {
+            lines.push(DiffLine::Removed(mid_a[i].to_string()));
+            i += 1;
+        }
+    }
+    lines.extend(a[a.len() - suffix..].iter().map(|line| DiffLine::Same((*line).to_string())));
+    lines
+}
+
+impl fmt::Display for ResultDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        let mut lines = Vec::new();
+        for change in &self.fields {
+            lines.push(format!("{}: {} -> {}", change.field, change.before, change.after));
+        }
+        if !self.output.is_empty() {
+            lines.push("output:".to_string());
+            for line in &self.output {
+                lines.push(match line {
+                    DiffLine::Same(text) => format!("    {text}"),
+                    DiffLine::Removed(text) => format!("  - {text}"),
+                    DiffLine::Added(text) => format!("  + {text}"),
+                });
+            }
+        }
+        for diff in &self.tool_calls {
+            match diff {
+                ToolCallDiff::Changed { index, changes } => {
+                    lines.push(format!("tool call #{index} changed:"));
+                    for change in changes {
+                        lines.push(format!(
+                            "  {}: {} -> {}",
+                            change.field, change.before, change.after
+                        ));
+                    }
+                }
+                ToolCallDiff::Added { index, call } => {
+                    lines.push(format!("tool call #{index} added: {}", describe_call(call)));
+                }
+                ToolCallDiff::Removed { index, call } => {
+                    lines.push(format!("tool call #{index} removed: {}", describe_call(call)));
+                }
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// One-line summary of a call, e.g. `fetch("x") -> ok`.
+fn describe_call(call: &ToolCall) -> String {
+    let status = if call.success { "ok" } else { "failed" };
+    format!("{}({}) -> {status}", call.tool_name, call.input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, input: serde_json::Value, output: &str, success: bool) -> ToolCall {
+        ToolCall::new(name.to_string(), input, output.to_string(), success, 5)
+    }
+
+    fn golden() -> OrchestratorResult {
+        let calls = vec![
+            call("fetch", serde_json::json!("users"), "[1,2]", true),
+            call("count", serde_json::json!([1, 2]), "2", true),
+        ];
+        let mut result = OrchestratorResult::success("Users: 2\nDone".to_string(), calls, 40);
+        result.execution_id = "golden".to_string();
+        result
+    }
+
+    #[test]
+    fn test_identical_results_ignore_timing_and_ids() {
+        let mut other = golden();
+        other.execution_time_ms = 999;
+        other.tool_calls[0].duration_ms = 123;
+        other.execution_id = "another-run".to_string();
+
+        assert!(golden().is_equivalent(&other));
+        assert_eq!(golden().diff(&other).to_string(), "no differences");
+
+        let options = DiffOptions::new().with_include_timing(true);
+        let timed = golden().diff_with_options(&other, options);
+        assert_eq!(timed.fields[0].field, "execution_time_ms");
+        assert!(matches!(
+            &timed.tool_calls[..],
+            [ToolCallDiff::Changed { index: 0, changes }] if changes[0].field == "duration_ms"
+        ));
+    }
+
+    #[test]
+    fn test_reordered_calls_and_changed_output() {
+        let mut other = golden();
+        other.tool_calls.swap(0, 1);
+        other.output = "Users: 3\nDone".to_string();
+
+        let diff = golden().diff(&other);
+        assert!(!golden().is_equivalent(&other));
+        assert_eq!(
+            diff.output,
+            [
+                DiffLine::Added("Users: 3".to_string()),
+                DiffLine::Removed("Users: 2".to_string()),
+                DiffLine::Same("Done".to_string()),
+            ]
+        );
+        assert_eq!(diff.tool_calls.len(), 2);
+        let ToolCallDiff::Changed { index: 0, changes } = &diff.tool_calls[0] else {
+            panic!("expected call #0 to change: {diff:?}");
+        };
+        let fields: Vec<&str> = changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, ["tool_name", "input", "output"]);
+
+        let report = diff.to_string();
+        assert!(report.contains("  + Users: 3\n  - Users: 2\n    Done"), "{report}");
+        assert!(report.contains("tool call #1 changed:\n  tool_name: \"count\" -> \"fetch\""));
+    }
+
+    #[test]
+    fn test_added_failing_call() {
+        let mut other = golden();
+        other.tool_calls.push(call("notify", serde_json::json!({"to": "ops"}), "boom", false));
+        other.success = false;
+        other.error = Some("notify failed".to_string());
+
+        let diff = golden().diff(&other);
+        let fields: Vec<&str> = diff.fields.iter().map(|c| c.field).collect();
+        assert_eq!(fields, ["success", "error"]);
+        assert!(diff.output.is_empty());
+        assert!(matches!(
+            &diff.tool_calls[..],
+            [ToolCallDiff::Added { index: 2, call }] if !call.success
+        ));
+        let report = diff.to_string();
+        assert!(report.contains(r#"tool call #2 added: notify({"to":"ops"}) -> failed"#));
+
+        // The other direction reports the call as removed
+        assert!(matches!(
+            &other.diff(&golden()).tool_calls[..],
+            [ToolCallDiff::Removed { index: 2, .. }]
+        ));
+    }
+}
@@ -0,0 +1,853 @@
+//! C ABI (`ffi` feature).
+//!
+//! Exposes the orchestrator as `extern "C"` functions so hosts written in C,
+//! C++ or anything else with a C FFI can run scripts under the same sandbox.
+//! The declarations are in `include/tool_orchestrator_ffi.h`, generated with
+//! [cbindgen](https://github.com/mozilla/cbindgen) from this module, and a
+//! test checks it is up to date whenever `cbindgen` is on the `PATH`:
+//!
+//! ```bash
+//! cbindgen --config cbindgen.toml --output include/tool_orchestrator_ffi.h src/ffi.rs
+//! ```
+//!
+//! ```c
+//! static int greet(const char *json_input, char **out, char **err) {
+//!     *out = strdup("\"Hello!\"");
+//!     return 0;
+//! }
+//!
+//! ToOrchestrator *orchestrator = to_orchestrator_new();
+//! to_orchestrator_register_tool(orchestrator, "greet", greet);
+//!
+//! ToLimits limits = { .struct_size = sizeof(ToLimits) };
+//! to_limits_default(&limits);
+//! limits.max_tool_calls = 5;
+//! ToResult *result = NULL;
+//! if (to_orchestrator_execute(orchestrator, "greet(\"Claude\")", &limits, &result) == TO_OK) {
+//!     char *json = to_result_json(result);
+//!     puts(json);
+//!     to_free(json);
+//!     to_result_free(result);
+//! }
+//! to_orchestrator_free(orchestrator);
+//! ```
+//!
+//! All strings are NUL-terminated UTF-8. Strings returned by these functions
+//! belong to the caller and are released with [`to_free`]; strings a tool
+//! callback hands back through `out` or `err` must be allocated with
+//! `malloc` (e.g. `strdup`) and are freed by the orchestrator.
+//!
+//! Functions returning `int` return [`TO_OK`] or one of the `TO_ERROR_*`
+//! codes; functions returning a pointer return `NULL` on failure. Either way
+//! [`to_last_error`] then describes what went wrong. Panics never cross the
+//! boundary: they are caught and reported as [`TO_ERROR_PANIC`]. A script
+//! that fails, e.g. on a compile error or a limit, is not an FFI error: the
+//! result reports it, as with [`ToolOrchestrator::execute`].
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::engine::{panic_message, ToolOrchestrator};
+use crate::sandbox::{ExecutionLimits, ProfileRegistry};
+use crate::types::OrchestratorResult;
+
+// ============================================================================
+// Error codes
+// ============================================================================
+
+/// The call succeeded.
+pub const TO_OK: c_int = 0;
+/// A required pointer argument was `NULL`.
+pub const TO_ERROR_NULL_POINTER: c_int = 1;
+/// A string argument was not valid UTF-8.
+pub const TO_ERROR_INVALID_UTF8: c_int = 2;
+/// An argument was rejected, e.g. an unknown limits profile.
+pub const TO_ERROR_INVALID_ARGUMENT: c_int = 3;
+/// The orchestrator panicked; the panic was caught at the boundary.
+pub const TO_ERROR_PANIC: c_int = 4;
+
+/// A failed FFI call: the code to return and the message for [`to_last_error`].
+struct FfiError {
+    code: c_int,
+    message: String,
+}
+
+impl FfiError {
+    fn new(code: c_int, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+thread_local! {
+    /// Message of the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run `body`, catching panics and recording the message of any failure
+/// for [`to_last_error`].
+fn guard<T>(body: impl FnOnce() -> Result<T, FfiError>) -> Result<T, c_int> {
+    let outcome = panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = format!("orchestrator panicked: {}", panic_message(payload.as_ref()));
+        Err(FfiError::new(TO_ERROR_PANIC, message))
+    });
+    outcome.map_err(|e| {
+        LAST_ERROR.with(|last| *last.borrow_mut() = Some(e.message));
+        e.code
+    })
+}
+
+/// [`guard`] for functions returning a status code.
+fn guard_status(body: impl FnOnce() -> Result<(), FfiError>) -> c_int {
+    guard(body).err().unwrap_or(TO_OK)
+}
+
+/// [`guard`] for functions returning a pointer, `NULL` on failure.
+fn guard_ptr<T>(body: impl FnOnce() -> Result<*mut T, FfiError>) -> *mut T {
+    guard(body).unwrap_or(ptr::null_mut())
+}
+
+/// Borrow a string argument.
+///
+/// # Safety
+///
+/// `ptr` must be `NULL` or a NUL-terminated string that outlives `'a`.
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if ptr.is_null() {
+        return Err(FfiError::new(TO_ERROR_NULL_POINTER, format!("`{name}` is NULL")));
+    }
+    // SAFETY: non-NULL and NUL-terminated per the caller's contract
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|e| FfiError::new(TO_ERROR_INVALID_UTF8, format!("`{name}` is not UTF-8: {e}")))
+}
+
+/// Borrow a pointer argument.
+///
+/// # Safety
+///
+/// `ptr` must be `NULL` or valid for reads for `'a`.
+unsafe fn ref_arg<'a, T>(ptr: *const T, name: &str) -> Result<&'a T, FfiError> {
+    // SAFETY: valid when non-NULL per the caller's contract
+    unsafe { ptr.as_ref() }
+        .ok_or_else(|| FfiError::new(TO_ERROR_NULL_POINTER, format!("`{name}` is NULL")))
+}
+
+/// Hand a string to the caller, to be released with [`to_free`].
+fn c_string(value: impl Into<Vec<u8>>) -> Result<*mut c_char, FfiError> {
+    CString::new(value).map(CString::into_raw).map_err(|e| {
+        let message = format!("string contains a NUL byte at {}", e.nul_position());
+        FfiError::new(TO_ERROR_INVALID_ARGUMENT, message)
+    })
+}
+
+// ============================================================================
+// Execution Limits
+// ============================================================================
+
+/// Execution limits passed to [`to_orchestrator_execute`].
+///
+/// Covers the numeric limits of [`ExecutionLimits`]; the rest keep their
+/// defaults. Start from [`to_limits_default`] or [`to_limits_from_profile`]
+/// and adjust the fields you need.
+///
+/// `struct_size` versions the layout. Callers set it to `sizeof(ToLimits)`
+/// before handing the struct to any function; new fields are only ever
+/// appended, so a caller built against an older header keeps working and the
+/// fields it doesn't know about keep their defaults.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ToLimits {
+    /// `sizeof(ToLimits)` as the caller was compiled
+    pub struct_size: usize,
+    /// Maximum number of operations (prevents infinite loops)
+    pub max_operations: u64,
+    /// Maximum number of tool calls allowed
+    pub max_tool_calls: usize,
+    /// Timeout in milliseconds
+    pub timeout_ms: u64,
+    /// Maximum string size in bytes
+    pub max_string_size: usize,
+    /// Maximum array size
+    pub max_array_size: usize,
+    /// Maximum map size
+    pub max_map_size: usize,
+    /// Maximum bytes a single tool call may return
+    pub max_tool_output_bytes: usize,
+    /// Maximum size of a single tool call's input, serialized as JSON
+    pub max_tool_input_bytes: usize,
+    /// Maximum cumulative bytes returned by all tool calls in one execution
+    pub max_total_tool_output_bytes: usize,
+    /// Maximum size of the script's final output in bytes
+    pub max_output_bytes: usize,
+    /// Maximum summed cost of the tool calls in one execution (negative for
+    /// no budget)
+    pub max_total_cost: f64,
+    /// Produce reproducible results (timing fields are reported as zero)
+    pub deterministic: bool,
+    /// Maximum script source size in bytes, checked before compilation
+    pub max_script_bytes: usize,
+    /// Maximum number of lines in the script source, checked before compilation
+    pub max_script_lines: usize,
+    /// Maximum number of functions the script may define
+    pub max_functions: usize,
+    /// Maximum number of statements in the compiled script, including function bodies
+    pub max_statements: usize,
+    /// Refuse to start a tool call with less than this much of `timeout_ms` left (0 to disable)
+    pub min_remaining_ms_for_tool_call: u64,
+    /// Maximum number of distinct names the script may `emit`
+    pub max_outputs: usize,
+    /// Maximum serialized size of all emitted outputs in bytes, names included
+    pub max_outputs_bytes: usize,
+    /// Maximum number of keys in the key-value store scripts write with `kv_set`
+    pub max_kv_entries: usize,
+    /// Maximum serialized size of the key-value store in bytes, keys included
+    pub max_kv_bytes: usize,
+    /// Maximum bytes of state the orchestrator holds for one execution
+    pub max_total_state_bytes: usize,
+    /// Maximum number of tool calls running at once on their own threads
+    pub max_concurrent_tool_calls: usize,
+    /// Operations charged against `max_operations` for each counted tool call
+    pub tool_call_operation_cost: u64,
+}
+
+impl From<ExecutionLimits> for ToLimits {
+    fn from(limits: ExecutionLimits) -> Self {
+        Self {
+            struct_size: size_of::<Self>(),
+            max_operations: limits.max_operations,
+            max_tool_calls: limits.max_tool_calls,
+            timeout_ms: limits.timeout_ms,
+            max_string_size: limits.max_string_size,
+            max_array_size: limits.max_array_size,
+            max_map_size: limits.max_map_size,
+            max_tool_output_bytes: limits.max_tool_output_bytes,
+            max_tool_input_bytes: limits.max_tool_input_bytes,
+            max_total_tool_output_bytes: limits.max_total_tool_output_bytes,
+            max_output_bytes: limits.max_output_bytes,
+            max_total_cost: limits.max_total_cost.unwrap_or(-1.0),
+            deterministic: limits.deterministic,
+            max_script_bytes: limits.max_script_bytes,
+            max_script_lines: limits.max_script_lines,
+            max_functions: limits.max_functions,
+            max_statements: limits.max_statements,
+            min_remaining_ms_for_tool_call: limits.min_remaining_ms_for_tool_call,
+            max_outputs: limits.max_outputs,
+            max_outputs_bytes: limits.max_outputs_bytes,
+            max_kv_entries: limits.max_kv_entries,
+            max_kv_bytes: limits.max_kv_bytes,
+            max_total_state_bytes: limits.max_total_state_bytes,
+            max_concurrent_tool_calls: limits.max_concurrent_tool_calls,
+            tool_call_operation_cost: limits.tool_call_operation_cost,
+        }
+    }
+}
+
+impl From<ToLimits> for ExecutionLimits {
+    fn from(limits: ToLimits) -> Self {
+        Self {
+            max_operations: limits.max_operations,
+            max_tool_calls: limits.max_tool_calls,
+            timeout_ms: limits.timeout_ms,
+            max_string_size: limits.max_string_size,
+            max_array_size: limits.max_array_size,
+            max_map_size: limits.max_map_size,
+            max_tool_output_bytes: limits.max_tool_output_bytes,
+            max_tool_input_bytes: limits.max_tool_input_bytes,
+            max_total_tool_output_bytes: limits.max_total_tool_output_bytes,
+            max_output_bytes: limits.max_output_bytes,
+            // NaN fails the comparison too
+            max_total_cost: (limits.max_total_cost >= 0.0).then_some(limits.max_total_cost),
+            deterministic: limits.deterministic,
+            max_script_bytes: limits.max_script_bytes,
+            max_script_lines: limits.max_script_lines,
+            max_functions: limits.max_functions,
+            max_statements: limits.max_statements,
+            min_remaining_ms_for_tool_call: limits.min_remaining_ms_for_tool_call,
+            max_outputs: limits.max_outputs,
+            max_outputs_bytes: limits.max_outputs_bytes,
+            max_kv_entries: limits.max_kv_entries,
+            max_kv_bytes: limits.max_kv_bytes,
+            max_total_state_bytes: limits.max_total_state_bytes,
+            max_concurrent_tool_calls: limits.max_concurrent_tool_calls,
+            tool_call_operation_cost: limits.tool_call_operation_cost,
+            ..Self::default()
+        }
+    }
+}
+
+/// The `struct_size` the caller set in `limits`, if this library knows it.
+///
+/// # Safety
+///
+/// `limits` must be non-NULL and valid for reads of a `usize`.
+unsafe fn limits_size(limits: *const ToLimits) -> Result<usize, FfiError> {
+    // SAFETY: every version of the struct starts with `struct_size`
+    let size = unsafe { limits.cast::<usize>().read() };
+    if (size_of::<usize>()..=size_of::<ToLimits>()).contains(&size) {
+        Ok(size)
+    } else {
+        let message = format!(
+            "`struct_size` is {size}, expected sizeof(ToLimits) (at most {})",
+            size_of::<ToLimits>()
+        );
+        Err(FfiError::new(TO_ERROR_INVALID_ARGUMENT, message))
+    }
+}
+
+/// Read the caller's limits, taking fields past its `struct_size` from the
+/// defaults.
+///
+/// # Safety
+///
+/// `limits` must be non-NULL and valid for reads of its `struct_size` bytes.
+unsafe fn read_limits(limits: *const ToLimits) -> Result<ExecutionLimits, FfiError> {
+    // SAFETY: per this function's contract
+    let size = unsafe { limits_size(limits) }?;
+    let mut full = ToLimits::from(ExecutionLimits::default());
+    // SAFETY: the caller's struct is a `size`-byte prefix of ours
+    unsafe { ptr::copy_nonoverlapping(limits.cast::<u8>(), (&raw mut full).cast::<u8>(), size) };
+    Ok(full.into())
+}
+
+/// Write `limits` to the caller's struct, up to its `struct_size`.
+///
+/// # Safety
+///
+/// `out` must be `NULL` or valid for reads and writes of its `struct_size`
+/// bytes.
+unsafe fn write_limits(out: *mut ToLimits, limits: ExecutionLimits) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::new(TO_ERROR_NULL_POINTER, "`out` is NULL"));
+    }
+    // SAFETY: per this function's contract
+    let size = unsafe { limits_size(out) }?;
+    let limits = ToLimits { struct_size: size, ..limits.into() };
+    // SAFETY: the caller's struct is a `size`-byte prefix of ours
+    unsafe { ptr::copy_nonoverlapping((&raw const limits).cast::<u8>(), out.cast::<u8>(), size) };
+    Ok(())
+}
+
+/// Write the default execution limits to `out`.
+///
+/// Set `out->struct_size` to `sizeof(ToLimits)` first.
+///
+/// # Safety
+///
+/// `out` must be valid for reads and writes of its `struct_size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_limits_default(out: *mut ToLimits) -> c_int {
+    // SAFETY: per this function's contract
+    guard_status(|| unsafe { write_limits(out, ExecutionLimits::default()) })
+}
+
+/// Write the limits of a preset profile (`"quick"`, `"default"`,
+/// `"extended"` or `"unlimited"`) to `out`.
+///
+/// Set `out->struct_size` to `sizeof(ToLimits)` first. Returns
+/// [`TO_ERROR_INVALID_ARGUMENT`] for an unknown profile.
+///
+/// # Safety
+///
+/// `name` must be a NUL-terminated string and `out` must be valid for reads
+/// and writes of its `struct_size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_limits_from_profile(name: *const c_char, out: *mut ToLimits) -> c_int {
+    guard_status(|| {
+        // SAFETY: per this function's contract
+        let name = unsafe { str_arg(name, "name") }?;
+        let limits = ProfileRegistry::new()
+            .resolve(name)
+            .map_err(|e| FfiError::new(TO_ERROR_INVALID_ARGUMENT, e.to_string()))?;
+        // SAFETY: per this function's contract
+        unsafe { write_limits(out, limits) }
+    })
+}
+
+// ============================================================================
+// Orchestrator
+// ============================================================================
+
+/// A tool implemented in C.
+///
+/// Receives the script's argument as JSON in `json_input`, which is only
+/// valid during the call. On success it returns 0 and may set `*out` to the
+/// tool's output (an empty string if left `NULL`); any other return value
+/// fails the call, with `*err` as the message if set. Both strings must be
+/// allocated with `malloc` and are freed by the orchestrator. Callbacks may
+/// run on other threads (`call_parallel`, `spawn_tool`) and must not unwind.
+pub type ToToolCallback = Option<
+    unsafe extern "C" fn(json_input: *const c_char, out: *mut *mut c_char, err: *mut *mut c_char) -> c_int,
+>;
+
+/// A non-NULL [`ToToolCallback`].
+type ToolFn = unsafe extern "C" fn(*const c_char, *mut *mut c_char, *mut *mut c_char) -> c_int;
+
+/// An orchestrator created by [`to_orchestrator_new`].
+pub struct ToOrchestrator {
+    inner: ToolOrchestrator,
+}
+
+/// The result of [`to_orchestrator_execute`].
+pub struct ToResult {
+    inner: OrchestratorResult,
+}
+
+unsafe extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+/// Take ownership of a string a tool callback allocated.
+///
+/// # Safety
+///
+/// `ptr` must be `NULL` or a NUL-terminated string allocated with `malloc`.
+unsafe fn take_callback_string(ptr: *mut c_char) -> Option<Result<String, String>> {
+    if ptr.is_null() {
+        return None;
+    }
+    // SAFETY: non-NULL and NUL-terminated per the caller's contract
+    let value = unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map(str::to_string)
+        .map_err(|_| "Tool returned invalid UTF-8".to_string());
+    // SAFETY: allocated with malloc per the caller's contract, and not used again
+    unsafe { free(ptr.cast()) };
+    Some(value)
+}
+
+/// Call a C tool with `input`, turning a non-zero return into a tool error.
+fn call_c_tool(callback: ToolFn, input: &serde_json::Value) -> Result<String, String> {
+    // Serialized JSON escapes NUL, so this can't fail
+    let input = CString::new(input.to_string()).map_err(|e| e.to_string())?;
+    let mut out = ptr::null_mut();
+    let mut err = ptr::null_mut();
+    // SAFETY: the callback's contract is documented on `ToToolCallback`
+    let code = unsafe { callback(input.as_ptr(), &raw mut out, &raw mut err) };
+    // SAFETY: the callback allocates both with malloc
+    let (out, err) = unsafe { (take_callback_string(out), take_callback_string(err)) };
+    if code == 0 {
+        out.unwrap_or_else(|| Ok(String::new()))
+    } else {
+        Err(err.and_then(Result::ok).unwrap_or_else(|| format!("tool callback returned {code}")))
+    }
+}
+
+/// Create an orchestrator with no tools, to be released with
+/// [`to_orchestrator_free`].
+#[unsafe(no_mangle)]
+pub extern "C" fn to_orchestrator_new() -> *mut ToOrchestrator {
+    guard_ptr(|| Ok(Box::into_raw(Box::new(ToOrchestrator { inner: ToolOrchestrator::new() }))))
+}
+
+/// Destroy an orchestrator. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `orchestrator` must be `NULL` or come from [`to_orchestrator_new`], and
+/// must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_orchestrator_free(orchestrator: *mut ToOrchestrator) {
+    if orchestrator.is_null() {
+        return;
+    }
+    let _ = guard(|| {
+        // SAFETY: allocated by to_orchestrator_new per this function's contract
+        drop(unsafe { Box::from_raw(orchestrator) });
+        Ok(())
+    });
+}
+
+/// Register `callback` as the tool `name`, replacing any tool already
+/// registered under that name.
+///
+/// # Safety
+///
+/// `orchestrator` must come from [`to_orchestrator_new`] and `name` must be
+/// a NUL-terminated string. Neither may be in use on another thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_orchestrator_register_tool(
+    orchestrator: *mut ToOrchestrator,
+    name: *const c_char,
+    callback: ToToolCallback,
+) -> c_int {
+    guard_status(|| {
+        // SAFETY: per this function's contract
+        let orchestrator = unsafe { orchestrator.as_mut() }
+            .ok_or_else(|| FfiError::new(TO_ERROR_NULL_POINTER, "`orchestrator` is NULL"))?;
+        // SAFETY: per this function's contract
+        let name = unsafe { str_arg(name, "name") }?;
+        let callback =
+            callback.ok_or_else(|| FfiError::new(TO_ERROR_NULL_POINTER, "`callback` is NULL"))?;
+        orchestrator.inner.register_executor(name, move |input| call_c_tool(callback, &input));
+        Ok(())
+    })
+}
+
+/// Execute a script with the registered tools, storing the result in
+/// `*result` for release with [`to_result_free`].
+///
+/// Uses the default limits when `limits` is `NULL`, and returns
+/// [`TO_ERROR_INVALID_ARGUMENT`] when its `struct_size` isn't one this
+/// library knows. Returns [`TO_OK`] even when the script fails; check
+/// [`to_result_success`].
+///
+/// # Safety
+///
+/// `orchestrator` must come from [`to_orchestrator_new`], `script` must be a
+/// NUL-terminated string, `limits` must be `NULL` or valid for reads of its
+/// `struct_size` bytes and `result` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_orchestrator_execute(
+    orchestrator: *const ToOrchestrator,
+    script: *const c_char,
+    limits: *const ToLimits,
+    result: *mut *mut ToResult,
+) -> c_int {
+    guard_status(|| {
+        // SAFETY: per this function's contract
+        let orchestrator = unsafe { ref_arg(orchestrator, "orchestrator") }?;
+        // SAFETY: per this function's contract
+        let script = unsafe { str_arg(script, "script") }?;
+        if result.is_null() {
+            return Err(FfiError::new(TO_ERROR_NULL_POINTER, "`result` is NULL"));
+        }
+        let limits = if limits.is_null() {
+            ExecutionLimits::default()
+        } else {
+            // SAFETY: non-NULL and valid for reads per this function's contract
+            unsafe { read_limits(limits) }?
+        };
+        let inner = orchestrator
+            .inner
+            .execute(script, limits)
+            .unwrap_or_else(|e| OrchestratorResult::error(e.to_string(), Vec::new(), 0));
+        // SAFETY: non-NULL and valid for writes per this function's contract
+        unsafe { result.write(Box::into_raw(Box::new(ToResult { inner }))) };
+        Ok(())
+    })
+}
+
+// ============================================================================
+// Results
+// ============================================================================
+
+/// Whether the script ran to completion. `false` for `NULL`.
+///
+/// # Safety
+///
+/// `result` must be `NULL` or come from [`to_orchestrator_execute`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_result_success(result: *const ToResult) -> bool {
+    // SAFETY: per this function's contract
+    unsafe { result.as_ref() }.is_some_and(|r| r.inner.success)
+}
+
+/// The script's output, to be released with [`to_free`].
+///
+/// # Safety
+///
+/// `result` must come from [`to_orchestrator_execute`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_result_output(result: *const ToResult) -> *mut c_char {
+    guard_ptr(|| {
+        // SAFETY: per this function's contract
+        let result = unsafe { ref_arg(result, "result") }?;
+        c_string(result.inner.output.as_str())
+    })
+}
+
+/// The whole result serialized as JSON, with the same fields as
+/// `OrchestratorResult`, to be released with [`to_free`].
+///
+/// # Safety
+///
+/// `result` must come from [`to_orchestrator_execute`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_result_json(result: *const ToResult) -> *mut c_char {
+    guard_ptr(|| {
+        // SAFETY: per this function's contract
+        let result = unsafe { ref_arg(result, "result") }?;
+        let json = serde_json::to_string(&result.inner)
+            .map_err(|e| FfiError::new(TO_ERROR_INVALID_ARGUMENT, e.to_string()))?;
+        c_string(json)
+    })
+}
+
+/// Destroy a result. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `result` must be `NULL` or come from [`to_orchestrator_execute`], and
+/// must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_result_free(result: *mut ToResult) {
+    if result.is_null() {
+        return;
+    }
+    let _ = guard(|| {
+        // SAFETY: allocated by to_orchestrator_execute per this function's contract
+        drop(unsafe { Box::from_raw(result) });
+        Ok(())
+    });
+}
+
+/// The message of the last failed call on this thread, to be released with
+/// [`to_free`], or `NULL` if none has failed.
+#[unsafe(no_mangle)]
+pub extern "C" fn to_last_error() -> *mut c_char {
+    LAST_ERROR
+        .with(|last| last.borrow().clone())
+        .and_then(|message| CString::new(message).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Release a string returned by one of these functions. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `ptr` must be `NULL` or a string returned by this library, and must not
+/// be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn to_free(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        // SAFETY: allocated by CString::into_raw per this function's contract
+        drop(unsafe { CString::from_raw(ptr) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" {
+        fn strdup(s: *const c_char) -> *mut c_char;
+    }
+
+    /// Take a string returned by the library.
+    fn take(ptr: *mut c_char) -> String {
+        assert!(!ptr.is_null(), "unexpected NULL");
+        let value = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { to_free(ptr) };
+        value
+    }
+
+    fn last_error() -> String {
+        take(to_last_error())
+    }
+
+    /// Echoes its input, prefixed.
+    unsafe extern "C" fn echo(input: *const c_char, out: *mut *mut c_char, _err: *mut *mut c_char) -> c_int {
+        let input = unsafe { CStr::from_ptr(input) }.to_str().unwrap();
+        let output = CString::new(format!("echo:{input}")).unwrap();
+        unsafe { *out = strdup(output.as_ptr()) };
+        0
+    }
+
+    /// Fails with a message.
+    unsafe extern "C" fn broken(_input: *const c_char, _out: *mut *mut c_char, err: *mut *mut c_char) -> c_int {
+        unsafe { *err = strdup(c"service down".as_ptr()) };
+        1
+    }
+
+    /// Fails without a message.
+    unsafe extern "C" fn silent(_input: *const c_char, _out: *mut *mut c_char, _err: *mut *mut c_char) -> c_int {
+        7
+    }
+
+    fn execute(orchestrator: *const ToOrchestrator, script: &CStr, limits: Option<&ToLimits>) -> *mut ToResult {
+        let mut result = ptr::null_mut();
+        let limits = limits.map_or(ptr::null(), ptr::from_ref);
+        let code = unsafe { to_orchestrator_execute(orchestrator, script.as_ptr(), limits, &raw mut result) };
+        assert_eq!(code, TO_OK);
+        result
+    }
+
+    #[test]
+    fn test_register_and_execute() {
+        let orchestrator = to_orchestrator_new();
+        assert!(!orchestrator.is_null());
+        unsafe {
+            assert_eq!(to_orchestrator_register_tool(orchestrator, c"echo".as_ptr(), Some(echo)), TO_OK);
+            assert_eq!(to_orchestrator_register_tool(orchestrator, c"broken".as_ptr(), Some(broken)), TO_OK);
+            assert_eq!(to_orchestrator_register_tool(orchestrator, c"silent".as_ptr(), Some(silent)), TO_OK);
+        }
+
+        let result = execute(orchestrator, c"[echo(#{ a: 1 }), broken(1), silent(2)]", None);
+        unsafe {
+            assert!(to_result_success(result));
+            let output: Vec<String> = serde_json::from_str(&take(to_result_output(result))).unwrap();
+            assert_eq!(output[0], r#"echo:{"a":1}"#);
+
+            let json: serde_json::Value = serde_json::from_str(&take(to_result_json(result))).unwrap();
+            let calls = json["tool_calls"].as_array().unwrap();
+            assert_eq!(calls.len(), 3);
+            assert_eq!(calls[0]["tool_name"], "echo");
+            assert_eq!(calls[1]["output"], "Tool error: service down");
+            assert_eq!(calls[2]["output"], "Tool error: tool callback returned 7");
+            assert_eq!(calls[2]["success"], false);
+
+            to_result_free(result);
+            to_orchestrator_free(orchestrator);
+        }
+    }
+
+    /// Limits the way a C caller starts them.
+    fn sized_limits() -> ToLimits {
+        ToLimits { struct_size: size_of::<ToLimits>(), ..ExecutionLimits::quick().into() }
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut limits = sized_limits();
+        assert_eq!(unsafe { to_limits_default(&raw mut limits) }, TO_OK);
+        assert_eq!(limits.max_tool_calls, ExecutionLimits::default().max_tool_calls);
+        assert_eq!(limits.max_kv_entries, ExecutionLimits::default().max_kv_entries);
+        assert_eq!(limits.max_total_cost, -1.0);
+
+        let mut quick = sized_limits();
+        assert_eq!(unsafe { to_limits_from_profile(c"quick".as_ptr(), &raw mut quick) }, TO_OK);
+        assert_eq!(quick.max_tool_calls, ExecutionLimits::quick().max_tool_calls);
+        assert_eq!(
+            unsafe { to_limits_from_profile(c"lavish".as_ptr(), &raw mut quick) },
+            TO_ERROR_INVALID_ARGUMENT
+        );
+        assert!(last_error().contains("lavish"));
+
+        quick.max_total_cost = 2.5;
+        quick.max_concurrent_tool_calls = 3;
+        let converted = ExecutionLimits::from(quick);
+        assert_eq!(converted.max_total_cost, Some(2.5));
+        assert_eq!(converted.max_concurrent_tool_calls, 3);
+        quick.max_total_cost = f64::NAN;
+        assert_eq!(ExecutionLimits::from(quick).max_total_cost, None);
+
+        let orchestrator = to_orchestrator_new();
+        unsafe { to_orchestrator_register_tool(orchestrator, c"echo".as_ptr(), Some(echo)) };
+        let mut limits = sized_limits();
+        unsafe { to_limits_default(&raw mut limits) };
+        limits.max_tool_calls = 1;
+        let result = execute(orchestrator, c"echo(1); echo(2)", Some(&limits));
+        unsafe {
+            assert_eq!(take(to_result_output(result)), "ERROR: Maximum tool calls (1) exceeded");
+            to_result_free(result);
+        }
+
+        // Script failures are reported in the result, not as FFI errors
+        let result = execute(orchestrator, c"let x = ;", None);
+        unsafe {
+            assert!(!to_result_success(result));
+            let json: serde_json::Value = serde_json::from_str(&take(to_result_json(result))).unwrap();
+            assert!(json["error"].is_string());
+            to_result_free(result);
+            to_orchestrator_free(orchestrator);
+        }
+    }
+
+    /// The first fields of `ToLimits`, as a caller built against an older
+    /// header would have it.
+    #[repr(C)]
+    struct OldLimits {
+        struct_size: usize,
+        max_operations: u64,
+        max_tool_calls: usize,
+        sentinel: u64,
+    }
+
+    #[test]
+    fn test_limits_struct_size() {
+        let struct_size = std::mem::offset_of!(OldLimits, sentinel);
+        let mut old = OldLimits { struct_size, max_operations: 0, max_tool_calls: 0, sentinel: 7 };
+        assert_eq!(unsafe { to_limits_default((&raw mut old).cast()) }, TO_OK);
+        assert_eq!(old.max_tool_calls, ExecutionLimits::default().max_tool_calls);
+        assert_eq!(old.sentinel, 7, "wrote past struct_size");
+
+        // Fields the caller doesn't know about keep their defaults
+        old.max_tool_calls = 1;
+        let limits = unsafe { read_limits((&raw const old).cast()) }.ok().unwrap();
+        assert_eq!(limits.max_tool_calls, 1);
+        assert_eq!(limits.max_kv_bytes, ExecutionLimits::default().max_kv_bytes);
+
+        let orchestrator = to_orchestrator_new();
+        let mut result = ptr::null_mut();
+        let mut limits = sized_limits();
+        for size in [0, size_of::<ToLimits>() + 8] {
+            limits.struct_size = size;
+            unsafe {
+                let script = c"1".as_ptr();
+                let code = to_orchestrator_execute(orchestrator, script, &raw const limits, &raw mut result);
+                assert_eq!(code, TO_ERROR_INVALID_ARGUMENT);
+                assert!(last_error().starts_with("`struct_size` is"));
+                assert_eq!(to_limits_default(&raw mut limits), TO_ERROR_INVALID_ARGUMENT);
+            }
+        }
+        assert!(result.is_null());
+        unsafe { to_orchestrator_free(orchestrator) };
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let orchestrator = to_orchestrator_new();
+        unsafe {
+            assert_eq!(
+                to_orchestrator_register_tool(ptr::null_mut(), c"echo".as_ptr(), Some(echo)),
+                TO_ERROR_NULL_POINTER
+            );
+            assert_eq!(last_error(), "`orchestrator` is NULL");
+            assert_eq!(
+                to_orchestrator_register_tool(orchestrator, c"echo".as_ptr(), None),
+                TO_ERROR_NULL_POINTER
+            );
+
+            let invalid = [0xff_u8, 0];
+            let code = to_orchestrator_register_tool(orchestrator, invalid.as_ptr().cast(), Some(echo));
+            assert_eq!(code, TO_ERROR_INVALID_UTF8);
+            assert!(last_error().starts_with("`name` is not UTF-8"));
+
+            let mut result = ptr::null_mut();
+            let code = to_orchestrator_execute(orchestrator, ptr::null(), ptr::null(), &raw mut result);
+            assert_eq!(code, TO_ERROR_NULL_POINTER);
+            assert!(result.is_null());
+
+            assert!(to_result_output(ptr::null()).is_null());
+            assert!(!to_result_success(ptr::null()));
+
+            to_free(ptr::null_mut());
+            to_result_free(ptr::null_mut());
+            to_orchestrator_free(orchestrator);
+            to_orchestrator_free(ptr::null_mut());
+        }
+    }
+
+    /// The checked-in header is cbindgen's output for this module. Skipped
+    /// when cbindgen isn't installed.
+    #[test]
+    fn test_header_is_up_to_date() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let Ok(output) = std::process::Command::new("cbindgen")
+            .current_dir(root)
+            .args(["--config", "cbindgen.toml", "src/ffi.rs"])
+            .output()
+        else {
+            eprintln!("cbindgen not found; skipping the header check");
+            return;
+        };
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let header = format!("{root}/include/tool_orchestrator_ffi.h");
+        let header = std::fs::read_to_string(header).unwrap();
+        assert!(
+            String::from_utf8_lossy(&output.stdout) == header,
+            "include/tool_orchestrator_ffi.h is out of date; regenerate it with \
+             `cbindgen --config cbindgen.toml --output include/tool_orchestrator_ffi.h src/ffi.rs`"
+        );
+    }
+
+    #[test]
+    fn test_panics_become_error_codes() {
+        let code = guard_status(|| panic!("boom"));
+        assert_eq!(code, TO_ERROR_PANIC);
+        assert_eq!(last_error(), "orchestrator panicked: boom");
+        assert!(guard_ptr::<c_char>(|| panic!("boom")).is_null());
+    }
+}
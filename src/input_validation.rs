@@ -0,0 +1,133 @@
+//! JSON Schema validation of tool input (`input-validation` feature).
+//!
+//! A tool registered with
+//! [`register_executor_with_schema`](crate::ToolOrchestrator::register_executor_with_schema)
+//! has its input checked before the executor runs. Input that doesn't match
+//! never reaches the tool: the script gets a tool error naming each problem
+//! by its JSON pointer, e.g.
+//! `input validation failed: /user_id: expected integer`, which a model can
+//! act on without knowing how the tool itself reports bad input.
+
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use jsonschema::{ValidationError, Validator};
+
+/// Most problems reported for one call
+const MAX_REPORTED_ERRORS: usize = 5;
+
+/// A compiled JSON Schema for a tool's input.
+pub(crate) struct InputSchema {
+    validator: Validator,
+    /// The schema as registered, for describing the tool to a model
+    source: serde_json::Value,
+}
+
+impl InputSchema {
+    /// Compile `schema`, describing why it is invalid otherwise.
+    pub(crate) fn compile(schema: &serde_json::Value) -> Result<Self, String> {
+        let validator = jsonschema::validator_for(schema).map_err(|e| e.to_string())?;
+        Ok(Self { validator, source: schema.clone() })
+    }
+
+    /// The schema as it was registered.
+    pub(crate) fn source(&self) -> &serde_json::Value {
+        &self.source
+    }
+
+    /// Check `input` against the schema.
+    pub(crate) fn check(&self, input: &serde_json::Value) -> Result<(), String> {
+        let problems: Vec<String> = self
+            .validator
+            .iter_errors(input)
+            .take(MAX_REPORTED_ERRORS)
+            .map(|e| describe(&e))
+            .collect();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(format!("input validation failed: {}", problems.join("; ")))
+    }
+}
+
+/// One problem as `pointer: what is wrong`.
+fn describe(error: &ValidationError<'_>) -> String {
+    let path = error.instance_path.as_str();
+    match &error.kind {
+        // Point at the missing field rather than the object lacking it
+        ValidationErrorKind::Required { property } => {
+            let name = property.as_str().map_or_else(|| property.to_string(), str::to_string);
+            let name = name.replace('~', "~0").replace('/', "~1");
+            format!("{path}/{name}: missing required field")
+        }
+        ValidationErrorKind::Type { kind } => {
+            let expected = match kind {
+                TypeKind::Single(expected) => expected.to_string(),
+                TypeKind::Multiple(set) => {
+                    set.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" or ")
+                }
+            };
+            format!("{}: expected {expected}", or_root(path))
+        }
+        _ => format!("{}: {error}", or_root(path)),
+    }
+}
+
+/// `path`, or `(root)` for the input as a whole.
+fn or_root(path: &str) -> &str {
+    if path.is_empty() { "(root)" } else { path }
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user_schema() -> InputSchema {
+        InputSchema::compile(&json!({
+            "type": "object",
+            "required": ["user_id", "address"],
+            "properties": {
+                "user_id": {"type": "integer"},
+                "address": {
+                    "type": "object",
+                    "required": ["city"],
+                    "properties": {"city": {"type": "string"}, "zip": {"type": ["string", "null"]}}
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_valid_input_passes() {
+        let input = json!({"user_id": 7, "address": {"city": "Oslo", "zip": null}});
+        assert_eq!(user_schema().check(&input), Ok(()));
+    }
+
+    #[test]
+    fn test_problems_are_reported_by_pointer() {
+        let schema = user_schema();
+        assert_eq!(
+            schema.check(&json!({"user_id": "7", "address": {"city": "Oslo"}})),
+            Err("input validation failed: /user_id: expected integer".to_string())
+        );
+        assert_eq!(
+            schema.check(&json!({"user_id": 7})),
+            Err("input validation failed: /address: missing required field".to_string())
+        );
+        assert_eq!(
+            schema.check(&json!({"user_id": 7, "address": {"zip": 1}})),
+            Err("input validation failed: /address/zip: expected null or string; \
+                 /address/city: missing required field"
+                .to_string())
+        );
+        assert_eq!(
+            schema.check(&json!("user 7")),
+            Err("input validation failed: (root): expected object".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_schema_is_rejected() {
+        assert!(InputSchema::compile(&json!({"type": "no-such-type"})).is_err());
+    }
+}
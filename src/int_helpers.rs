@@ -0,0 +1,89 @@
+//! Overflow-checked integer arithmetic for scripts.
+//!
+//! Registers `checked_add`, `checked_sub`, `checked_mul`, `checked_div`,
+//! `checked_rem` and `checked_pow`. Plain Rhai arithmetic that overflows
+//! aborts the script; these return `()` instead, so a script summing large
+//! counters or IDs can notice and fall back. Division and remainder by zero
+//! return `()` as well, as does a negative exponent.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let total = checked_add(stats.bytes_in, stats.bytes_out);
+//! if total == () { "total too large to compute" } else { `total: ${total}` }
+//! ```
+
+use rhai::{Dynamic, FuncRegistration, Module, INT};
+
+/// Names of the functions registered by [`int_module`]
+pub(crate) const FUNCTION_NAMES: [&str; 6] =
+    ["checked_add", "checked_sub", "checked_mul", "checked_div", "checked_rem", "checked_pow"];
+
+/// A checked operation of `INT`, such as `INT::checked_add`
+type CheckedOp = fn(INT, INT) -> Option<INT>;
+
+/// `result` as a script value, `()` for `None`.
+fn to_dynamic(result: Option<INT>) -> Dynamic {
+    result.map_or(Dynamic::UNIT, Dynamic::from_int)
+}
+
+/// Build a module with the checked arithmetic functions.
+pub(crate) fn int_module() -> Module {
+    let mut module = Module::new();
+    let binary: [(&str, CheckedOp); 5] = [
+        ("checked_add", INT::checked_add),
+        ("checked_sub", INT::checked_sub),
+        ("checked_mul", INT::checked_mul),
+        ("checked_div", INT::checked_div),
+        ("checked_rem", INT::checked_rem),
+    ];
+    for (name, op) in binary {
+        FuncRegistration::new(name)
+            .set_into_module(&mut module, move |a: INT, b: INT| to_dynamic(op(a, b)));
+    }
+    FuncRegistration::new("checked_pow").set_into_module(&mut module, |base: INT, exp: INT| {
+        to_dynamic(u32::try_from(exp).ok().and_then(|exp| base.checked_pow(exp)))
+    });
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecutionLimits, ToolOrchestrator};
+
+    fn run(script: &str) -> String {
+        ToolOrchestrator::new()
+            .execute(script, ExecutionLimits::default())
+            .unwrap()
+            .output
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(run("checked_add(40, 2)"), "42");
+        assert_eq!(run("checked_sub(-40, 2)"), "-42");
+        assert_eq!(run("checked_mul(6, 7)"), "42");
+        assert_eq!(run("checked_div(85, 2)"), "42");
+        assert_eq!(run("checked_rem(85, 43)"), "42");
+        assert_eq!(run("checked_pow(2, 10)"), "1024");
+    }
+
+    #[test]
+    fn test_overflow_returns_unit() {
+        let max = INT::MAX;
+        let min = INT::MIN;
+        for script in [
+            format!("checked_add({max}, 1)"),
+            format!("checked_sub({min}, 1)"),
+            format!("checked_mul({max}, 2)"),
+            format!("checked_div({min}, -1)"),
+            "checked_div(1, 0)".to_string(),
+            "checked_rem(1, 0)".to_string(),
+            "checked_pow(10, 40)".to_string(),
+            "checked_pow(2, -1)".to_string(),
+        ] {
+            assert_eq!(run(&format!("let r = {script}; r == ()")), "true", "{script}");
+        }
+    }
+}
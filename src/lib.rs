@@ -10,6 +10,15 @@
 //!
 //! - **`native`** (default) - Thread-safe Rust library with `Arc`/`Mutex`
 //! - **`wasm`** - WebAssembly bindings for browser/Node.js via `wasm-bindgen`
+//! - **`python`** - Python bindings via PyO3, built with maturin (see [`python`])
+//! - **`ffi`** - C ABI for C, C++ and other non-Rust hosts (see [`ffi`])
+//!
+//! Enabling both, which feature unification in a workspace does easily,
+//! builds the native library: `Arc`/`Mutex` work wherever threads do, and
+//! the `wasm` module is left out because Rhai's `sync` mode (part of
+//! `native`) can't hold JavaScript callbacks. Only a `wasm32` build with both
+//! features fails, as it can't have been meant. The test matrix covers
+//! `native`, `wasm` (`--no-default-features`) and `native,wasm`.
 //!
 //! ## Benefits
 //!
@@ -54,40 +63,89 @@
 //! console.log(result.output); // "Hello, Claude!"
 //! ```
 
-// Require either native or wasm feature (but not both)
+// Require either native or wasm feature; with both, native wins
 #[cfg(not(any(feature = "native", feature = "wasm")))]
 compile_error!(
     "Either the `native` or `wasm` feature must be enabled. \
      Add `features = [\"native\"]` to your Cargo.toml dependency or use `--features native`."
 );
 
-#[cfg(all(feature = "native", feature = "wasm"))]
+#[cfg(all(feature = "native", feature = "wasm", target_arch = "wasm32"))]
 compile_error!(
-    "The `native` and `wasm` features are mutually exclusive. \
-     Use `--features native` for Rust library or `--no-default-features --features wasm` for WASM."
+    "The `wasm` feature's WasmOrchestrator is unavailable while `native` is also enabled, \
+     often through the default features of another dependency in the workspace. \
+     Depend on this crate with `default-features = false, features = [\"wasm\"]` everywhere."
 );
 
 // Core modules (always available)
+pub mod diff;
 pub mod engine;
+mod int_helpers;
+pub mod output_filters;
 pub mod sandbox;
+mod sha256;
+pub mod template;
+mod text_helpers;
+mod tool_definitions;
 pub mod types;
+pub mod util;
+
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "datetime")]
+mod datetime_helpers;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "input-validation")]
+mod input_validation;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "regex")]
+pub mod regex_helpers;
+#[cfg(any(feature = "testing", all(test, feature = "native")))]
+pub mod testing;
 
 // Re-export core types
-pub use engine::{dynamic_to_json, ToolExecutor, ToolOrchestrator};
+pub use diff::{DiffLine, DiffOptions, FieldChange, ResultDiff, ToolCallDiff};
+pub use engine::{
+    dynamic_to_json, json_to_dynamic, json_to_dynamic_checked, tool_matches, BinaryToolExecutor,
+    ExecutionEndHook, NameCheck, OrchestratorSession, OutputFilter, RateLimit, RateLimitBehavior,
+    Tool, ToolCacheConfig, ToolCallEndHook, ToolCallStartHook, ToolExecutor, ToolOrchestrator,
+    ToolProvider, ToolSet, MAX_TOOL_ARITY, RESERVED_TOOL_NAMES,
+};
 pub use sandbox::{
-    ExecutionLimits,
+    ExecutionLimits, LanguageRestrictions, LimitsError, NoProgressConfig, OutputPolicy,
+    ProfileRegistry, SizeLimitBehavior,
     // Default limit constants
-    DEFAULT_MAX_ARRAY_SIZE, DEFAULT_MAX_MAP_SIZE, DEFAULT_MAX_OPERATIONS, DEFAULT_MAX_STRING_SIZE,
-    DEFAULT_MAX_TOOL_CALLS, DEFAULT_TIMEOUT_MS,
+    DEFAULT_MAX_ARRAY_SIZE, DEFAULT_MAX_CONCURRENT_TOOL_CALLS, DEFAULT_MAX_FUNCTIONS, DEFAULT_MAX_KV_BYTES, DEFAULT_MAX_KV_ENTRIES,
+    DEFAULT_MAX_MAP_SIZE, DEFAULT_MAX_OPERATIONS, DEFAULT_MAX_OUTPUTS, DEFAULT_MAX_OUTPUTS_BYTES,
+    DEFAULT_MAX_OUTPUT_BYTES,
+    DEFAULT_MAX_SCRIPT_BYTES, DEFAULT_MAX_SCRIPT_LINES, DEFAULT_MAX_STATEMENTS,
+    DEFAULT_MAX_STRING_SIZE, DEFAULT_MAX_TOOL_CALLS, DEFAULT_MAX_TOOL_INPUT_BYTES,
+    DEFAULT_MAX_TOOL_OUTPUT_BYTES, DEFAULT_MAX_TOTAL_STATE_BYTES,
+    DEFAULT_MAX_TOTAL_TOOL_OUTPUT_BYTES, DEFAULT_NO_PROGRESS_FAILED_CALLS,
+    DEFAULT_NO_PROGRESS_REPEATED_CALLS, DEFAULT_TIMEOUT_MS,
     // Profile constants
     EXTENDED_MAX_OPERATIONS, EXTENDED_MAX_TOOL_CALLS, EXTENDED_TIMEOUT_MS, QUICK_MAX_OPERATIONS,
     QUICK_MAX_TOOL_CALLS, QUICK_TIMEOUT_MS,
 };
-pub use types::{OrchestratorError, OrchestratorResult, ToolCall};
+pub use template::{ScriptTemplate, TemplateError};
+pub use types::{
+    canonical_json_hash, estimate_tokens, script_hash, BinaryOutputMode, CancellationToken,
+    ErrorReport, ExecutionOptions, ExecutionTiming, ExecutionWarning, FunctionInfo,
+    LlmFormatOptions, MissingDependency, OrchestratorError, OrchestratorResult, OutputFormat,
+    ProgressCallback, ProgressInfo, ScriptDiagnostic, ScriptValidation, ToolCall, ToolCallStatus,
+    ToolErrorMode, ToolInfo, ToolOutput,
+    DEFAULT_MAX_DEPTH, DEFAULT_PROGRESS_STRIDE,
+};
+#[cfg(feature = "native")]
+pub use types::ExecutionEvent;
 
-// WASM module (only when wasm feature is enabled)
-#[cfg(feature = "wasm")]
+// WASM module (only when wasm feature is enabled without native)
+#[cfg(all(feature = "wasm", not(feature = "native")))]
 pub mod wasm;
 
-#[cfg(feature = "wasm")]
+#[cfg(all(feature = "wasm", not(feature = "native")))]
 pub use wasm::{ExecutionLimits as WasmExecutionLimits, WasmOrchestrator};
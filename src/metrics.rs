@@ -0,0 +1,235 @@
+//! Execution and tool call metrics (`metrics` feature).
+//!
+//! Every [`ToolOrchestrator`] execution records the metrics below through the
+//! [`metrics`](::metrics) facade, to whatever recorder the application has
+//! installed, such as `metrics-exporter-prometheus`. Without a recorder they
+//! cost next to nothing. Call [`describe`] once after installing the
+//! recorder to give them units and help texts.
+//!
+//! | Metric | Type | Labels |
+//! |--------|------|--------|
+//! | `tool_orchestrator_executions_started_total` | counter | |
+//! | `tool_orchestrator_executions_completed_total` | counter | |
+//! | `tool_orchestrator_executions_failed_total` | counter | `kind` |
+//! | `tool_orchestrator_tool_calls_total` | counter | `tool`, `status` |
+//! | `tool_orchestrator_tool_call_duration_seconds` | histogram | `tool` |
+//! | `tool_orchestrator_limit_violations_total` | counter | `limit` |
+//! | `tool_orchestrator_tool_cache_lookups_total` | counter | `tool`, `result` |
+//!
+//! Label values come from fixed sets or the names of registered tools, never
+//! from tool input, output or script text, so the number of series is
+//! bounded by the number of tools:
+//!
+//! - `kind` is the failed execution's [`OrchestratorError::kind`].
+//! - `status` is the call's [`ToolCallStatus::as_str`].
+//! - `limit` is the `kind` of an execution that failed on a limit
+//!   (`timeout`, `max_operations_exceeded`, `output_too_large`, ...), or, for
+//!   a refused tool call, `max_tool_calls_exceeded`, `max_total_cost_exceeded`,
+//!   `limit_exceeded`, `rate_limited` or `deadline_exceeded`.
+//! - `result` is `hit` or `miss`.
+//!
+//! Durations are only recorded for calls that ran the tool, not for cached or
+//! refused ones.
+//!
+//! # Example
+//!
+//! ```ignore
+//! metrics_exporter_prometheus::PrometheusBuilder::new().install()?;
+//! tool_orchestrator::metrics::describe();
+//! ```
+//!
+//! [`ToolOrchestrator`]: crate::ToolOrchestrator
+//! [`ToolCallStatus::as_str`]: crate::ToolCallStatus::as_str
+
+use ::metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+
+use crate::types::{OrchestratorError, OrchestratorResult, ToolCall, ToolCallStatus};
+
+/// Executions started, counted before the script is compiled
+pub const EXECUTIONS_STARTED: &str = "tool_orchestrator_executions_started_total";
+
+/// Executions that returned a result
+pub const EXECUTIONS_COMPLETED: &str = "tool_orchestrator_executions_completed_total";
+
+/// Executions that returned an error, labelled by its `kind`
+pub const EXECUTIONS_FAILED: &str = "tool_orchestrator_executions_failed_total";
+
+/// Tool calls, labelled by `tool` and `status`
+pub const TOOL_CALLS: &str = "tool_orchestrator_tool_calls_total";
+
+/// Time spent in tool executors, labelled by `tool`
+pub const TOOL_CALL_DURATION: &str = "tool_orchestrator_tool_call_duration_seconds";
+
+/// Executions and tool calls stopped by a limit, labelled by `limit`
+pub const LIMIT_VIOLATIONS: &str = "tool_orchestrator_limit_violations_total";
+
+/// Tool result cache lookups, labelled by `tool` and `result`
+pub const TOOL_CACHE_LOOKUPS: &str = "tool_orchestrator_tool_cache_lookups_total";
+
+/// Register the units and descriptions of the orchestrator's metrics with
+/// the installed recorder.
+pub fn describe() {
+    describe_counter!(EXECUTIONS_STARTED, Unit::Count, "Script executions started");
+    describe_counter!(EXECUTIONS_COMPLETED, Unit::Count, "Script executions that succeeded");
+    describe_counter!(EXECUTIONS_FAILED, Unit::Count, "Script executions that failed, by error");
+    describe_counter!(TOOL_CALLS, Unit::Count, "Tool calls made by scripts, by tool and status");
+    describe_histogram!(TOOL_CALL_DURATION, Unit::Seconds, "Tool call duration, by tool");
+    describe_counter!(LIMIT_VIOLATIONS, Unit::Count, "Executions and calls stopped by a limit");
+    describe_counter!(TOOL_CACHE_LOOKUPS, Unit::Count, "Tool result cache hits and misses");
+}
+
+pub(crate) fn execution_started() {
+    counter!(EXECUTIONS_STARTED).increment(1);
+}
+
+pub(crate) fn execution_finished(outcome: Result<&OrchestratorResult, &OrchestratorError>) {
+    match outcome {
+        Ok(_) => counter!(EXECUTIONS_COMPLETED).increment(1),
+        Err(e) => {
+            counter!(EXECUTIONS_FAILED, "kind" => e.kind()).increment(1);
+            if is_limit_error(e) {
+                limit_violation(e.kind());
+            }
+        }
+    }
+}
+
+pub(crate) fn tool_call(call: &ToolCall) {
+    let tool = call.tool_name.clone();
+    counter!(TOOL_CALLS, "tool" => tool.clone(), "status" => call.status.as_str()).increment(1);
+    match call.status {
+        ToolCallStatus::Ok | ToolCallStatus::Error | ToolCallStatus::TimedOut => {
+            #[allow(clippy::cast_precision_loss)]
+            let seconds = call.duration_ms as f64 / 1000.0;
+            histogram!(TOOL_CALL_DURATION, "tool" => tool).record(seconds);
+        }
+        ToolCallStatus::LimitExceeded
+        | ToolCallStatus::RateLimited
+        | ToolCallStatus::DeadlineExceeded => limit_violation(call.status.as_str()),
+        ToolCallStatus::CallLimitExceeded => limit_violation("max_tool_calls_exceeded"),
+        ToolCallStatus::CostLimitExceeded => limit_violation("max_total_cost_exceeded"),
+        _ => {}
+    }
+}
+
+pub(crate) fn limit_violation(limit: &'static str) {
+    counter!(LIMIT_VIOLATIONS, "limit" => limit).increment(1);
+}
+
+pub(crate) fn cache_lookup(tool: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    counter!(TOOL_CACHE_LOOKUPS, "tool" => tool.to_string(), "result" => result).increment(1);
+}
+
+/// Whether `error` means the script ran into one of its limits.
+const fn is_limit_error(error: &OrchestratorError) -> bool {
+    matches!(
+        error,
+        OrchestratorError::MaxOperationsExceeded(_)
+            | OrchestratorError::MaxToolCallsExceeded(_)
+            | OrchestratorError::Timeout(_)
+            | OrchestratorError::OutputTooLarge(_)
+            | OrchestratorError::ValueTooLarge(_)
+            | OrchestratorError::ScriptTooLarge { .. }
+            | OrchestratorError::ScriptTooComplex { .. }
+            | OrchestratorError::MaxDepthExceeded(_)
+            | OrchestratorError::NoProgress(_)
+            | OrchestratorError::MemoryBudgetExceeded(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecutionLimits, ToolOrchestrator};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    /// The value of every metric recorded by `f`, keyed by name and labels,
+    /// e.g. `tool_orchestrator_tool_calls_total{tool=fetch,status=ok}`.
+    fn record(f: impl FnOnce()) -> Vec<(String, DebugValue)> {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, f);
+        let mut values: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let key = key.key();
+                let labels: Vec<_> =
+                    key.labels().map(|l| format!("{}={}", l.key(), l.value())).collect();
+                (format!("{}{{{}}}", key.name(), labels.join(",")), value)
+            })
+            .collect();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+
+    fn counter(values: &[(String, DebugValue)], key: &str) -> u64 {
+        match values.iter().find(|(name, _)| name == key) {
+            Some((_, DebugValue::Counter(n))) => *n,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn test_execution_and_tool_call_counters() {
+        let mut orchestrator = ToolOrchestrator::new();
+        orchestrator.register_executor("fetch", |input| Ok(input.to_string()));
+        orchestrator.register_executor("broken", |_| Err("down".to_string()));
+
+        let values = record(|| {
+            let script = r#"fetch("a"); fetch("b"); broken(1)"#;
+            orchestrator.execute(script, ExecutionLimits::default()).unwrap();
+            orchestrator.execute("fetch(", ExecutionLimits::default()).unwrap_err();
+        });
+
+        assert_eq!(counter(&values, "tool_orchestrator_executions_started_total{}"), 2);
+        assert_eq!(counter(&values, "tool_orchestrator_executions_completed_total{}"), 1);
+        let failed = "tool_orchestrator_executions_failed_total{kind=compilation_error}";
+        assert_eq!(counter(&values, failed), 1);
+        let calls = "tool_orchestrator_tool_calls_total";
+        assert_eq!(counter(&values, &format!("{calls}{{tool=fetch,status=ok}}")), 2);
+        assert_eq!(counter(&values, &format!("{calls}{{tool=broken,status=error}}")), 1);
+
+        let durations = values
+            .iter()
+            .find(|(name, _)| name == "tool_orchestrator_tool_call_duration_seconds{tool=fetch}");
+        assert!(matches!(durations, Some((_, DebugValue::Histogram(h))) if h.len() == 2));
+        // A compilation error isn't a limit
+        assert!(!values.iter().any(|(name, _)| name.starts_with(LIMIT_VIOLATIONS)));
+    }
+
+    #[test]
+    fn test_limit_violations() {
+        let mut orchestrator = ToolOrchestrator::new();
+        orchestrator.register_executor("ping", |_| Ok("pong".to_string()));
+
+        let values = record(|| {
+            let limits = ExecutionLimits::default().with_max_tool_calls(1);
+            orchestrator.execute("ping(1); ping(2)", limits).unwrap();
+            let limits = ExecutionLimits::default().with_max_operations(1_000);
+            orchestrator.execute("loop {}", limits).unwrap_err();
+        });
+
+        let limit = |name: &str| counter(&values, &format!("{LIMIT_VIOLATIONS}{{limit={name}}}"));
+        assert_eq!(limit("max_tool_calls_exceeded"), 1);
+        assert_eq!(limit("max_operations_exceeded"), 1);
+        let failed = "tool_orchestrator_executions_failed_total{kind=max_operations_exceeded}";
+        assert_eq!(counter(&values, failed), 1);
+    }
+
+    #[test]
+    fn test_describe_registers_units() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, || {
+            describe();
+            counter!(TOOL_CALLS, "tool" => "t", "status" => "ok").increment(1);
+        });
+        let snapshot = snapshotter.snapshot().into_vec();
+        let (_, unit, description, _) = &snapshot[0];
+        assert_eq!(*unit, Some(Unit::Count));
+        assert!(description.is_some());
+    }
+}
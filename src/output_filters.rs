@@ -0,0 +1,151 @@
+//! Built-in filters for [`ToolOrchestrator::add_output_filter`].
+//!
+//! Each filter is a plain `Fn(String) -> String`, so they can be registered
+//! directly and chained with the host's own filters:
+//!
+//! ```ignore
+//! use tool_orchestrator::output_filters::{collapse_whitespace, strip_ansi};
+//!
+//! orchestrator
+//!     .add_output_filter(strip_ansi)
+//!     .add_output_filter(collapse_whitespace);
+//! ```
+//!
+//! [`ToolOrchestrator::add_output_filter`]: crate::ToolOrchestrator::add_output_filter
+
+/// The escape character that starts every ANSI control sequence
+const ESC: char = '\u{1b}';
+
+/// The bell character, which may end an operating system command
+const BEL: char = '\u{7}';
+
+/// Remove ANSI escape sequences such as colours and cursor movement.
+///
+/// Handles CSI sequences (`ESC [ ... final`), operating system commands
+/// (`ESC ] ... BEL` or `ESC ] ... ESC \`, used for hyperlinks and window
+/// titles) and two-character escapes. A truncated sequence at the end of the
+/// text is dropped.
+#[must_use]
+pub fn strip_ansi(text: String) -> String {
+    if !text.contains(ESC) {
+        return text;
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != ESC {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Parameters and intermediates, then one final byte in `@`..=`~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == BEL {
+                        break;
+                    }
+                    if c == ESC && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Collapse runs of whitespace and trim both ends.
+///
+/// A run without a line break becomes a single space, a run with one line
+/// break becomes `\n`, and a run with more becomes one blank line, so
+/// paragraphs survive while indentation and padding don't.
+#[must_use]
+pub fn collapse_whitespace(text: String) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut newlines = 0;
+    let mut in_run = false;
+    for c in text.trim().chars() {
+        if c.is_whitespace() {
+            in_run = true;
+            newlines += usize::from(c == '\n');
+            continue;
+        }
+        if in_run {
+            out.push_str(match newlines {
+                0 => " ",
+                1 => "\n",
+                _ => "\n\n",
+            });
+            in_run = false;
+            newlines = 0;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A filter replacing every match of `pattern` with `[REDACTED]`.
+///
+/// # Errors
+///
+/// Returns the [`regex::Error`] if `pattern` is invalid.
+///
+/// # Example
+///
+/// ```ignore
+/// orchestrator.add_output_filter(redact_pattern(r"sk-[A-Za-z0-9]{20,}")?);
+/// ```
+#[cfg(feature = "regex")]
+pub fn redact_pattern(
+    pattern: &str,
+) -> Result<impl Fn(String) -> String + Clone + Send + Sync + 'static, regex::Error> {
+    let regex = regex::Regex::new(pattern)?;
+    Ok(move |text: String| match regex.replace_all(&text, "[REDACTED]") {
+        std::borrow::Cow::Borrowed(_) => text,
+        std::borrow::Cow::Owned(redacted) => redacted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        let colored = "\u{1b}[1;31merror\u{1b}[0m: \u{1b}[2Kdone".to_string();
+        assert_eq!(strip_ansi(colored), "error: done");
+
+        let link = "\u{1b}]8;;https://example.com\u{7}docs\u{1b}]8;;\u{1b}\\ here".to_string();
+        assert_eq!(strip_ansi(link), "docs here");
+
+        assert_eq!(strip_ansi("plain é".to_string()), "plain é");
+        assert_eq!(strip_ansi("cut \u{1b}[38;5".to_string()), "cut ");
+        assert_eq!(strip_ansi("reset\u{1b}c".to_string()), "reset");
+    }
+
+    #[test]
+    fn test_collapse_whitespace() {
+        let text = "  a \t  b\n   c\n\n\n  d  \r\n".to_string();
+        assert_eq!(collapse_whitespace(text), "a b\nc\n\nd");
+        assert_eq!(collapse_whitespace(" \n ".to_string()), "");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_redact_pattern() {
+        let redact = redact_pattern(r"sk-[a-z0-9]+").unwrap();
+        assert_eq!(redact("key sk-abc123 and sk-x".to_string()), "key [REDACTED] and [REDACTED]");
+        assert_eq!(redact("nothing".to_string()), "nothing");
+        assert!(redact_pattern("(").is_err());
+    }
+}
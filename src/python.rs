@@ -0,0 +1,598 @@
+//! Python bindings (`python` feature).
+//!
+//! Exposes the orchestrator to Python through PyO3, with the same surface as
+//! the WASM bindings, so Python agents run scripts under the same sandbox
+//! instead of a reimplementation. The extension module is built with
+//! [maturin](https://www.maturin.rs), which reads the features to enable from
+//! `pyproject.toml`:
+//!
+//! ```bash
+//! maturin develop --release
+//! ```
+//!
+//! ```python
+//! from tool_orchestrator import ToolOrchestrator, ExecutionLimits
+//!
+//! orchestrator = ToolOrchestrator()
+//! orchestrator.register_tool("greet", lambda name: f"Hello, {name}!")
+//!
+//! result = orchestrator.execute('greet("Claude")', ExecutionLimits.quick())
+//! assert result.success and result.output == "Hello, Claude!"
+//! ```
+//!
+//! Tools receive the script's argument as a Python value (`dict`, `list`,
+//! `str`, `int`, `float`, `bool` or `None`) and must return a `str`. An
+//! exception raised by a tool, or a result that isn't a `str`, fails the
+//! call like an executor error: the script receives a tool error and the
+//! [`ToolCall`] is recorded as failed.
+//!
+//! Scripts run with the GIL released; each tool call takes it for as long
+//! as the Python callable runs, so other Python threads keep going while a
+//! script evaluates.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyList, PyString};
+
+use crate::engine::ToolOrchestrator;
+use crate::sandbox::{ExecutionLimits, ProfileRegistry};
+use crate::types::{OrchestratorResult, ToolCall};
+
+// ============================================================================
+// Execution Limits
+// ============================================================================
+
+/// Execution limits for safe script execution (Python `ExecutionLimits`).
+#[pyclass(name = "ExecutionLimits", module = "tool_orchestrator")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PyExecutionLimits {
+    inner: ExecutionLimits,
+}
+
+#[pymethods]
+impl PyExecutionLimits {
+    /// Create new execution limits with defaults.
+    #[new]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create quick execution limits for simple scripts.
+    #[staticmethod]
+    #[must_use]
+    pub fn quick() -> Self {
+        Self { inner: ExecutionLimits::quick() }
+    }
+
+    /// Create extended limits for complex orchestration.
+    #[staticmethod]
+    #[must_use]
+    pub fn extended() -> Self {
+        Self { inner: ExecutionLimits::extended() }
+    }
+
+    /// Create limits that never stop a script (fully trusted scripts only).
+    #[staticmethod]
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self { inner: ExecutionLimits::unlimited() }
+    }
+
+    /// Create the limits of a preset profile by name.
+    #[staticmethod]
+    pub fn from_profile(name: &str) -> PyResult<Self> {
+        ProfileRegistry::new()
+            .resolve(name)
+            .map(|inner| Self { inner })
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Max operations.
+    #[getter]
+    #[must_use]
+    pub const fn max_operations(&self) -> u64 {
+        self.inner.max_operations
+    }
+
+    /// Set max operations.
+    #[setter]
+    pub const fn set_max_operations(&mut self, value: u64) {
+        self.inner.max_operations = value;
+    }
+
+    /// Max tool calls.
+    #[getter]
+    #[must_use]
+    pub const fn max_tool_calls(&self) -> usize {
+        self.inner.max_tool_calls
+    }
+
+    /// Set max tool calls.
+    #[setter]
+    pub const fn set_max_tool_calls(&mut self, value: usize) {
+        self.inner.max_tool_calls = value;
+    }
+
+    /// Timeout in milliseconds.
+    #[getter]
+    #[must_use]
+    pub const fn timeout_ms(&self) -> u64 {
+        self.inner.timeout_ms
+    }
+
+    /// Set timeout in milliseconds.
+    #[setter]
+    pub const fn set_timeout_ms(&mut self, value: u64) {
+        self.inner.timeout_ms = value;
+    }
+
+    /// Max string size in bytes.
+    #[getter]
+    #[must_use]
+    pub const fn max_string_size(&self) -> usize {
+        self.inner.max_string_size
+    }
+
+    /// Set max string size in bytes.
+    #[setter]
+    pub const fn set_max_string_size(&mut self, value: usize) {
+        self.inner.max_string_size = value;
+    }
+
+    /// Max array size.
+    #[getter]
+    #[must_use]
+    pub const fn max_array_size(&self) -> usize {
+        self.inner.max_array_size
+    }
+
+    /// Set max array size.
+    #[setter]
+    pub const fn set_max_array_size(&mut self, value: usize) {
+        self.inner.max_array_size = value;
+    }
+
+    /// Max map size.
+    #[getter]
+    #[must_use]
+    pub const fn max_map_size(&self) -> usize {
+        self.inner.max_map_size
+    }
+
+    /// Set max map size.
+    #[setter]
+    pub const fn set_max_map_size(&mut self, value: usize) {
+        self.inner.max_map_size = value;
+    }
+
+    /// Max bytes a single tool call may return.
+    #[getter]
+    #[must_use]
+    pub const fn max_tool_output_bytes(&self) -> usize {
+        self.inner.max_tool_output_bytes
+    }
+
+    /// Set max bytes a single tool call may return.
+    #[setter]
+    pub const fn set_max_tool_output_bytes(&mut self, value: usize) {
+        self.inner.max_tool_output_bytes = value;
+    }
+
+    /// Max serialized size of a single tool call's input in bytes.
+    #[getter]
+    #[must_use]
+    pub const fn max_tool_input_bytes(&self) -> usize {
+        self.inner.max_tool_input_bytes
+    }
+
+    /// Set max serialized size of a single tool call's input in bytes.
+    #[setter]
+    pub const fn set_max_tool_input_bytes(&mut self, value: usize) {
+        self.inner.max_tool_input_bytes = value;
+    }
+
+    /// Max cumulative tool output in bytes.
+    #[getter]
+    #[must_use]
+    pub const fn max_total_tool_output_bytes(&self) -> usize {
+        self.inner.max_total_tool_output_bytes
+    }
+
+    /// Set max cumulative tool output in bytes.
+    #[setter]
+    pub const fn set_max_total_tool_output_bytes(&mut self, value: usize) {
+        self.inner.max_total_tool_output_bytes = value;
+    }
+
+    /// Max bytes of state held for one execution.
+    #[getter]
+    #[must_use]
+    pub const fn max_total_state_bytes(&self) -> usize {
+        self.inner.max_total_state_bytes
+    }
+
+    /// Set max bytes of state held for one execution.
+    #[setter]
+    pub const fn set_max_total_state_bytes(&mut self, value: usize) {
+        self.inner.max_total_state_bytes = value;
+    }
+
+    /// Max final output size in bytes.
+    #[getter]
+    #[must_use]
+    pub const fn max_output_bytes(&self) -> usize {
+        self.inner.max_output_bytes
+    }
+
+    /// Set max final output size in bytes.
+    #[setter]
+    pub const fn set_max_output_bytes(&mut self, value: usize) {
+        self.inner.max_output_bytes = value;
+    }
+
+    /// Whether results are reproducible (timing fields reported as zero).
+    #[getter]
+    #[must_use]
+    pub const fn deterministic(&self) -> bool {
+        self.inner.deterministic
+    }
+
+    /// Set whether results are reproducible.
+    #[setter]
+    pub const fn set_deterministic(&mut self, value: bool) {
+        self.inner.deterministic = value;
+    }
+
+    fn __repr__(&self) -> String {
+        let ExecutionLimits { max_operations, max_tool_calls, timeout_ms, .. } = self.inner;
+        format!(
+            "ExecutionLimits(max_operations={max_operations}, \
+             max_tool_calls={max_tool_calls}, timeout_ms={timeout_ms})"
+        )
+    }
+}
+
+// ============================================================================
+// Results
+// ============================================================================
+
+/// A tool call made during execution (Python `ToolCall`).
+#[pyclass(name = "ToolCall", module = "tool_orchestrator", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyToolCall {
+    /// Name of the tool
+    #[pyo3(get)]
+    tool_name: String,
+    /// The input as JSON, converted to Python on access
+    input: serde_json::Value,
+    /// What the script received
+    #[pyo3(get)]
+    output: String,
+    /// Whether the call succeeded
+    #[pyo3(get)]
+    success: bool,
+    /// How the call ended, e.g. `"ok"` or `"error"`
+    #[pyo3(get)]
+    status: &'static str,
+    /// Time spent in the tool in milliseconds
+    #[pyo3(get)]
+    duration_ms: u64,
+    /// Whether the call was refused before the tool ran
+    #[pyo3(get)]
+    refused: bool,
+}
+
+#[pymethods]
+impl PyToolCall {
+    /// The input the script passed, as a Python value.
+    #[getter]
+    fn input<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        json_to_py(py, &self.input)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ToolCall(tool_name={:?}, status={:?}, duration_ms={})",
+            self.tool_name, self.status, self.duration_ms
+        )
+    }
+}
+
+impl From<ToolCall> for PyToolCall {
+    fn from(call: ToolCall) -> Self {
+        Self {
+            tool_name: call.tool_name,
+            input: call.input,
+            output: call.output,
+            success: call.success,
+            status: call.status.as_str(),
+            duration_ms: call.duration_ms,
+            refused: call.refused,
+        }
+    }
+}
+
+/// The result of an execution (Python `ExecutionResult`).
+///
+/// Executions that fail, whether the script doesn't compile, runs into a
+/// limit or the limits are invalid, are reported with `success` set to
+/// `False` and the reason in `error`, like the WASM bindings do.
+#[pyclass(name = "ExecutionResult", module = "tool_orchestrator", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyExecutionResult {
+    /// Output from the script (final expression value)
+    output: String,
+    /// Whether execution completed successfully
+    success: bool,
+    /// Error message if execution failed
+    error: Option<String>,
+    /// All tool calls made during execution
+    tool_calls: Vec<PyToolCall>,
+    /// Total execution time in milliseconds
+    execution_time_ms: u64,
+    /// Summed duration of the tool calls in milliseconds
+    tool_time_ms: u64,
+    /// Time spent compiling the script in milliseconds
+    compile_ms: u64,
+    /// Unique id of this execution
+    execution_id: String,
+    /// SHA-256 of the script that ran
+    script_hash: String,
+}
+
+#[pymethods]
+impl PyExecutionResult {
+    fn __repr__(&self) -> String {
+        format!(
+            "ExecutionResult(success={}, output={:?}, error={:?}, tool_calls={}, \
+             execution_time_ms={})",
+            if self.success { "True" } else { "False" },
+            self.output,
+            self.error,
+            self.tool_calls.len(),
+            self.execution_time_ms
+        )
+    }
+}
+
+impl From<OrchestratorResult> for PyExecutionResult {
+    fn from(result: OrchestratorResult) -> Self {
+        Self {
+            output: result.output,
+            success: result.success,
+            error: result.error,
+            tool_calls: result.tool_calls.into_iter().map(PyToolCall::from).collect(),
+            execution_time_ms: result.execution_time_ms,
+            tool_time_ms: result.timing.tool_time_ms,
+            compile_ms: result.timing.compile_ms,
+            execution_id: result.execution_id,
+            script_hash: result.script_hash,
+        }
+    }
+}
+
+// ============================================================================
+// Orchestrator
+// ============================================================================
+
+/// Python tool orchestrator (Python `ToolOrchestrator`).
+///
+/// Wraps the native [`ToolOrchestrator`], with Python callables as tools.
+#[pyclass(name = "ToolOrchestrator", module = "tool_orchestrator")]
+#[derive(Default)]
+pub struct PyToolOrchestrator {
+    inner: ToolOrchestrator,
+}
+
+#[pymethods]
+impl PyToolOrchestrator {
+    /// Create a new orchestrator with no tools.
+    #[new]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a Python callable as a tool.
+    ///
+    /// The callable receives the script's argument as a Python value and
+    /// must return a `str`. Returns `True` if this replaced a tool already
+    /// registered under `name`.
+    ///
+    /// # Errors
+    ///
+    /// Raises `TypeError` if `callable` can't be called.
+    pub fn register_tool(&mut self, name: &str, callable: Bound<'_, PyAny>) -> PyResult<bool> {
+        if !callable.is_callable() {
+            return Err(PyTypeError::new_err(format!("tool '{name}' is not callable")));
+        }
+        let replaced = self.inner.registered_tools().contains(&name);
+        let callable = callable.unbind();
+        self.inner.register_executor(name, move |input| {
+            Python::with_gil(|py| call_python_tool(py, &callable, &input))
+        });
+        Ok(replaced)
+    }
+
+    /// Names of the registered tools.
+    #[must_use]
+    pub fn registered_tools(&self) -> Vec<String> {
+        self.inner.registered_tools().into_iter().map(str::to_string).collect()
+    }
+
+    /// Execute a Rhai script with the registered tools.
+    ///
+    /// Uses the default limits when `limits` is omitted. The GIL is released
+    /// while the script runs.
+    #[pyo3(signature = (script, limits = None))]
+    pub fn execute(
+        &self,
+        py: Python<'_>,
+        script: &str,
+        limits: Option<PyExecutionLimits>,
+    ) -> PyExecutionResult {
+        let limits = limits.unwrap_or_default().inner;
+        let outcome = py.allow_threads(|| self.inner.execute(script, limits));
+        outcome.unwrap_or_else(|e| OrchestratorResult::error(e.to_string(), Vec::new(), 0)).into()
+    }
+}
+
+/// Call a Python tool with `input`, turning exceptions and non-string
+/// results into tool errors.
+fn call_python_tool(
+    py: Python<'_>,
+    callable: &Py<PyAny>,
+    input: &serde_json::Value,
+) -> Result<String, String> {
+    let input = json_to_py(py, input).map_err(|e| e.to_string())?;
+    let result = callable.call1(py, (input,)).map_err(|e| e.to_string())?;
+    result.extract::<String>(py).map_err(|_| "Tool returned non-string result".to_string())
+}
+
+/// Convert a JSON value to the equivalent Python value.
+fn json_to_py<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        serde_json::Value::Null => py.None().into_bound(py),
+        serde_json::Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any(),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                i.into_pyobject(py)?.into_any()
+            } else if let Some(u) = n.as_u64() {
+                u.into_pyobject(py)?.into_any()
+            } else {
+                PyFloat::new(py, n.as_f64().unwrap_or(f64::NAN)).into_any()
+            }
+        }
+        serde_json::Value::String(s) => PyString::new(py, s).into_any(),
+        serde_json::Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_any()
+        }
+        serde_json::Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+/// The `tool_orchestrator` Python module.
+#[pymodule]
+fn tool_orchestrator(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyToolOrchestrator>()?;
+    module.add_class::<PyExecutionLimits>()?;
+    module.add_class::<PyExecutionResult>()?;
+    module.add_class::<PyToolCall>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    /// Run `code` with the module importable as `tool_orchestrator`, failing
+    /// the test on any exception, e.g. a failed `assert`.
+    fn run_python(code: &CStr) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "tool_orchestrator").unwrap();
+            tool_orchestrator(&module).unwrap();
+            let modules = py.import("sys").unwrap().getattr("modules").unwrap();
+            modules.set_item("tool_orchestrator", module).unwrap();
+            if let Err(e) = py.run(code, None, None) {
+                e.display(py);
+                panic!("{e}");
+            }
+        });
+    }
+
+    #[test]
+    fn test_register_and_execute() {
+        run_python(
+            cr#"
+from tool_orchestrator import ToolOrchestrator, ExecutionLimits
+
+orchestrator = ToolOrchestrator()
+assert orchestrator.register_tool("greet", lambda name: f"Hello, {name}!") is False
+assert orchestrator.register_tool("sorted_keys", lambda d: ",".join(sorted(d))) is False
+assert orchestrator.register_tool("greet", lambda name: f"Hi, {name}!") is True
+
+result = orchestrator.execute('greet("Claude") + " " + sorted_keys(#{b: 1, a: [true, ()]})')
+assert result.success, result
+assert result.output == "Hi, Claude! a,b", result.output
+assert result.error is None
+assert [c.tool_name for c in result.tool_calls] == ["greet", "sorted_keys"]
+assert result.tool_calls[1].input == {"a": [True, None], "b": 1}
+assert result.tool_calls[0].status == "ok"
+assert len(result.script_hash) == 64
+assert sorted(orchestrator.registered_tools()) == ["greet", "sorted_keys"]
+"#,
+        );
+    }
+
+    #[test]
+    fn test_exceptions_become_failed_calls() {
+        run_python(
+            cr#"
+from tool_orchestrator import ToolOrchestrator
+
+def broken(_):
+    raise ValueError("down")
+
+orchestrator = ToolOrchestrator()
+orchestrator.register_tool("broken", broken)
+orchestrator.register_tool("number", lambda _: 42)
+
+result = orchestrator.execute('[broken(1), number(2)]')
+assert result.success, result
+broken_call, number_call = result.tool_calls
+assert not broken_call.success and broken_call.status == "error"
+assert broken_call.output == "Tool error: ValueError: down", broken_call.output
+assert number_call.output == "Tool error: Tool returned non-string result"
+
+try:
+    orchestrator.register_tool("nope", 1)
+    raise AssertionError("registered a non-callable")
+except TypeError:
+    pass
+"#,
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        run_python(
+            cr#"
+from tool_orchestrator import ToolOrchestrator, ExecutionLimits
+
+assert ExecutionLimits().max_tool_calls == 50
+assert ExecutionLimits.quick().max_tool_calls == 10
+assert ExecutionLimits.extended().timeout_ms == 120000
+assert "max_operations=100000" in repr(ExecutionLimits())
+
+orchestrator = ToolOrchestrator()
+orchestrator.register_tool("ping", lambda _: "pong")
+
+limits = ExecutionLimits()
+limits.max_tool_calls = 1
+result = orchestrator.execute("ping(1); ping(2)", limits)
+assert result.output == "ERROR: Maximum tool calls (1) exceeded", result.output
+
+limits.max_operations = 1000
+result = orchestrator.execute("loop {}", limits)
+assert not result.success
+assert "operations" in result.error.lower(), result.error
+
+result = orchestrator.execute("let x = ;")
+assert not result.success and result.tool_calls == []
+"#,
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Regular expression functions for scripts (`regex` feature).
+//!
+//! Registers `regex_match`, `regex_find_all`, `regex_captures` and
+//! `regex_replace` so scripts can pick apart semi-structured tool output
+//! without chains of `split` and `index_of`.
+//!
+//! Patterns use the [`regex`] crate's syntax, which matches in linear time,
+//! so a hostile pattern cannot backtrack its way past the timeout. Pattern
+//! length and compiled size are still capped, and each execution caches the
+//! patterns it compiles. An invalid or oversized pattern raises a Rhai
+//! runtime error the script can `catch`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let log = fetch_logs();
+//! let errors = regex_find_all(`ERROR \[(\w+)\]`, log);
+//! let m = regex_captures(`(?P<user>\w+)@(?P<host>[\w.]+)`, "mail bob@example.com");
+//! `${errors.len()} errors, first mail to ${m.user} at ${m.host}`
+//! ```
+
+use std::collections::HashMap;
+
+#[cfg(feature = "native")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+use std::cell::RefCell;
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+use std::rc::Rc;
+
+use regex::{Regex, RegexBuilder};
+use rhai::{Array, Dynamic, EvalAltResult, FuncRegistration, ImmutableString, Map, Module};
+
+/// Maximum length of a pattern in bytes
+pub const MAX_REGEX_PATTERN_LEN: usize = 1_000;
+
+/// Maximum compiled size of a pattern in bytes (1 MB)
+const MAX_REGEX_COMPILED_SIZE: usize = 1 << 20;
+
+/// Maximum number of distinct patterns cached per execution
+const MAX_CACHED_PATTERNS: usize = 64;
+
+/// Names of the functions registered by [`regex_module`]
+pub(crate) const FUNCTION_NAMES: [&str; 4] =
+    ["regex_match", "regex_find_all", "regex_captures", "regex_replace"];
+
+/// Compiled patterns of one execution (native: `Arc<Mutex>`)
+#[cfg(feature = "native")]
+type PatternCache = Arc<Mutex<HashMap<String, Regex>>>;
+
+/// Compiled patterns of one execution (WASM: `Rc<RefCell>`)
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+type PatternCache = Rc<RefCell<HashMap<String, Regex>>>;
+
+#[cfg(feature = "native")]
+fn cached(cache: &PatternCache, pattern: &str) -> Option<Regex> {
+    let cache = cache.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    cache.get(pattern).cloned()
+}
+
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+fn cached(cache: &PatternCache, pattern: &str) -> Option<Regex> {
+    cache.borrow().get(pattern).cloned()
+}
+
+#[cfg(feature = "native")]
+fn store(cache: &PatternCache, pattern: &str, regex: &Regex) {
+    let mut cache = cache.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    if cache.len() < MAX_CACHED_PATTERNS {
+        cache.insert(pattern.to_string(), regex.clone());
+    }
+}
+
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+fn store(cache: &PatternCache, pattern: &str, regex: &Regex) {
+    let mut cache = cache.borrow_mut();
+    if cache.len() < MAX_CACHED_PATTERNS {
+        cache.insert(pattern.to_string(), regex.clone());
+    }
+}
+
+/// Get the compiled form of `pattern`, compiling and caching it if needed.
+fn compile(cache: &PatternCache, pattern: &str) -> Result<Regex, Box<EvalAltResult>> {
+    if let Some(regex) = cached(cache, pattern) {
+        return Ok(regex);
+    }
+    if pattern.len() > MAX_REGEX_PATTERN_LEN {
+        return Err(format!(
+            "regex pattern is too long ({} bytes, limit {MAX_REGEX_PATTERN_LEN})",
+            pattern.len()
+        )
+        .into());
+    }
+    let regex = RegexBuilder::new(pattern)
+        .size_limit(MAX_REGEX_COMPILED_SIZE)
+        .build()
+        .map_err(|e| format!("invalid regex `{pattern}`: {e}"))?;
+    store(cache, pattern, &regex);
+    Ok(regex)
+}
+
+/// Build a module with the regex functions and a fresh pattern cache.
+///
+/// Registered on the engine of every execution, so the cache lives exactly
+/// as long as one script run.
+pub(crate) fn regex_module() -> Module {
+    let cache = PatternCache::default();
+    let mut module = Module::new();
+
+    let c = cache.clone();
+    FuncRegistration::new("regex_match").set_into_module(
+        &mut module,
+        move |pattern: ImmutableString, text: ImmutableString| {
+            Ok::<_, Box<EvalAltResult>>(compile(&c, &pattern)?.is_match(&text))
+        },
+    );
+
+    let c = cache.clone();
+    FuncRegistration::new("regex_find_all").set_into_module(
+        &mut module,
+        move |pattern: ImmutableString, text: ImmutableString| {
+            let regex = compile(&c, &pattern)?;
+            let found: Array = regex.find_iter(&text).map(|m| m.as_str().into()).collect();
+            Ok::<_, Box<EvalAltResult>>(found)
+        },
+    );
+
+    let c = cache.clone();
+    FuncRegistration::new("regex_captures").set_into_module(
+        &mut module,
+        move |pattern: ImmutableString, text: ImmutableString| {
+            let regex = compile(&c, &pattern)?;
+            let mut map = Map::new();
+            if let Some(captures) = regex.captures(&text) {
+                // Every group by index, named groups also by name; `()` if it didn't match
+                let names = regex.capture_names();
+                for (index, (group, name)) in captures.iter().zip(names).enumerate() {
+                    let value = group.map_or(Dynamic::UNIT, |m| m.as_str().into());
+                    if let Some(name) = name {
+                        map.insert(name.into(), value.clone());
+                    }
+                    map.insert(index.to_string().into(), value);
+                }
+            }
+            Ok::<_, Box<EvalAltResult>>(map)
+        },
+    );
+
+    let c = cache;
+    FuncRegistration::new("regex_replace").set_into_module(
+        &mut module,
+        move |pattern: ImmutableString, text: ImmutableString, replacement: ImmutableString| {
+            let regex = compile(&c, &pattern)?;
+            Ok::<_, Box<EvalAltResult>>(regex.replace_all(&text, replacement.as_str()).into_owned())
+        },
+    );
+
+    module
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use crate::{ExecutionLimits, ToolOrchestrator};
+
+    fn run(script: &str) -> String {
+        ToolOrchestrator::new()
+            .execute(script, ExecutionLimits::default())
+            .unwrap()
+            .output
+    }
+
+    #[test]
+    fn test_match_find_and_replace() {
+        assert_eq!(run(r#"regex_match(`^\d+$`, "123")"#), "true");
+        assert_eq!(run(r#"regex_match(`^\d+$`, "12a")"#), "false");
+        assert_eq!(
+            run(r#"regex_find_all(`\d+`, "a1 b22 c333")"#),
+            r#"["1","22","333"]"#
+        );
+        assert_eq!(
+            run(r#"regex_replace(`(\w+)=(\w+)`, "a=1, b=2", "$2:$1")"#),
+            "1:a, 2:b"
+        );
+        assert!(ToolOrchestrator::new().validate_script(r#"regex_match("a", "a")"#).valid);
+    }
+
+    #[test]
+    fn test_captures_by_index_and_name() {
+        let output = run(
+            r#"
+            let pattern = `(?P<user>\w+)@(?P<host>[\w.]+)( \(.*\))?`;
+            let m = regex_captures(pattern, "to bob@example.com");
+            `${m["0"]}|${m.user}|${m["1"]}|${m.host}|${type_of(m["3"])}`
+            "#,
+        );
+        assert_eq!(output, "bob@example.com|bob|bob|example.com|()");
+        assert_eq!(run(r#"regex_captures(`\d`, "none").len()"#), "0");
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_catchable() {
+        let output = run(
+            r#"
+            let out = "not thrown";
+            try { regex_match("(unclosed", "x"); } catch (e) { out = `caught: ${e}`; }
+            out
+            "#,
+        );
+        assert!(output.starts_with("caught: invalid regex `(unclosed`"), "{output}");
+    }
+
+    #[test]
+    fn test_oversized_patterns_are_rejected() {
+        let long = "a".repeat(MAX_REGEX_PATTERN_LEN + 1);
+        let output = run(&format!(
+            r#"let out = ""; try {{ regex_match("{long}", "a"); }} catch (e) {{ out = e; }} out"#
+        ));
+        assert!(output.contains("regex pattern is too long"), "{output}");
+
+        // Short, but compiles to far more than the size limit
+        let output = run(
+            r#"let out = ""; try { regex_match(`(\w{100}){100}`, "a"); } catch (e) { out = e; }
+            out"#,
+        );
+        assert!(output.starts_with("invalid regex"), "{output}");
+    }
+
+    #[test]
+    fn test_patterns_are_cached() {
+        let cache = PatternCache::default();
+        let first = compile(&cache, r"\d+").unwrap();
+        assert!(cached(&cache, r"\d+").is_some());
+        assert_eq!(compile(&cache, r"\d+").unwrap().as_str(), first.as_str());
+        assert!(compile(&cache, "(").is_err());
+        assert!(cached(&cache, "(").is_none());
+    }
+}
@@ -7,16 +7,34 @@
 //! - **Runaway tool calls** - via `max_tool_calls`
 //! - **Long-running scripts** - via `timeout_ms` (real-time enforcement)
 //! - **Memory exhaustion** - via `max_string_size`, `max_array_size`, `max_map_size`
+//!   and [`SizeLimitBehavior`]
+//! - **Tool output floods** - via `max_total_tool_output_bytes`
+//! - **Oversized tool inputs** - via `max_tool_input_bytes`
+//! - **Context floods** - via `max_output_bytes` and [`OutputPolicy`]
+//! - **Oversized scripts** - via `max_script_bytes` and `max_script_lines`
+//!   (checked before parsing) and `max_functions` and `max_statements`
+//! - **Named output floods** - via `max_outputs` and `max_outputs_bytes`
+//! - **Scratch storage floods** - via `max_kv_entries` and `max_kv_bytes`
+//! - **State piling up across values** - via `max_total_state_bytes`
+//! - **Scripts spinning on tools** - via the optional [`NoProgressConfig`] guard
+//! - **Unwanted language features** - via [`LanguageRestrictions`]
 //!
 //! # Preset Profiles
 //!
-//! Three preset profiles are provided for common use cases:
+//! Four preset profiles are provided for common use cases, also available
+//! by name through [`ExecutionLimits::from_profile`] and a
+//! [`ProfileRegistry`], which can hold custom profiles too:
 //!
 //! | Profile | Max Ops | Max Tools | Timeout | Use Case |
 //! |---------|---------|-----------|---------|----------|
 //! | `quick()` | 10,000 | 10 | 5s | Simple scripts |
 //! | `default()` | 100,000 | 50 | 30s | General use |
 //! | `extended()` | 500,000 | 100 | 120s | Complex orchestration |
+//! | `unlimited()` | - | - | - | Fully trusted scripts only |
+//!
+//! Limits are checked with [`ExecutionLimits::validate`] before a script
+//! runs, so nonsensical values (such as a zero timeout) are reported as a
+//! [`LimitsError`] instead of failing the script in confusing ways.
 //!
 //! # Example
 //!
@@ -32,7 +50,10 @@
 //!     .with_timeout_ms(10_000);
 //! ```
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 // =============================================================================
 // Default Limit Constants
@@ -56,6 +77,56 @@ pub const DEFAULT_MAX_ARRAY_SIZE: usize = 10_000;
 /// Default maximum map size (number of key-value pairs)
 pub const DEFAULT_MAX_MAP_SIZE: usize = 1_000;
 
+/// Default maximum bytes a single tool call may return (10 MB)
+pub const DEFAULT_MAX_TOOL_OUTPUT_BYTES: usize = 10_000_000;
+
+/// Default maximum serialized size of a single tool call's input (1 MB)
+pub const DEFAULT_MAX_TOOL_INPUT_BYTES: usize = 1_000_000;
+
+/// Default maximum cumulative bytes returned by all tool calls (50 MB)
+pub const DEFAULT_MAX_TOTAL_TOOL_OUTPUT_BYTES: usize = 50_000_000;
+
+/// Default maximum size of the script's final output in bytes (100 KB, ~25k tokens)
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 100_000;
+
+/// Default maximum script source size in bytes (1 MB)
+pub const DEFAULT_MAX_SCRIPT_BYTES: usize = 1_000_000;
+
+/// Default maximum number of lines in the script source
+pub const DEFAULT_MAX_SCRIPT_LINES: usize = 20_000;
+
+/// Default maximum number of functions a script may define
+pub const DEFAULT_MAX_FUNCTIONS: usize = 1_000;
+
+/// Default maximum number of statements in a compiled script, including function bodies
+pub const DEFAULT_MAX_STATEMENTS: usize = 100_000;
+
+/// Default maximum number of named outputs a script may `emit`
+pub const DEFAULT_MAX_OUTPUTS: usize = 100;
+
+/// Default maximum serialized size of all named outputs in bytes (1 MB)
+pub const DEFAULT_MAX_OUTPUTS_BYTES: usize = 1_000_000;
+
+/// Default maximum number of keys in a script's key-value store
+pub const DEFAULT_MAX_KV_ENTRIES: usize = 10_000;
+
+/// Default maximum size of a script's key-value store in bytes (10 MB)
+pub const DEFAULT_MAX_KV_BYTES: usize = 10_000_000;
+
+/// Default maximum bytes of state an execution may hold, summed over tool
+/// calls, variables, outputs and the key-value store (100 MB)
+pub const DEFAULT_MAX_TOTAL_STATE_BYTES: usize = 100_000_000;
+
+/// Default number of tool calls of a `call_parallel` batch, or spawned with
+/// `spawn_tool`, that may run at once
+pub const DEFAULT_MAX_CONCURRENT_TOOL_CALLS: usize = 16;
+
+/// Default number of identical consecutive tool calls that trips the no-progress guard
+pub const DEFAULT_NO_PROGRESS_REPEATED_CALLS: usize = 5;
+
+/// Default number of consecutive failed tool calls that trips the no-progress guard
+pub const DEFAULT_NO_PROGRESS_FAILED_CALLS: usize = 10;
+
 // =============================================================================
 // Quick Profile Constants
 // =============================================================================
@@ -82,6 +153,306 @@ pub const EXTENDED_MAX_TOOL_CALLS: usize = 100;
 /// Extended profile: timeout in milliseconds (2 minutes)
 pub const EXTENDED_TIMEOUT_MS: u64 = 120_000;
 
+// =============================================================================
+// LimitsError
+// =============================================================================
+
+/// `ExecutionLimits` fields that [`ExecutionLimits::validate`] rejects when zero
+const NONZERO_FIELDS: [&str; 10] = [
+    "max_operations",
+    "timeout_ms",
+    "max_string_size",
+    "max_array_size",
+    "max_map_size",
+    "max_output_bytes",
+    "max_script_bytes",
+    "max_script_lines",
+    "max_statements",
+    "max_concurrent_tool_calls",
+];
+
+/// Reason an [`ExecutionLimits`] value cannot be used.
+///
+/// Serializes as `{"type": "zero_limit", "details": {"field": "timeout_ms"}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "type", content = "details", rename_all = "snake_case")]
+pub enum LimitsError {
+    /// A limit is zero, which would make every script fail (or, for Rhai's
+    /// size limits, silently disable the limit).
+    #[error(
+        "{field} must be greater than 0; use ExecutionLimits::unlimited() to remove limits"
+    )]
+    ZeroLimit {
+        /// Name of the offending `ExecutionLimits` field
+        #[serde(deserialize_with = "deserialize_nonzero_field")]
+        field: StaticName,
+    },
+
+    /// `max_tool_calls` is zero but the script calls a registered tool.
+    #[error("max_tool_calls is 0 but the script calls the `{tool}` tool")]
+    ToolCallsDisabled {
+        /// First tool the script calls
+        tool: String,
+    },
+
+    /// `max_total_cost` is negative or NaN.
+    #[error("max_total_cost must be a non-negative number")]
+    InvalidCost,
+
+    /// No profile is known by this name.
+    #[error("unknown limits profile `{name}`; known profiles: {}", known.join(", "))]
+    UnknownProfile {
+        /// Name that was looked up
+        name: String,
+        /// Names of the profiles that do exist
+        known: Vec<String>,
+    },
+}
+
+/// A `&'static str` that serde does not try to borrow from the input.
+///
+/// Serde implicitly borrows fields spelled `&str`, which would make errors
+/// deserializable only from `'static` data; these are looked up in a list
+/// of known names instead, see [`known_name`].
+pub(crate) type StaticName = &'static str;
+
+/// Deserialize a field name of [`LimitsError::ZeroLimit`].
+fn deserialize_nonzero_field<'de, D>(deserializer: D) -> Result<&'static str, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    known_name(&name, &NONZERO_FIELDS)
+}
+
+/// Find `name` among `known`, for deserializing `&'static str` fields.
+pub(crate) fn known_name<E: serde::de::Error>(
+    name: &str,
+    known: &'static [&'static str],
+) -> Result<&'static str, E> {
+    known
+        .iter()
+        .find(|k| **k == name)
+        .copied()
+        .ok_or_else(|| E::unknown_variant(name, known))
+}
+
+// =============================================================================
+// OutputPolicy
+// =============================================================================
+
+/// What to do when a script's final output exceeds `max_output_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum OutputPolicy {
+    /// Keep the start of the output and append a `…[truncated N bytes]` marker
+    #[default]
+    Truncate,
+    /// Fail the execution with `OrchestratorError::OutputTooLarge`
+    Error,
+}
+
+// =============================================================================
+// SizeLimitBehavior
+// =============================================================================
+
+/// What to do when a collection would grow past `max_array_size` or
+/// `max_map_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SizeLimitBehavior {
+    /// Fail the execution, or the injected variable, on the first oversized
+    /// collection
+    #[default]
+    Abort,
+    /// Cut collections down to the limit and record an
+    /// `ExecutionWarning::CollectionTruncated`, where the orchestrator builds
+    /// or grows them: injected variables, `parse_json` results (the way tool
+    /// output usually becomes data), and `push` and `insert` on arrays. A
+    /// collection grown any other way, such as with `+=`, a map index or
+    /// `map`, still aborts the execution.
+    TruncateWithWarning,
+}
+
+// =============================================================================
+// LanguageRestrictions
+// =============================================================================
+
+/// Keywords that start a restricted construct, with how to name it in an error.
+const RESTRICTED_SYMBOLS: [(&str, &str); 5] = [
+    ("while", "'while' loops are"),
+    ("loop", "'loop' loops are"),
+    ("do", "'do' loops are"),
+    ("fn", "function definitions are"),
+    ("eval", "'eval' is"),
+];
+
+/// Rhai language features a script may use.
+///
+/// Everything is allowed by default. A script using a forbidden construct
+/// fails to compile with `OrchestratorError::CompilationError`, naming the
+/// construct and its position. Set with
+/// [`ExecutionLimits::with_language_restrictions`].
+///
+/// # Example
+///
+/// ```ignore
+/// // Only bounded `for` loops, and no code built at run time
+/// let restrictions = LanguageRestrictions::default()
+///     .with_unbounded_loops(false)
+///     .with_eval(false);
+/// let limits = ExecutionLimits::default().with_language_restrictions(restrictions);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct LanguageRestrictions {
+    /// `while`, `loop` and `do` loops; `for` loops are always allowed
+    pub allow_unbounded_loops: bool,
+    /// Anonymous functions such as `|x| x + 1`
+    pub allow_closures: bool,
+    /// Functions defined with `fn`
+    pub allow_function_definitions: bool,
+    /// `eval` of code built while the script runs
+    pub allow_eval: bool,
+}
+
+impl Default for LanguageRestrictions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LanguageRestrictions {
+    /// Allow every language feature.
+    ///
+    /// Equivalent to [`LanguageRestrictions::default()`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            allow_unbounded_loops: true,
+            allow_closures: true,
+            allow_function_definitions: true,
+            allow_eval: true,
+        }
+    }
+
+    /// Allow or forbid `while`, `loop` and `do` loops (builder pattern).
+    #[must_use]
+    pub const fn with_unbounded_loops(mut self, allow: bool) -> Self {
+        self.allow_unbounded_loops = allow;
+        self
+    }
+
+    /// Allow or forbid anonymous functions (builder pattern).
+    #[must_use]
+    pub const fn with_closures(mut self, allow: bool) -> Self {
+        self.allow_closures = allow;
+        self
+    }
+
+    /// Allow or forbid `fn` definitions (builder pattern).
+    #[must_use]
+    pub const fn with_function_definitions(mut self, allow: bool) -> Self {
+        self.allow_function_definitions = allow;
+        self
+    }
+
+    /// Allow or forbid `eval` (builder pattern).
+    #[must_use]
+    pub const fn with_eval(mut self, allow: bool) -> Self {
+        self.allow_eval = allow;
+        self
+    }
+
+    fn allows(&self, symbol: &str) -> bool {
+        match symbol {
+            "while" | "loop" | "do" => self.allow_unbounded_loops,
+            "fn" => self.allow_function_definitions,
+            "eval" => self.allow_eval,
+            _ => true,
+        }
+    }
+
+    /// How to name the construct `symbol` starts, if it is forbidden, e.g.
+    /// `'while' loops are`.
+    pub(crate) fn forbidden_construct(&self, symbol: &str) -> Option<&'static str> {
+        RESTRICTED_SYMBOLS
+            .iter()
+            .find(|(restricted, _)| *restricted == symbol && !self.allows(symbol))
+            .map(|(_, construct)| *construct)
+    }
+
+    fn apply_to(&self, engine: &mut rhai::Engine) {
+        for (symbol, _) in RESTRICTED_SYMBOLS {
+            if !self.allows(symbol) {
+                engine.disable_symbol(symbol);
+            }
+        }
+    }
+}
+
+// =============================================================================
+// NoProgressConfig
+// =============================================================================
+
+/// When to stop a script that keeps calling tools without getting anywhere.
+///
+/// Enabled with [`ExecutionLimits::with_no_progress_guard`]. The script is
+/// terminated with `OrchestratorError::NoProgress` as soon as either pattern
+/// shows up in its call log; a threshold of zero disables that pattern.
+/// Refused and cached calls count like any other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct NoProgressConfig {
+    /// Stop after this many consecutive calls to the same tool with the same input
+    pub max_repeated_calls: usize,
+    /// Stop after this many consecutive failed calls, whatever the tools
+    pub max_failed_calls: usize,
+}
+
+impl Default for NoProgressConfig {
+    fn default() -> Self {
+        Self {
+            max_repeated_calls: DEFAULT_NO_PROGRESS_REPEATED_CALLS,
+            max_failed_calls: DEFAULT_NO_PROGRESS_FAILED_CALLS,
+        }
+    }
+}
+
+impl NoProgressConfig {
+    /// Set the number of identical consecutive calls that stops the script
+    /// (builder pattern).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let guard = NoProgressConfig::default().with_max_repeated_calls(3);
+    /// ```
+    #[must_use]
+    pub const fn with_max_repeated_calls(mut self, max: usize) -> Self {
+        self.max_repeated_calls = max;
+        self
+    }
+
+    /// Set the number of consecutive failed calls that stops the script
+    /// (builder pattern).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let guard = NoProgressConfig::default().with_max_failed_calls(0);
+    /// ```
+    #[must_use]
+    pub const fn with_max_failed_calls(mut self, max: usize) -> Self {
+        self.max_failed_calls = max;
+        self
+    }
+}
+
 // =============================================================================
 // ExecutionLimits
 // =============================================================================
@@ -102,7 +473,15 @@ pub const EXTENDED_TIMEOUT_MS: u64 = 120_000;
 ///
 /// This struct derives `Serialize` and `Deserialize` for easy configuration
 /// storage and transmission (e.g., in JSON config files or API requests).
+/// Fields missing from the serialized form fall back to their defaults.
+///
+/// New limits are added as fields, so the struct is `#[non_exhaustive]`:
+/// start from [`ExecutionLimits::default`] or a preset and adjust it with the
+/// `with_*` builders or by assigning fields.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+#[non_exhaustive]
 pub struct ExecutionLimits {
     /// Maximum number of operations (prevents infinite loops)
     pub max_operations: u64,
@@ -116,6 +495,52 @@ pub struct ExecutionLimits {
     pub max_array_size: usize,
     /// Maximum map size
     pub max_map_size: usize,
+    /// Maximum bytes a single tool call may return, text or binary
+    pub max_tool_output_bytes: usize,
+    /// Maximum size of a single tool call's input, serialized as JSON
+    pub max_tool_input_bytes: usize,
+    /// Maximum cumulative bytes returned by all tool calls in one execution
+    pub max_total_tool_output_bytes: usize,
+    /// Maximum size of the script's final output in bytes
+    pub max_output_bytes: usize,
+    /// How to handle final output larger than `max_output_bytes`
+    pub output_policy: OutputPolicy,
+    /// How to handle collections larger than `max_array_size` or `max_map_size`
+    pub size_limit_behavior: SizeLimitBehavior,
+    /// Maximum summed cost of the tool calls in one execution (`None` for no budget)
+    pub max_total_cost: Option<f64>,
+    /// Produce reproducible results (timing fields are reported as zero)
+    pub deterministic: bool,
+    /// Maximum script source size in bytes, checked before compilation
+    pub max_script_bytes: usize,
+    /// Maximum number of lines in the script source, checked before compilation
+    pub max_script_lines: usize,
+    /// Maximum number of functions the script may define
+    pub max_functions: usize,
+    /// Maximum number of statements in the compiled script, including function bodies
+    pub max_statements: usize,
+    /// Refuse to start a tool call with less than this much of `timeout_ms` left (0 to disable)
+    pub min_remaining_ms_for_tool_call: u64,
+    /// Maximum number of distinct names the script may `emit`
+    pub max_outputs: usize,
+    /// Maximum serialized size of all emitted outputs in bytes, names included
+    pub max_outputs_bytes: usize,
+    /// Maximum number of keys in the key-value store scripts write with `kv_set`
+    pub max_kv_entries: usize,
+    /// Maximum serialized size of the key-value store in bytes, keys included
+    pub max_kv_bytes: usize,
+    /// Maximum bytes of state the orchestrator holds for one execution: logged
+    /// tool calls, injected variables, emitted outputs and the key-value store
+    pub max_total_state_bytes: usize,
+    /// Maximum number of tool calls running at once on their own threads,
+    /// per `call_parallel` batch and across calls started by `spawn_tool`
+    pub max_concurrent_tool_calls: usize,
+    /// Operations charged against `max_operations` for each counted tool call
+    pub tool_call_operation_cost: u64,
+    /// Stop scripts that keep repeating a call or failing (`None`, the default, to never stop)
+    pub no_progress_guard: Option<NoProgressConfig>,
+    /// Language features scripts may use (all of them by default)
+    pub language: LanguageRestrictions,
 }
 
 impl Default for ExecutionLimits {
@@ -127,6 +552,28 @@ impl Default for ExecutionLimits {
             max_string_size: DEFAULT_MAX_STRING_SIZE,
             max_array_size: DEFAULT_MAX_ARRAY_SIZE,
             max_map_size: DEFAULT_MAX_MAP_SIZE,
+            max_tool_output_bytes: DEFAULT_MAX_TOOL_OUTPUT_BYTES,
+            max_tool_input_bytes: DEFAULT_MAX_TOOL_INPUT_BYTES,
+            max_total_tool_output_bytes: DEFAULT_MAX_TOTAL_TOOL_OUTPUT_BYTES,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            output_policy: OutputPolicy::Truncate,
+            size_limit_behavior: SizeLimitBehavior::Abort,
+            max_total_cost: None,
+            deterministic: false,
+            max_script_bytes: DEFAULT_MAX_SCRIPT_BYTES,
+            max_script_lines: DEFAULT_MAX_SCRIPT_LINES,
+            max_functions: DEFAULT_MAX_FUNCTIONS,
+            max_statements: DEFAULT_MAX_STATEMENTS,
+            min_remaining_ms_for_tool_call: 0,
+            max_outputs: DEFAULT_MAX_OUTPUTS,
+            max_outputs_bytes: DEFAULT_MAX_OUTPUTS_BYTES,
+            max_kv_entries: DEFAULT_MAX_KV_ENTRIES,
+            max_kv_bytes: DEFAULT_MAX_KV_BYTES,
+            max_total_state_bytes: DEFAULT_MAX_TOTAL_STATE_BYTES,
+            max_concurrent_tool_calls: DEFAULT_MAX_CONCURRENT_TOOL_CALLS,
+            tool_call_operation_cost: 0,
+            no_progress_guard: None,
+            language: LanguageRestrictions::default(),
         }
     }
 }
@@ -140,6 +587,15 @@ impl ExecutionLimits {
         Self::default()
     }
 
+    /// JSON Schema describing the serialized form of the limits.
+    ///
+    /// Every field is optional, since missing fields take their defaults.
+    #[cfg(feature = "schema")]
+    #[must_use]
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(Self).to_value()
+    }
+
     /// Create quick execution limits for simple scripts.
     ///
     /// Suitable for short, trusted scripts that need fast execution:
@@ -186,6 +642,150 @@ impl ExecutionLimits {
         }
     }
 
+    /// Create limits that never stop a script.
+    ///
+    /// Rhai's operation and size limits are disabled, the timeout never
+    /// fires, and tools may be called any number of times with any amount
+    /// of output.
+    ///
+    /// # Risks
+    ///
+    /// Only use this for scripts you trust completely. An infinite loop
+    /// spins until the process is killed, a runaway allocation can exhaust
+    /// memory, and every tool call's side effects and costs go unbounded.
+    /// Prefer [`extended`](Self::extended) for anything an LLM wrote.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::unlimited();
+    /// let result = orchestrator.execute(trusted_script, limits)?;
+    /// ```
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_operations: u64::MAX,
+            max_tool_calls: usize::MAX,
+            timeout_ms: u64::MAX,
+            max_string_size: usize::MAX,
+            max_array_size: usize::MAX,
+            max_map_size: usize::MAX,
+            max_tool_output_bytes: usize::MAX,
+            max_tool_input_bytes: usize::MAX,
+            max_total_tool_output_bytes: usize::MAX,
+            max_output_bytes: usize::MAX,
+            output_policy: OutputPolicy::Truncate,
+            size_limit_behavior: SizeLimitBehavior::Abort,
+            max_total_cost: None,
+            deterministic: false,
+            max_script_bytes: usize::MAX,
+            max_script_lines: usize::MAX,
+            max_functions: usize::MAX,
+            max_statements: usize::MAX,
+            min_remaining_ms_for_tool_call: 0,
+            max_outputs: usize::MAX,
+            max_outputs_bytes: usize::MAX,
+            max_kv_entries: usize::MAX,
+            max_kv_bytes: usize::MAX,
+            max_total_state_bytes: usize::MAX,
+            // Threads are a resource of the host, not of the script
+            max_concurrent_tool_calls: DEFAULT_MAX_CONCURRENT_TOOL_CALLS,
+            tool_call_operation_cost: 0,
+            no_progress_guard: None,
+            language: LanguageRestrictions::new(),
+        }
+    }
+
+    /// Names of the preset profiles, as accepted by
+    /// [`from_profile`](Self::from_profile).
+    pub const PROFILES: [&'static str; 4] = ["quick", "default", "extended", "unlimited"];
+
+    /// Look up a preset profile by name.
+    ///
+    /// Returns `None` for names other than those in [`PROFILES`](Self::PROFILES);
+    /// use a [`ProfileRegistry`] to add custom profiles and to get an error
+    /// listing the known names.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::from_profile("quick").unwrap();
+    /// assert_eq!(limits.timeout_ms, 5_000);
+    /// ```
+    #[must_use]
+    pub fn from_profile(name: &str) -> Option<Self> {
+        match name {
+            "quick" => Some(Self::quick()),
+            "default" => Some(Self::default()),
+            "extended" => Some(Self::extended()),
+            "unlimited" => Some(Self::unlimited()),
+            _ => None,
+        }
+    }
+
+    /// Check that these limits allow a script to run at all.
+    ///
+    /// Called automatically at the start of every execution. Zero is
+    /// rejected for `max_operations`, `timeout_ms`, `max_output_bytes`,
+    /// `max_statements` and the size limits; a zero `max_tool_calls` is only
+    /// rejected once the script is known to call a tool, and a zero
+    /// `max_functions` or `max_outputs` simply forbids function definitions
+    /// or `emit` calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LimitsError::ZeroLimit`] naming the first zero field, or
+    /// [`LimitsError::InvalidCost`] for a negative or NaN `max_total_cost`.
+    pub const fn validate(&self) -> Result<(), LimitsError> {
+        if let Some(cost) = self.max_total_cost {
+            if cost.is_nan() || cost < 0.0 {
+                return Err(LimitsError::InvalidCost);
+            }
+        }
+
+        let field = if self.max_operations == 0 {
+            "max_operations"
+        } else if self.timeout_ms == 0 {
+            "timeout_ms"
+        } else if self.max_string_size == 0 {
+            "max_string_size"
+        } else if self.max_array_size == 0 {
+            "max_array_size"
+        } else if self.max_map_size == 0 {
+            "max_map_size"
+        } else if self.max_output_bytes == 0 {
+            "max_output_bytes"
+        } else if self.max_script_bytes == 0 {
+            "max_script_bytes"
+        } else if self.max_script_lines == 0 {
+            "max_script_lines"
+        } else if self.max_statements == 0 {
+            "max_statements"
+        } else if self.max_concurrent_tool_calls == 0 {
+            "max_concurrent_tool_calls"
+        } else {
+            return Ok(());
+        };
+        Err(LimitsError::ZeroLimit { field })
+    }
+
+    /// Apply the operation and size limits to a Rhai engine.
+    ///
+    /// `u64::MAX` operations and sizes no allocation could reach (above
+    /// `isize::MAX`, e.g. `usize::MAX` on 32-bit WASM) are clamped to Rhai's
+    /// "unlimited" setting rather than passed through.
+    pub(crate) fn apply_to(&self, engine: &mut rhai::Engine) {
+        engine.set_max_operations(if self.max_operations == u64::MAX {
+            0
+        } else {
+            self.max_operations
+        });
+        engine.set_max_string_size(rhai_size_limit(self.max_string_size));
+        engine.set_max_array_size(rhai_size_limit(self.max_array_size));
+        engine.set_max_map_size(rhai_size_limit(self.max_map_size));
+        self.language.apply_to(engine);
+    }
+
     /// Set maximum operations (builder pattern).
     ///
     /// Controls how many Rhai operations (expressions, statements) can execute
@@ -287,61 +887,651 @@ impl ExecutionLimits {
         self.max_map_size = size;
         self
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_default_limits() {
-        let limits = ExecutionLimits::default();
-        assert_eq!(limits.max_operations, DEFAULT_MAX_OPERATIONS);
-        assert_eq!(limits.max_tool_calls, DEFAULT_MAX_TOOL_CALLS);
-        assert_eq!(limits.timeout_ms, DEFAULT_TIMEOUT_MS);
+    /// Set maximum bytes a single tool call may return (builder pattern).
+    ///
+    /// Binary outputs count their raw length, text its length in UTF-8.
+    /// A call whose output is larger fails with a tool error instead of
+    /// handing the output to the script; later calls are unaffected.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_tool_output_bytes(1_000_000); // 1MB
+    /// ```
+    #[must_use]
+    pub const fn with_max_tool_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_tool_output_bytes = bytes;
+        self
     }
 
-    #[test]
-    fn test_quick_limits() {
-        let limits = ExecutionLimits::quick();
-        assert_eq!(limits.max_operations, QUICK_MAX_OPERATIONS);
-        assert_eq!(limits.max_tool_calls, QUICK_MAX_TOOL_CALLS);
+    /// Set maximum size of a single tool call's input in bytes (builder pattern).
+    ///
+    /// The input is measured as compact JSON, after conversion from the
+    /// script's value, so nested maps and arrays count in full. A larger
+    /// input never reaches the executor: the call is recorded as a refused
+    /// [`ToolCall`](crate::ToolCall) with status `limit_exceeded` and fails
+    /// like any other tool error. Tools registered with
+    /// [`register_executor_with_max_input_bytes`] use their own limit instead.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_tool_input_bytes(64_000); // 64KB
+    /// ```
+    ///
+    /// [`register_executor_with_max_input_bytes`]:
+    ///     crate::ToolOrchestrator::register_executor_with_max_input_bytes
+    #[must_use]
+    pub const fn with_max_tool_input_bytes(mut self, bytes: usize) -> Self {
+        self.max_tool_input_bytes = bytes;
+        self
     }
 
-    #[test]
-    fn test_extended_limits() {
-        let limits = ExecutionLimits::extended();
-        assert_eq!(limits.max_operations, EXTENDED_MAX_OPERATIONS);
-        assert_eq!(limits.max_tool_calls, EXTENDED_MAX_TOOL_CALLS);
+    /// Set maximum cumulative tool output in bytes (builder pattern).
+    ///
+    /// Bounds the total volume of data all tools may return during a single
+    /// execution. The call whose output would exceed the budget fails, and
+    /// every later tool call is refused without being executed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_total_tool_output_bytes(2_500_000); // 2.5MB
+    /// ```
+    #[must_use]
+    pub const fn with_max_total_tool_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_total_tool_output_bytes = bytes;
+        self
     }
 
-    #[test]
-    fn test_builder_pattern() {
-        let limits = ExecutionLimits::default()
-            .with_max_operations(50_000)
-            .with_max_tool_calls(25)
-            .with_timeout_ms(10_000);
-
-        assert_eq!(limits.max_operations, 50_000);
-        assert_eq!(limits.max_tool_calls, 25);
-        assert_eq!(limits.timeout_ms, 10_000);
+    /// Set maximum size of the script's final output in bytes (builder pattern).
+    ///
+    /// Keeps a script from flooding the model's context with a huge return
+    /// value. What happens to larger output is decided by the
+    /// [`OutputPolicy`]. To budget in tokens, multiply by four (see
+    /// [`estimate_tokens`](crate::estimate_tokens)).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_output_bytes(2_000 * 4); // ~2k tokens
+    /// ```
+    #[must_use]
+    pub const fn with_max_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_output_bytes = bytes;
+        self
     }
 
-    #[test]
-    fn test_new_equals_default() {
-        let new_limits = ExecutionLimits::new();
-        let default_limits = ExecutionLimits::default();
+    /// Set the cost budget for tool calls (builder pattern).
+    ///
+    /// Each call adds the cost its tool was registered with (see
+    /// `ToolOrchestrator::register_executor_with_cost`; other tools are
+    /// free). A call that would take the total over `cost` is refused without
+    /// being executed, so cheap tools can keep running after an expensive one
+    /// is turned away. This complements `max_tool_calls`, which still applies.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_total_cost(10.0); // e.g. ten LLM calls, or any number of cache lookups
+    /// ```
+    #[must_use]
+    pub const fn with_max_total_cost(mut self, cost: f64) -> Self {
+        self.max_total_cost = Some(cost);
+        self
+    }
 
-        assert_eq!(new_limits.max_operations, default_limits.max_operations);
-        assert_eq!(new_limits.max_tool_calls, default_limits.max_tool_calls);
-        assert_eq!(new_limits.timeout_ms, default_limits.timeout_ms);
-        assert_eq!(new_limits.max_string_size, default_limits.max_string_size);
-        assert_eq!(new_limits.max_array_size, default_limits.max_array_size);
-        assert_eq!(new_limits.max_map_size, default_limits.max_map_size);
+    /// Set how oversized final output is handled (builder pattern).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_output_bytes(10_000)
+    ///     .with_output_policy(OutputPolicy::Error);
+    /// ```
+    #[must_use]
+    pub const fn with_output_policy(mut self, policy: OutputPolicy) -> Self {
+        self.output_policy = policy;
+        self
     }
 
-    #[test]
-    fn test_with_max_string_size() {
+    /// Set how oversized arrays and maps are handled (builder pattern).
+    ///
+    /// With [`SizeLimitBehavior::TruncateWithWarning`], a script collecting
+    /// more rows than `max_array_size` keeps the first ones and carries on
+    /// instead of failing; see the variant for where this applies.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_array_size(1_000)
+    ///     .with_size_limit_behavior(SizeLimitBehavior::TruncateWithWarning);
+    /// ```
+    #[must_use]
+    pub const fn with_size_limit_behavior(mut self, behavior: SizeLimitBehavior) -> Self {
+        self.size_limit_behavior = behavior;
+        self
+    }
+
+    /// Enable deterministic mode (builder pattern).
+    ///
+    /// Running the same script against tools that return the same outputs
+    /// then yields byte-for-byte identical results: `execution_time_ms` and
+    /// every `ToolCall::duration_ms` are reported as zero. Map keys are always
+    /// emitted in sorted order, so tool inputs are stable regardless of this
+    /// setting. The timeout is still enforced against wall-clock time.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default().with_deterministic(true);
+    /// let a = orchestrator.execute(script, limits)?;
+    /// let b = orchestrator.execute(script, limits)?;
+    /// assert_eq!(serde_json::to_string(&a)?, serde_json::to_string(&b)?);
+    /// ```
+    #[must_use]
+    pub const fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Set maximum script source size in bytes (builder pattern).
+    ///
+    /// Checked before the script is parsed, so an oversized script is
+    /// rejected without spending time compiling it.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_script_bytes(64_000); // 64KB
+    /// ```
+    #[must_use]
+    pub const fn with_max_script_bytes(mut self, bytes: usize) -> Self {
+        self.max_script_bytes = bytes;
+        self
+    }
+
+    /// Set maximum number of lines in the script source (builder pattern).
+    ///
+    /// Checked before the script is parsed, like `max_script_bytes`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_script_lines(500);
+    /// ```
+    #[must_use]
+    pub const fn with_max_script_lines(mut self, lines: usize) -> Self {
+        self.max_script_lines = lines;
+        self
+    }
+
+    /// Set maximum number of functions a script may define (builder pattern).
+    ///
+    /// Checked after compilation; the prelude's helpers do not count. Zero
+    /// forbids function definitions entirely.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_functions(10);
+    /// ```
+    #[must_use]
+    pub const fn with_max_functions(mut self, max: usize) -> Self {
+        self.max_functions = max;
+        self
+    }
+
+    /// Set maximum number of statements in the compiled script (builder pattern).
+    ///
+    /// Checked after compilation, counting statements in function bodies
+    /// too. Unlike `max_operations`, this bounds the size of the script
+    /// rather than how long it runs.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_statements(5_000);
+    /// ```
+    #[must_use]
+    pub const fn with_max_statements(mut self, max: usize) -> Self {
+        self.max_statements = max;
+        self
+    }
+
+    /// Set the time a tool call needs left on the clock to start (builder pattern).
+    ///
+    /// A running executor cannot be interrupted, so a call started just
+    /// before the timeout overruns it. With this set, a call made with less
+    /// than `ms` of `timeout_ms` remaining is refused instead: it is recorded
+    /// as a failed [`ToolCall`](crate::ToolCall) with `refused` set and the
+    /// script receives a "deadline exceeded before dispatch" tool error.
+    /// Tools registered with an expected duration (see
+    /// `ToolOrchestrator::register_executor_with_expected_duration`) need at
+    /// least that long.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_timeout_ms(10_000)
+    ///     .with_min_remaining_ms_for_tool_call(500);
+    /// ```
+    #[must_use]
+    pub const fn with_min_remaining_ms_for_tool_call(mut self, ms: u64) -> Self {
+        self.min_remaining_ms_for_tool_call = ms;
+        self
+    }
+
+    /// Set maximum number of named outputs a script may `emit` (builder pattern).
+    ///
+    /// Re-emitting an existing name replaces its value and does not count
+    /// again. Zero forbids `emit` entirely.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_outputs(10);
+    /// ```
+    #[must_use]
+    pub const fn with_max_outputs(mut self, max: usize) -> Self {
+        self.max_outputs = max;
+        self
+    }
+
+    /// Set maximum serialized size of all named outputs in bytes (builder pattern).
+    ///
+    /// Measured as the length of each name plus its value's compact JSON.
+    /// An `emit` that would exceed it raises a runtime error the script can
+    /// `catch`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_outputs_bytes(64_000);
+    /// ```
+    #[must_use]
+    pub const fn with_max_outputs_bytes(mut self, max: usize) -> Self {
+        self.max_outputs_bytes = max;
+        self
+    }
+
+    /// Set maximum number of keys in the script's key-value store (builder pattern).
+    ///
+    /// Overwriting an existing key does not count again. Zero forbids
+    /// `kv_set` entirely.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_kv_entries(1_000);
+    /// ```
+    #[must_use]
+    pub const fn with_max_kv_entries(mut self, max: usize) -> Self {
+        self.max_kv_entries = max;
+        self
+    }
+
+    /// Set maximum size of the script's key-value store in bytes (builder pattern).
+    ///
+    /// Measured as the length of each key plus its value's compact JSON, and
+    /// checked on every `kv_set`, against what an
+    /// [`OrchestratorSession`](crate::OrchestratorSession) kept from earlier
+    /// executions too.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_kv_bytes(1_000_000);
+    /// ```
+    #[must_use]
+    pub const fn with_max_kv_bytes(mut self, max: usize) -> Self {
+        self.max_kv_bytes = max;
+        self
+    }
+
+    /// Set maximum bytes of state held for one execution (builder pattern).
+    ///
+    /// The per-value limits don't stop a script from holding a thousand
+    /// values each just under them. This budget sums what the orchestrator
+    /// itself keeps: every logged tool call (its name, input JSON and
+    /// output, so retained tool output counts here), the injected
+    /// variables, the emitted outputs and the key-value store, measured
+    /// like `max_outputs_bytes` and `max_kv_bytes`. Once the total passes
+    /// `max`, the script stops at its next operation with
+    /// `OrchestratorError::MemoryBudgetExceeded`, which `try` can't catch.
+    ///
+    /// The budget is approximate, and values the script builds itself, such
+    /// as strings it concatenates, aren't counted; `max_string_size`,
+    /// `max_array_size` and `max_map_size` bound those.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_total_state_bytes(20_000_000);
+    /// ```
+    #[must_use]
+    pub const fn with_max_total_state_bytes(mut self, max: usize) -> Self {
+        self.max_total_state_bytes = max;
+        self
+    }
+
+    /// Set how many tool calls may run at once (builder pattern).
+    ///
+    /// Natively, the calls of a `call_parallel` batch run on a pool of at
+    /// most `max` worker threads, and the rest of the batch waits for a free
+    /// worker. Likewise at most `max` calls started by `spawn_tool` run at
+    /// once; spawning another first waits for the oldest one to finish. This
+    /// bounds the threads an execution starts, not how many calls it makes;
+    /// `max_tool_calls` does that. It has no effect under
+    /// WASM, where calls run one after another.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_concurrent_tool_calls(4);
+    /// ```
+    #[must_use]
+    pub const fn with_max_concurrent_tool_calls(mut self, max: usize) -> Self {
+        self.max_concurrent_tool_calls = max;
+        self
+    }
+
+    /// Charge each tool call this many operations (builder pattern).
+    ///
+    /// Rhai only counts the script's own operations, so a script that spends
+    /// its time waiting on tools barely touches `max_operations`. With a cost
+    /// set, every tool call that counts against `max_tool_calls` also adds
+    /// `cost` operations, and the script is stopped with
+    /// `MaxOperationsExceeded` once the combined total passes
+    /// `max_operations`. The call that crosses the limit still runs; the
+    /// script stops at its next operation. Zero, the default, charges nothing.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_max_operations(100_000)
+    ///     .with_tool_call_operation_cost(5_000);
+    /// ```
+    #[must_use]
+    pub const fn with_tool_call_operation_cost(mut self, cost: u64) -> Self {
+        self.tool_call_operation_cost = cost;
+        self
+    }
+
+    /// Stop scripts that stop making progress (builder pattern).
+    ///
+    /// Some runaway scripts never trip `max_operations`: they spin calling
+    /// the same tool with the same input, or keep retrying tools that fail,
+    /// until the timeout. With a guard set, the script is terminated with
+    /// `OrchestratorError::NoProgress` as soon as its call log shows either
+    /// pattern; the calls made so far are kept in the error result passed to
+    /// the `on_execution_end` hooks. Off by default.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_no_progress_guard(NoProgressConfig::default().with_max_failed_calls(5));
+    /// ```
+    #[must_use]
+    pub const fn with_no_progress_guard(mut self, config: NoProgressConfig) -> Self {
+        self.no_progress_guard = Some(config);
+        self
+    }
+
+    /// Restrict the language features scripts may use (builder pattern).
+    ///
+    /// Everything is allowed by default; see [`LanguageRestrictions`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let limits = ExecutionLimits::default()
+    ///     .with_language_restrictions(LanguageRestrictions::default().with_closures(false));
+    /// ```
+    #[must_use]
+    pub const fn with_language_restrictions(mut self, restrictions: LanguageRestrictions) -> Self {
+        self.language = restrictions;
+        self
+    }
+}
+
+// =============================================================================
+// ProfileRegistry
+// =============================================================================
+
+/// Named [`ExecutionLimits`], so callers can ask for `"batch"` instead of
+/// passing numbers.
+///
+/// Starts out with the presets of [`ExecutionLimits::from_profile`]. A
+/// profile registered under the name of a preset replaces it.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut profiles = ProfileRegistry::new();
+/// profiles.register_profile("batch", ExecutionLimits::extended().with_max_tool_calls(500));
+///
+/// let limits = profiles.resolve("batch")?;
+/// assert!(profiles.resolve("btach").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProfileRegistry {
+    custom: BTreeMap<String, ExecutionLimits>,
+}
+
+impl ProfileRegistry {
+    /// Create a registry holding only the preset profiles.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a profile, replacing any profile of the same name.
+    pub fn register_profile(&mut self, name: impl Into<String>, limits: ExecutionLimits) {
+        self.custom.insert(name.into(), limits);
+    }
+
+    /// Look up a profile by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LimitsError::UnknownProfile`], listing the known profiles,
+    /// if no profile has this name.
+    pub fn resolve(&self, name: &str) -> Result<ExecutionLimits, LimitsError> {
+        self.custom
+            .get(name)
+            .copied()
+            .or_else(|| ExecutionLimits::from_profile(name))
+            .ok_or_else(|| LimitsError::UnknownProfile {
+                name: name.to_string(),
+                known: self.names(),
+            })
+    }
+
+    /// Names of every profile: the presets, then custom profiles in
+    /// alphabetical order.
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        let presets = ExecutionLimits::PROFILES;
+        let custom = self.custom.keys().filter(|name| !presets.contains(&name.as_str()));
+        presets.iter().map(ToString::to_string).chain(custom.cloned()).collect()
+    }
+}
+
+/// Map a size limit to Rhai, where 0 means unlimited.
+const fn rhai_size_limit(size: usize) -> usize {
+    if size > isize::MAX.unsigned_abs() { 0 } else { size }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_limits() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_operations, DEFAULT_MAX_OPERATIONS);
+        assert_eq!(limits.max_tool_calls, DEFAULT_MAX_TOOL_CALLS);
+        assert_eq!(limits.timeout_ms, DEFAULT_TIMEOUT_MS);
+        assert_eq!(limits.tool_call_operation_cost, 0);
+        assert_eq!(limits.no_progress_guard, None);
+        assert_eq!(limits.max_tool_input_bytes, DEFAULT_MAX_TOOL_INPUT_BYTES);
+        let limits = limits.with_max_tool_input_bytes(100);
+        assert_eq!(limits.max_tool_input_bytes, 100);
+    }
+
+    #[test]
+    fn test_with_no_progress_guard() {
+        let guard = NoProgressConfig::default().with_max_repeated_calls(3);
+        let limits = ExecutionLimits::default().with_no_progress_guard(guard);
+        let guard = limits.no_progress_guard.unwrap();
+        assert_eq!(guard.max_repeated_calls, 3);
+        assert_eq!(guard.max_failed_calls, DEFAULT_NO_PROGRESS_FAILED_CALLS);
+
+        let limits: ExecutionLimits =
+            serde_json::from_str(r#"{"no_progress_guard": {"max_failed_calls": 4}}"#).unwrap();
+        let guard = limits.no_progress_guard.unwrap();
+        assert_eq!(guard.max_repeated_calls, DEFAULT_NO_PROGRESS_REPEATED_CALLS);
+        assert_eq!(guard.max_failed_calls, 4);
+    }
+
+    #[test]
+    fn test_language_restrictions() {
+        assert_eq!(ExecutionLimits::default().language, LanguageRestrictions::default());
+        let restrictions = LanguageRestrictions::default().with_unbounded_loops(false);
+        assert_eq!(restrictions.forbidden_construct("loop"), Some("'loop' loops are"));
+        assert_eq!(restrictions.forbidden_construct("fn"), None);
+        assert_eq!(restrictions.forbidden_construct("for"), None);
+
+        let limits: ExecutionLimits =
+            serde_json::from_str(r#"{"language": {"allow_eval": false}}"#).unwrap();
+        assert!(!limits.language.allow_eval);
+        assert!(limits.language.allow_closures);
+    }
+
+    #[test]
+    fn test_quick_limits() {
+        let limits = ExecutionLimits::quick();
+        assert_eq!(limits.max_operations, QUICK_MAX_OPERATIONS);
+        assert_eq!(limits.max_tool_calls, QUICK_MAX_TOOL_CALLS);
+    }
+
+    #[test]
+    fn test_extended_limits() {
+        let limits = ExecutionLimits::extended();
+        assert_eq!(limits.max_operations, EXTENDED_MAX_OPERATIONS);
+        assert_eq!(limits.max_tool_calls, EXTENDED_MAX_TOOL_CALLS);
+    }
+
+    #[test]
+    fn test_builder_pattern() {
+        let limits = ExecutionLimits::default()
+            .with_max_operations(50_000)
+            .with_max_tool_calls(25)
+            .with_timeout_ms(10_000);
+
+        assert_eq!(limits.max_operations, 50_000);
+        assert_eq!(limits.max_tool_calls, 25);
+        assert_eq!(limits.timeout_ms, 10_000);
+    }
+
+    #[test]
+    fn test_new_equals_default() {
+        let new_limits = ExecutionLimits::new();
+        let default_limits = ExecutionLimits::default();
+
+        assert_eq!(new_limits.max_operations, default_limits.max_operations);
+        assert_eq!(new_limits.max_tool_calls, default_limits.max_tool_calls);
+        assert_eq!(new_limits.timeout_ms, default_limits.timeout_ms);
+        assert_eq!(new_limits.max_string_size, default_limits.max_string_size);
+        assert_eq!(new_limits.max_array_size, default_limits.max_array_size);
+        assert_eq!(new_limits.max_map_size, default_limits.max_map_size);
+        assert_eq!(
+            new_limits.max_total_tool_output_bytes,
+            default_limits.max_total_tool_output_bytes
+        );
+    }
+
+    #[test]
+    fn test_with_max_total_tool_output_bytes() {
+        let limits = ExecutionLimits::default().with_max_total_tool_output_bytes(2_500_000);
+        assert_eq!(limits.max_total_tool_output_bytes, 2_500_000);
+        // Other values should remain at defaults
+        assert_eq!(limits.max_operations, DEFAULT_MAX_OPERATIONS);
+    }
+
+    #[test]
+    fn test_output_limit_builders() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_output_bytes, DEFAULT_MAX_OUTPUT_BYTES);
+        assert_eq!(limits.output_policy, OutputPolicy::Truncate);
+
+        let limits = limits
+            .with_max_output_bytes(500)
+            .with_output_policy(OutputPolicy::Error);
+        assert_eq!(limits.max_output_bytes, 500);
+        assert_eq!(limits.output_policy, OutputPolicy::Error);
+    }
+
+    #[test]
+    fn test_size_limit_behavior() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.size_limit_behavior, SizeLimitBehavior::Abort);
+        let limits = limits.with_size_limit_behavior(SizeLimitBehavior::TruncateWithWarning);
+        assert_eq!(limits.size_limit_behavior, SizeLimitBehavior::TruncateWithWarning);
+        let json = r#"{"size_limit_behavior": "truncate_with_warning"}"#;
+        let limits: ExecutionLimits = serde_json::from_str(json).unwrap();
+        assert_eq!(limits.size_limit_behavior, SizeLimitBehavior::TruncateWithWarning);
+    }
+
+    #[test]
+    fn test_output_policy_serializes_as_snake_case() {
+        let limits: ExecutionLimits = serde_json::from_str(r#"{"output_policy": "error"}"#).unwrap();
+        assert_eq!(limits.output_policy, OutputPolicy::Error);
+        assert_eq!(serde_json::to_string(&OutputPolicy::Truncate).unwrap(), r#""truncate""#);
+    }
+
+    #[test]
+    fn test_with_deterministic() {
+        assert!(!ExecutionLimits::default().deterministic);
+        let limits = ExecutionLimits::default().with_deterministic(true);
+        assert!(limits.deterministic);
+    }
+
+    #[test]
+    fn test_deserialize_missing_fields_uses_defaults() {
+        let limits: ExecutionLimits =
+            serde_json::from_str(r#"{"max_operations": 500}"#).unwrap();
+        assert_eq!(limits.max_operations, 500);
+        assert_eq!(
+            limits.max_total_tool_output_bytes,
+            DEFAULT_MAX_TOTAL_TOOL_OUTPUT_BYTES
+        );
+    }
+
+    #[test]
+    fn test_with_max_string_size() {
         let limits = ExecutionLimits::default().with_max_string_size(5_000_000);
         assert_eq!(limits.max_string_size, 5_000_000);
         // Other values should remain at defaults
@@ -381,4 +1571,219 @@ mod tests {
         assert_eq!(limits.max_array_size, 20_000);
         assert_eq!(limits.max_map_size, 2_000);
     }
+
+    #[test]
+    fn test_unlimited_limits() {
+        let limits = ExecutionLimits::unlimited();
+        assert_eq!(limits.max_operations, u64::MAX);
+        assert_eq!(limits.max_tool_calls, usize::MAX);
+        assert_eq!(limits.timeout_ms, u64::MAX);
+        assert_eq!(limits.max_total_tool_output_bytes, usize::MAX);
+        assert_eq!(limits.max_tool_input_bytes, usize::MAX);
+        assert!(limits.validate().is_ok());
+    }
+
+    #[test]
+    fn test_presets_are_valid() {
+        assert!(ExecutionLimits::default().validate().is_ok());
+        assert!(ExecutionLimits::quick().validate().is_ok());
+        assert!(ExecutionLimits::extended().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_limits() {
+        let cases = [
+            (ExecutionLimits::default().with_max_operations(0), "max_operations"),
+            (ExecutionLimits::default().with_timeout_ms(0), "timeout_ms"),
+            (ExecutionLimits::default().with_max_string_size(0), "max_string_size"),
+            (ExecutionLimits::default().with_max_array_size(0), "max_array_size"),
+            (ExecutionLimits::default().with_max_map_size(0), "max_map_size"),
+            (ExecutionLimits::default().with_max_output_bytes(0), "max_output_bytes"),
+            (ExecutionLimits::default().with_max_script_bytes(0), "max_script_bytes"),
+            (ExecutionLimits::default().with_max_script_lines(0), "max_script_lines"),
+            (ExecutionLimits::default().with_max_statements(0), "max_statements"),
+        ];
+        for (limits, field) in cases {
+            let err = limits.validate().unwrap_err();
+            assert_eq!(err, LimitsError::ZeroLimit { field });
+            assert!(err.to_string().contains(field));
+            assert!(err.to_string().contains("unlimited()"));
+
+            let json = serde_json::to_value(&err).unwrap();
+            let expected = serde_json::json!({"type": "zero_limit", "details": {"field": field}});
+            assert_eq!(json, expected);
+            assert_eq!(serde_json::from_value::<LimitsError>(json).unwrap(), err);
+        }
+    }
+
+    #[test]
+    fn test_limits_error_serde() {
+        for err in [
+            LimitsError::ToolCallsDisabled { tool: "fetch".to_string() },
+            LimitsError::InvalidCost,
+        ] {
+            let json = serde_json::to_string(&err).unwrap();
+            assert_eq!(serde_json::from_str::<LimitsError>(&json).unwrap(), err);
+        }
+        assert_eq!(
+            serde_json::to_value(LimitsError::InvalidCost).unwrap(),
+            serde_json::json!({"type": "invalid_cost"})
+        );
+
+        let err = LimitsError::UnknownProfile {
+            name: "batch".to_string(),
+            known: vec!["quick".to_string(), "default".to_string()],
+        };
+        let json = serde_json::to_string(&err).unwrap();
+        assert_eq!(serde_json::from_str::<LimitsError>(&json).unwrap(), err);
+
+        let unknown = r#"{"type": "zero_limit", "details": {"field": "max_nonsense"}}"#;
+        assert!(serde_json::from_str::<LimitsError>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_from_profile() {
+        for name in ExecutionLimits::PROFILES {
+            assert!(ExecutionLimits::from_profile(name).is_some(), "{name}");
+        }
+        assert_eq!(ExecutionLimits::from_profile("quick").unwrap().timeout_ms, QUICK_TIMEOUT_MS);
+        let extended = ExecutionLimits::from_profile("extended").unwrap();
+        assert_eq!(extended.max_tool_calls, EXTENDED_MAX_TOOL_CALLS);
+        assert_eq!(ExecutionLimits::from_profile("unlimited").unwrap().max_operations, u64::MAX);
+        assert!(ExecutionLimits::from_profile("Quick").is_none());
+    }
+
+    #[test]
+    fn test_profile_registry() {
+        let mut profiles = ProfileRegistry::new();
+        profiles.register_profile("batch", ExecutionLimits::extended().with_max_tool_calls(500));
+        profiles.register_profile("quick", ExecutionLimits::quick().with_timeout_ms(1_000));
+
+        assert_eq!(profiles.resolve("batch").unwrap().max_tool_calls, 500);
+        assert_eq!(profiles.resolve("quick").unwrap().timeout_ms, 1_000);
+        assert_eq!(profiles.resolve("default").unwrap().timeout_ms, DEFAULT_TIMEOUT_MS);
+        assert_eq!(profiles.names(), ["quick", "default", "extended", "unlimited", "batch"]);
+
+        let err = profiles.resolve("btach").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown limits profile `btach`; known profiles: quick, default, extended, \
+             unlimited, batch"
+        );
+    }
+
+    #[test]
+    fn test_validate_cost_budget() {
+        assert!(ExecutionLimits::default().with_max_total_cost(0.0).validate().is_ok());
+        assert!(ExecutionLimits::default().with_max_total_cost(2.5).validate().is_ok());
+        assert_eq!(
+            ExecutionLimits::default().with_max_total_cost(-1.0).validate(),
+            Err(LimitsError::InvalidCost)
+        );
+        assert_eq!(
+            ExecutionLimits::default().with_max_total_cost(f64::NAN).validate(),
+            Err(LimitsError::InvalidCost)
+        );
+    }
+
+    #[test]
+    fn test_script_size_limits() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_script_bytes, DEFAULT_MAX_SCRIPT_BYTES);
+        assert_eq!(limits.max_script_lines, DEFAULT_MAX_SCRIPT_LINES);
+        assert_eq!(limits.max_functions, DEFAULT_MAX_FUNCTIONS);
+        assert_eq!(limits.max_statements, DEFAULT_MAX_STATEMENTS);
+
+        let limits = limits
+            .with_max_script_bytes(1_024)
+            .with_max_script_lines(10)
+            .with_max_functions(0)
+            .with_max_statements(50);
+        assert_eq!(limits.max_script_bytes, 1_024);
+        assert_eq!(limits.max_script_lines, 10);
+        assert_eq!(limits.max_functions, 0);
+        assert_eq!(limits.max_statements, 50);
+        assert!(limits.validate().is_ok());
+    }
+
+    #[test]
+    fn test_output_limits() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_outputs, DEFAULT_MAX_OUTPUTS);
+        assert_eq!(limits.max_outputs_bytes, DEFAULT_MAX_OUTPUTS_BYTES);
+
+        let limits = limits.with_max_outputs(0).with_max_outputs_bytes(10);
+        assert_eq!(limits.max_outputs, 0);
+        assert_eq!(limits.max_outputs_bytes, 10);
+        assert!(limits.validate().is_ok());
+    }
+
+    #[test]
+    fn test_kv_limits() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_kv_entries, DEFAULT_MAX_KV_ENTRIES);
+        assert_eq!(limits.max_kv_bytes, DEFAULT_MAX_KV_BYTES);
+
+        let limits = limits.with_max_kv_entries(0).with_max_kv_bytes(10);
+        assert_eq!(limits.max_kv_entries, 0);
+        assert_eq!(limits.max_kv_bytes, 10);
+        assert!(limits.validate().is_ok());
+    }
+
+    #[test]
+    fn test_with_max_total_state_bytes() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_total_state_bytes, DEFAULT_MAX_TOTAL_STATE_BYTES);
+        assert_eq!(ExecutionLimits::unlimited().max_total_state_bytes, usize::MAX);
+
+        let limits = limits.with_max_total_state_bytes(1_000);
+        assert_eq!(limits.max_total_state_bytes, 1_000);
+        assert!(limits.validate().is_ok());
+    }
+
+    #[test]
+    fn test_with_max_concurrent_tool_calls() {
+        let limits = ExecutionLimits::default();
+        assert_eq!(limits.max_concurrent_tool_calls, DEFAULT_MAX_CONCURRENT_TOOL_CALLS);
+        assert_eq!(
+            ExecutionLimits::unlimited().max_concurrent_tool_calls,
+            DEFAULT_MAX_CONCURRENT_TOOL_CALLS
+        );
+
+        let limits = limits.with_max_concurrent_tool_calls(2);
+        assert_eq!(limits.max_concurrent_tool_calls, 2);
+        assert!(limits.validate().is_ok());
+        assert_eq!(
+            limits.with_max_concurrent_tool_calls(0).validate(),
+            Err(LimitsError::ZeroLimit { field: "max_concurrent_tool_calls" })
+        );
+    }
+
+    #[test]
+    fn test_zero_tool_calls_is_valid_on_its_own() {
+        let limits = ExecutionLimits::default().with_max_tool_calls(0);
+        assert!(limits.validate().is_ok());
+    }
+
+    #[test]
+    fn test_apply_to_maps_unlimited_to_rhai() {
+        let mut engine = rhai::Engine::new_raw();
+        ExecutionLimits::unlimited().apply_to(&mut engine);
+        assert_eq!(engine.max_operations(), 0);
+        assert_eq!(engine.max_string_size(), 0);
+        assert_eq!(engine.max_array_size(), 0);
+        assert_eq!(engine.max_map_size(), 0);
+
+        ExecutionLimits::quick().apply_to(&mut engine);
+        assert_eq!(engine.max_operations(), QUICK_MAX_OPERATIONS);
+        assert_eq!(engine.max_string_size(), DEFAULT_MAX_STRING_SIZE);
+    }
+
+    #[test]
+    fn test_absurd_sizes_are_clamped_to_unlimited() {
+        assert_eq!(rhai_size_limit(usize::MAX), 0);
+        assert_eq!(rhai_size_limit(isize::MAX.unsigned_abs() + 1), 0);
+        assert_eq!(rhai_size_limit(isize::MAX.unsigned_abs()), isize::MAX.unsigned_abs());
+        assert_eq!(rhai_size_limit(1_000), 1_000);
+    }
 }
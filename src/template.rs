@@ -0,0 +1,484 @@
+//! Scripts with `{{placeholder}}` variables filled in safely.
+//!
+//! A [`ScriptTemplate`] is a stored orchestration script whose placeholders
+//! are replaced by Rhai literals when it is rendered: strings are quoted and
+//! escaped, numbers are written inline, and arrays and objects become array
+//! and map literals. A value therefore always stays a single value and can
+//! never inject code, whatever quotes, backticks or `${}` it contains.
+//!
+//! Placeholders must stand where an expression could, which includes the
+//! `${...}` of an interpolated string; one inside a string literal or
+//! comment is rejected by [`ScriptTemplate::parse`], since it could not be
+//! substituted safely there.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use tool_orchestrator::ScriptTemplate;
+//!
+//! let template = ScriptTemplate::parse(r#"
+//!     let orders = get_orders({{customer_id}});
+//!     `${ {{greeting}} }: ${orders.len()} orders`
+//! "#)?;
+//!
+//! let vars = serde_json::json!({"customer_id": 42, "greeting": "Hi \"there\""});
+//! let result = orchestrator.execute_template(&template, &vars, ExecutionLimits::default())?;
+//! ```
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Reason a [`ScriptTemplate`] could not be parsed or rendered.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "type", content = "details", rename_all = "snake_case")]
+pub enum TemplateError {
+    /// A `{{` without a closing `}}`.
+    #[error("unterminated placeholder at byte {offset}")]
+    Unterminated {
+        /// Byte offset of the opening `{{`
+        offset: usize,
+    },
+
+    /// The text between `{{` and `}}` is not a valid variable name.
+    #[error("invalid placeholder `{{{{{placeholder}}}}}`; names are letters, digits and `_`")]
+    InvalidPlaceholder {
+        /// The text between the braces
+        placeholder: String,
+    },
+
+    /// A placeholder inside a string literal or comment, where a value
+    /// could not be substituted safely.
+    #[error("placeholder `{{{{{name}}}}}` is inside a string literal or comment")]
+    PlaceholderInLiteral {
+        /// The placeholder's variable name
+        name: String,
+    },
+
+    /// The variables passed to [`ScriptTemplate::render`] are not a JSON object.
+    #[error("template variables must be a JSON object")]
+    VarsNotObject,
+
+    /// The variables don't match the template's placeholders.
+    #[error("template variables don't match: missing [{}], unexpected [{}]",
+        missing.join(", "), unexpected.join(", "))]
+    Variables {
+        /// Placeholders without a value
+        missing: Vec<String>,
+        /// Values without a placeholder
+        unexpected: Vec<String>,
+    },
+}
+
+/// One piece of a parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Variable(String),
+}
+
+/// A Rhai script with `{{name}}` placeholders.
+///
+/// See the [module documentation](self) for the substitution rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptTemplate {
+    segments: Vec<Segment>,
+}
+
+/// Where the scanner is in the Rhai source.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Code,
+    DoubleQuoted,
+    SingleQuoted,
+    Backtick,
+    LineComment,
+    /// Nesting depth of `/* */` comments
+    BlockComment(usize),
+}
+
+impl ScriptTemplate {
+    /// Parse a template, locating its placeholders.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TemplateError::Unterminated`] or
+    /// [`TemplateError::InvalidPlaceholder`] for a malformed placeholder, and
+    /// [`TemplateError::PlaceholderInLiteral`] for a placeholder inside a
+    /// string literal or comment.
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut text_start = 0;
+        let mut context = Context::Code;
+        // Open brace depth of each `${` interpolation being scanned as code
+        let mut interpolations: Vec<usize> = Vec::new();
+        let bytes = source.as_bytes();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let rest = &bytes[i..];
+            if rest.starts_with(b"{{") {
+                let Some(len) = source[i + 2..].find("}}") else {
+                    if context == Context::Code {
+                        return Err(TemplateError::Unterminated { offset: i });
+                    }
+                    i += 2;
+                    continue;
+                };
+                let placeholder = &source[i + 2..i + 2 + len];
+                let name = placeholder.trim();
+                let valid = is_variable_name(name);
+                if context == Context::Code {
+                    if !valid {
+                        return Err(TemplateError::InvalidPlaceholder {
+                            placeholder: placeholder.to_string(),
+                        });
+                    }
+                    segments.push(Segment::Text(source[text_start..i].to_string()));
+                    segments.push(Segment::Variable(name.to_string()));
+                    i += len + 4;
+                    text_start = i;
+                    continue;
+                }
+                // Other `{{` sequences in literals and comments are just text
+                if valid {
+                    return Err(TemplateError::PlaceholderInLiteral { name: name.to_string() });
+                }
+            }
+
+            if let (Context::Code, Some(depth)) = (context, interpolations.last_mut()) {
+                match rest[0] {
+                    b'{' => *depth += 1,
+                    b'}' if *depth == 0 => {
+                        interpolations.pop();
+                        context = Context::Backtick;
+                        i += 1;
+                        continue;
+                    }
+                    b'}' => *depth -= 1,
+                    _ => {}
+                }
+            }
+
+            let (next, skip) = match (context, rest) {
+                (Context::Code, [b'"', ..]) => (Context::DoubleQuoted, 1),
+                (Context::Code, [b'\'', ..]) => (Context::SingleQuoted, 1),
+                (Context::Code, [b'`', ..]) => (Context::Backtick, 1),
+                (Context::Code, [b'/', b'/', ..]) => (Context::LineComment, 2),
+                (Context::Code, [b'/', b'*', ..]) => (Context::BlockComment(1), 2),
+                (Context::DoubleQuoted | Context::SingleQuoted, [b'\\', _, ..]) => (context, 2),
+                (Context::DoubleQuoted, [b'"', ..])
+                | (Context::SingleQuoted, [b'\'', ..])
+                | (Context::LineComment, [b'\n', ..]) => (Context::Code, 1),
+                // A doubled backtick is an escaped backtick
+                (Context::Backtick, [b'`', b'`', ..]) => (context, 2),
+                (Context::Backtick, [b'$', b'{', ..]) => {
+                    interpolations.push(0);
+                    (Context::Code, 2)
+                }
+                (Context::Backtick, [b'`', ..]) => (Context::Code, 1),
+                (Context::BlockComment(depth), [b'/', b'*', ..]) => {
+                    (Context::BlockComment(depth + 1), 2)
+                }
+                (Context::BlockComment(1), [b'*', b'/', ..]) => (Context::Code, 2),
+                (Context::BlockComment(depth), [b'*', b'/', ..]) => {
+                    (Context::BlockComment(depth - 1), 2)
+                }
+                _ => (context, 1),
+            };
+            context = next;
+            i += skip;
+        }
+
+        segments.push(Segment::Text(source[text_start..].to_string()));
+        segments.retain(|segment| segment != &Segment::Text(String::new()));
+        Ok(Self { segments })
+    }
+
+    /// Names of the template's variables, in order of first use.
+    #[must_use]
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Variable(name) = segment {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Substitute `vars` into the template, producing a Rhai script.
+    ///
+    /// `vars` must be a JSON object with exactly one entry per placeholder.
+    /// Each value is written as a Rhai literal: `null` as `()`, numbers
+    /// inline (negative ones in parentheses), strings double-quoted with
+    /// every quote, backslash and control character escaped, and arrays and
+    /// objects as `[...]` and `#{...}` literals.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TemplateError::VarsNotObject`] if `vars` is not an object,
+    /// and [`TemplateError::Variables`] listing missing and unexpected
+    /// variables if they don't match the placeholders.
+    pub fn render(&self, vars: &serde_json::Value) -> Result<String, TemplateError> {
+        let vars = vars.as_object().ok_or(TemplateError::VarsNotObject)?;
+        let names = self.placeholders();
+        let missing: Vec<String> = names
+            .iter()
+            .filter(|name| !vars.contains_key(**name))
+            .map(|name| (*name).to_string())
+            .collect();
+        let unexpected: Vec<String> =
+            vars.keys().filter(|key| !names.contains(&key.as_str())).cloned().collect();
+        if !missing.is_empty() || !unexpected.is_empty() {
+            return Err(TemplateError::Variables { missing, unexpected });
+        }
+
+        let mut script = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => script.push_str(text),
+                Segment::Variable(name) => write_literal(&mut script, &vars[name]),
+            }
+        }
+        Ok(script)
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Append `value` to `out` as a Rhai literal.
+fn write_literal(out: &mut String, value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Null => out.push_str("()"),
+        serde_json::Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        serde_json::Value::Number(n) => write_number(out, n),
+        serde_json::Value::String(s) => write_string(out, s),
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_literal(out, item);
+            }
+            out.push(']');
+        }
+        serde_json::Value::Object(entries) => {
+            out.push_str("#{");
+            for (i, (key, item)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_string(out, key);
+                out.push_str(": ");
+                write_literal(out, item);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Append a number, converted like [`json_to_dynamic`](crate::json_to_dynamic).
+fn write_number(out: &mut String, n: &serde_json::Number) {
+    let text = match n.as_i64() {
+        Some(i) => i.to_string(),
+        None => match float_literal(n) {
+            Some(text) => text,
+            None => return write_string(out, &n.to_string()),
+        },
+    };
+    if text.starts_with('-') {
+        out.push('(');
+        out.push_str(&text);
+        out.push(')');
+    } else {
+        out.push_str(&text);
+    }
+}
+
+/// A number that doesn't fit an `i64` as a Rhai float literal.
+#[cfg(not(feature = "rhai-no-float"))]
+fn float_literal(n: &serde_json::Number) -> Option<String> {
+    // Debug formatting always includes a `.` or an exponent, e.g. `2.0` or `1e300`
+    n.as_f64().map(|f| format!("{f:?}"))
+}
+
+/// `None`: without floats such numbers become strings, as in `json_to_dynamic`.
+#[cfg(feature = "rhai-no-float")]
+const fn float_literal(_n: &serde_json::Number) -> Option<String> {
+    None
+}
+
+/// Append `s` as a double-quoted Rhai string, which never interpolates.
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use crate::{ExecutionLimits, ToolOrchestrator};
+
+    /// Render `template` with `vars` and run it.
+    fn run(template: &str, vars: &serde_json::Value) -> String {
+        let template = ScriptTemplate::parse(template).unwrap();
+        ToolOrchestrator::new()
+            .execute_template(&template, vars, ExecutionLimits::default())
+            .unwrap()
+            .output
+    }
+
+    #[test]
+    fn test_parse_finds_placeholders() {
+        let template = ScriptTemplate::parse("f({{ a }}, {{b}}) + {{a}}").unwrap();
+        assert_eq!(template.placeholders(), ["a", "b"]);
+
+        let vars = serde_json::json!({"a": 1, "b": "x"});
+        assert_eq!(template.render(&vars).unwrap(), r#"f(1, "x") + 1"#);
+    }
+
+    #[test]
+    fn test_values_become_rhai_literals() {
+        let vars = serde_json::json!({
+            "value": {"ids": [1, -2], "name": "Ada", "none": null, "ok": true, "a b": 3}
+        });
+        let template = ScriptTemplate::parse("{{value}}").unwrap();
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            r##"#{"a b": 3, "ids": [1, (-2)], "name": "Ada", "none": (), "ok": true}"##
+        );
+        // Evaluating the literal gives back the same JSON
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&run("{{value}}", &vars)).unwrap(),
+            vars["value"]
+        );
+        assert_eq!(run("10 - {{n}}", &serde_json::json!({"n": -5})), "15");
+    }
+
+    #[test]
+    #[cfg(not(feature = "rhai-no-float"))]
+    fn test_floats_become_float_literals() {
+        let template = ScriptTemplate::parse("[{{a}}, {{b}}, {{c}}]").unwrap();
+        let vars = serde_json::json!({"a": 2.5, "b": -0.25, "c": 1e300});
+        assert_eq!(template.render(&vars).unwrap(), "[2.5, (-0.25), 1e300]");
+        assert_eq!(run("{{a}} * 2.0", &serde_json::json!({"a": 2.5})), "5.0");
+    }
+
+    #[test]
+    #[cfg(feature = "rhai-no-float")]
+    fn test_floats_become_strings_without_float_support() {
+        let template = ScriptTemplate::parse("{{a}}").unwrap();
+        assert_eq!(template.render(&serde_json::json!({"a": 2.5})).unwrap(), r#""2.5""#);
+    }
+
+    #[test]
+    fn test_hostile_strings_stay_strings() {
+        let hostile = [
+            r#"" + evil() + ""#,
+            "`${evil()}`",
+            "${evil()}",
+            "line one\nline two\r\n\t\"quoted\"",
+            r"back\slash\",
+            "\u{0}\u{1b}[31m nul and escape",
+            "*/ evil() /*",
+            "unicode é ✓ 🚀",
+        ];
+        let mut orchestrator = ToolOrchestrator::new();
+        orchestrator.register_executor("evil", |_| Err("evil() was called".to_string()));
+
+        let template = ScriptTemplate::parse("let s = {{s}}; s").unwrap();
+        for s in hostile {
+            let vars = serde_json::json!({ "s": s });
+            let result = orchestrator
+                .execute_template(&template, &vars, ExecutionLimits::default())
+                .unwrap();
+            assert_eq!(result.output, s);
+            assert!(result.tool_calls.is_empty(), "{s:?} injected a call");
+        }
+
+        // Interpolated into a backtick string as an expression, not as text
+        let output = run("`Hello ${ {{name}} }!`", &serde_json::json!({"name": "${evil()}"}));
+        assert_eq!(output, "Hello ${evil()}!");
+    }
+
+    #[test]
+    fn test_placeholders_in_literals_and_comments_are_rejected() {
+        for source in [
+            r#""Hello {{name}}""#,
+            "`Hello {{name}}`",
+            "// {{name}}\n1",
+            "/* outer /* inner */ {{name}} */ 1",
+        ] {
+            assert_eq!(
+                ScriptTemplate::parse(source),
+                Err(TemplateError::PlaceholderInLiteral { name: "name".to_string() }),
+                "{source}"
+            );
+        }
+
+        // Interpolations are code, the text around them is not
+        let template = ScriptTemplate::parse("`${ #{a: {{x}}}.a } {{y}}`");
+        assert_eq!(template, Err(TemplateError::PlaceholderInLiteral { name: "y".to_string() }));
+
+        // Braces that aren't placeholders are ordinary text there
+        let template = ScriptTemplate::parse(r#""{{ not a name }}" + `{{` + {{x}}"#).unwrap();
+        assert_eq!(template.placeholders(), ["x"]);
+        let template = ScriptTemplate::parse("\"\\\"{{\" + /* */ {{x}}").unwrap();
+        assert_eq!(template.placeholders(), ["x"]);
+    }
+
+    #[test]
+    fn test_malformed_placeholders() {
+        assert_eq!(
+            ScriptTemplate::parse("1 + {{x"),
+            Err(TemplateError::Unterminated { offset: 4 })
+        );
+        let err = ScriptTemplate::parse("{{x.y}}").unwrap_err();
+        assert_eq!(err, TemplateError::InvalidPlaceholder { placeholder: "x.y".to_string() });
+        assert_eq!(
+            err.to_string(),
+            "invalid placeholder `{{x.y}}`; names are letters, digits and `_`"
+        );
+    }
+
+    #[test]
+    fn test_missing_and_unexpected_variables_are_reported() {
+        let template = ScriptTemplate::parse("{{a}} + {{b}}").unwrap();
+        let err = template.render(&serde_json::json!({"a": 1, "c": 2})).unwrap_err();
+        assert_eq!(
+            err,
+            TemplateError::Variables {
+                missing: vec!["b".to_string()],
+                unexpected: vec!["c".to_string()],
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "template variables don't match: missing [b], unexpected [c]"
+        );
+        assert_eq!(template.render(&serde_json::json!([1])), Err(TemplateError::VarsNotObject));
+
+        let err = ToolOrchestrator::new()
+            .execute_template(&template, &serde_json::json!({}), ExecutionLimits::default())
+            .unwrap_err();
+        assert_eq!(err.error_code(), "template_error");
+    }
+}
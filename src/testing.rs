@@ -0,0 +1,501 @@
+//! Helpers for testing tools and scripts (`testing` feature).
+//!
+//! Regression tests for LLM-generated scripts mostly need three things:
+//! tools that answer predictably, a way to run a script against them, and a
+//! check that the script called the right tools with the right input. This
+//! module provides each:
+//!
+//! - [`MockTool`] answers with a fixed output, a sequence of outputs or an
+//!   error, and records the input of every call.
+//! - [`OrchestratorTestBuilder`] registers mocks and real executors and runs
+//!   scripts with the chosen limits.
+//! - [`assert_calls!`](crate::assert_calls) compares the recorded
+//!   [`ToolCall`]s against [`ExpectedCall`]s: names, input and outcome, in
+//!   order.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use tool_orchestrator::assert_calls;
+//! use tool_orchestrator::testing::{ExpectedCall, MockTool, OrchestratorTestBuilder};
+//!
+//! let prices = MockTool::sequence(["3", "5"]);
+//! let result = OrchestratorTestBuilder::new()
+//!     .with_tool("price", &prices)
+//!     .with_tool("notify", &MockTool::failing("mail server down"))
+//!     .run_ok(r#"let t = parse_int(price("a")) + parse_int(price("b")); notify(t); t"#);
+//!
+//! assert_eq!(result.output, "8");
+//! assert_calls!(result.tool_calls, [
+//!     ExpectedCall::new("price").with_input(json!("a")),
+//!     "price",
+//!     ExpectedCall::new("notify").with_input(json!(8)).failed(),
+//! ]);
+//! assert_eq!(prices.call_count(), 2);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::engine::ToolOrchestrator;
+use crate::sandbox::ExecutionLimits;
+use crate::types::{ExecutionOptions, OrchestratorError, OrchestratorResult, ToolCall};
+
+// ============================================================================
+// Mock tools
+// ============================================================================
+
+/// What a [`MockTool`] answers with.
+#[derive(Debug)]
+enum Responses {
+    Always(Result<String, String>),
+    /// Used up front to back; calls after the last one fail
+    Sequence(VecDeque<String>),
+}
+
+#[derive(Debug)]
+struct MockState {
+    responses: Responses,
+    calls: Vec<serde_json::Value>,
+}
+
+/// A tool with scripted answers that records how it was called.
+///
+/// Clones share their state, so a test keeps one handle to inspect while the
+/// orchestrator calls another.
+#[derive(Debug, Clone)]
+pub struct MockTool {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTool {
+    fn new(responses: Responses) -> Self {
+        Self { state: Arc::new(Mutex::new(MockState { responses, calls: Vec::new() })) }
+    }
+
+    /// A tool that answers every call with `output`.
+    #[must_use]
+    pub fn returning(output: impl Into<String>) -> Self {
+        Self::new(Responses::Always(Ok(output.into())))
+    }
+
+    /// A tool that answers with `outputs` in turn, then fails every further
+    /// call with "mock tool has no more responses".
+    #[must_use]
+    pub fn sequence<I>(outputs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::new(Responses::Sequence(outputs.into_iter().map(Into::into).collect()))
+    }
+
+    /// A tool whose every call fails with `message`.
+    #[must_use]
+    pub fn failing(message: impl Into<String>) -> Self {
+        Self::new(Responses::Always(Err(message.into())))
+    }
+
+    /// Record a call and answer it.
+    fn call(&self, input: serde_json::Value) -> Result<String, String> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.calls.push(input);
+        let calls = state.calls.len();
+        match &mut state.responses {
+            Responses::Always(response) => response.clone(),
+            Responses::Sequence(outputs) => outputs.pop_front().ok_or_else(|| {
+                format!("mock tool has no more responses (call {calls})")
+            }),
+        }
+    }
+
+    /// The input of every call so far, oldest first.
+    #[must_use]
+    pub fn calls(&self) -> Vec<serde_json::Value> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).calls.clone()
+    }
+
+    /// How many times the tool has been called.
+    #[must_use]
+    pub fn call_count(&self) -> usize {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).calls.len()
+    }
+
+    /// Register the mock with `orchestrator` under `name`.
+    pub fn register(&self, orchestrator: &mut ToolOrchestrator, name: impl Into<String>) {
+        let mock = self.clone();
+        orchestrator.register_executor(name, move |input| mock.call(input));
+    }
+}
+
+// ============================================================================
+// Expected calls
+// ============================================================================
+
+/// How an [`ExpectedCall`] checks a call's input.
+enum InputMatcher {
+    Any,
+    Equals(serde_json::Value),
+    Contains(serde_json::Value),
+    Matches(Box<dyn Fn(&serde_json::Value) -> bool>),
+}
+
+/// A tool call a script is expected to make.
+///
+/// Matches by tool name, and optionally by input and by whether the call
+/// succeeded. A `&str` converts into an expectation on the name alone.
+pub struct ExpectedCall {
+    tool_name: String,
+    input: InputMatcher,
+    success: Option<bool>,
+}
+
+impl ExpectedCall {
+    /// Expect a call to `tool_name`, with any input and outcome.
+    #[must_use]
+    pub fn new(tool_name: impl Into<String>) -> Self {
+        Self { tool_name: tool_name.into(), input: InputMatcher::Any, success: None }
+    }
+
+    /// Require the input to equal `input` (builder pattern).
+    #[must_use]
+    pub fn with_input(mut self, input: serde_json::Value) -> Self {
+        self.input = InputMatcher::Equals(input);
+        self
+    }
+
+    /// Require the input to contain `subset` (builder pattern).
+    ///
+    /// Objects match if every key of `subset` is present with a matching
+    /// value, so extra fields are ignored; other values must be equal.
+    #[must_use]
+    pub fn with_input_containing(mut self, subset: serde_json::Value) -> Self {
+        self.input = InputMatcher::Contains(subset);
+        self
+    }
+
+    /// Require the input to satisfy `predicate` (builder pattern).
+    #[must_use]
+    pub fn with_input_matching<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&serde_json::Value) -> bool + 'static,
+    {
+        self.input = InputMatcher::Matches(Box::new(predicate));
+        self
+    }
+
+    /// Require the call to have succeeded (builder pattern).
+    #[must_use]
+    pub const fn succeeded(mut self) -> Self {
+        self.success = Some(true);
+        self
+    }
+
+    /// Require the call to have failed (builder pattern).
+    #[must_use]
+    pub const fn failed(mut self) -> Self {
+        self.success = Some(false);
+        self
+    }
+
+    /// Why `call` doesn't match, if it doesn't.
+    fn mismatch(&self, call: &ToolCall) -> Option<String> {
+        if call.tool_name != self.tool_name {
+            return Some(format!("expected a call to `{}`", self.tool_name));
+        }
+        let input_ok = match &self.input {
+            InputMatcher::Any => true,
+            InputMatcher::Equals(expected) => &call.input == expected,
+            InputMatcher::Contains(subset) => contains(&call.input, subset),
+            InputMatcher::Matches(predicate) => predicate(&call.input),
+        };
+        if !input_ok {
+            return Some(format!("input doesn't match {self}"));
+        }
+        match self.success {
+            Some(true) if !call.success => Some("expected the call to succeed".to_string()),
+            Some(false) if call.success => Some("expected the call to fail".to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for ExpectedCall {
+    fn from(tool_name: &str) -> Self {
+        Self::new(tool_name)
+    }
+}
+
+impl fmt::Display for ExpectedCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.tool_name)?;
+        match &self.input {
+            InputMatcher::Any => f.write_str("(..)")?,
+            InputMatcher::Equals(input) => write!(f, "({input})")?,
+            InputMatcher::Contains(subset) => write!(f, "(containing {subset})")?,
+            InputMatcher::Matches(_) => f.write_str("(<predicate>)")?,
+        }
+        match self.success {
+            Some(true) => f.write_str(" succeeded"),
+            Some(false) => f.write_str(" failed"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for ExpectedCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExpectedCall({self})")
+    }
+}
+
+/// Whether `value` contains everything in `subset`.
+fn contains(value: &serde_json::Value, subset: &serde_json::Value) -> bool {
+    match (value, subset) {
+        (serde_json::Value::Object(value), serde_json::Value::Object(subset)) => subset
+            .iter()
+            .all(|(key, expected)| value.get(key).is_some_and(|actual| contains(actual, expected))),
+        _ => value == subset,
+    }
+}
+
+/// Panic unless `calls` match `expected` one for one, in order.
+///
+/// Usually called through [`assert_calls!`](crate::assert_calls). The panic
+/// message lists every recorded call and the first one that didn't match.
+///
+/// # Panics
+///
+/// If there are more or fewer calls than expected, or any call doesn't
+/// match its expectation.
+#[track_caller]
+pub fn assert_calls(calls: &[ToolCall], expected: &[ExpectedCall]) {
+    let problem = calls
+        .iter()
+        .zip(expected)
+        .enumerate()
+        .find_map(|(i, (call, expected))| {
+            expected.mismatch(call).map(|why| format!("call {i} ({}): {why}", describe(call)))
+        })
+        .or_else(|| {
+            (calls.len() != expected.len()).then(|| {
+                format!("expected {} tool calls, got {}", expected.len(), calls.len())
+            })
+        });
+    if let Some(problem) = problem {
+        let recorded: Vec<String> = calls.iter().map(describe).collect();
+        let expected: Vec<String> = expected.iter().map(ToString::to_string).collect();
+        panic!(
+            "tool calls don't match: {problem}\n  recorded: [{}]\n  expected: [{}]",
+            recorded.join(", "),
+            expected.join(", ")
+        );
+    }
+}
+
+fn describe(call: &ToolCall) -> String {
+    let outcome = if call.success { "" } else { " failed" };
+    format!("{}({}){outcome}", call.tool_name, call.input)
+}
+
+/// Assert that recorded tool calls match a list of expectations, in order.
+///
+/// Each expectation is an [`ExpectedCall`](crate::testing::ExpectedCall) or
+/// anything that converts into one, such as a tool name.
+///
+/// # Example
+///
+/// ```ignore
+/// assert_calls!(result.tool_calls, [
+///     "search",
+///     ExpectedCall::new("fetch").with_input_containing(json!({"id": 1})),
+/// ]);
+/// ```
+#[macro_export]
+macro_rules! assert_calls {
+    ($calls:expr, [$($expected:expr),* $(,)?]) => {
+        $crate::testing::assert_calls(
+            &$calls,
+            &[$($crate::testing::ExpectedCall::from($expected)),*],
+        )
+    };
+}
+
+// ============================================================================
+// Test builder
+// ============================================================================
+
+/// An orchestrator set up for a test: tools, limits and execution options.
+///
+/// Limits default to [`ExecutionLimits::default`].
+pub struct OrchestratorTestBuilder {
+    orchestrator: ToolOrchestrator,
+    limits: ExecutionLimits,
+    options: ExecutionOptions,
+}
+
+impl OrchestratorTestBuilder {
+    /// Start with no tools and default limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            orchestrator: ToolOrchestrator::new(),
+            limits: ExecutionLimits::default(),
+            options: ExecutionOptions::new(),
+        }
+    }
+
+    /// Register `mock` as `name` (builder pattern).
+    #[must_use]
+    pub fn with_tool(mut self, name: impl Into<String>, mock: &MockTool) -> Self {
+        mock.register(&mut self.orchestrator, name);
+        self
+    }
+
+    /// Register a real executor as `name` (builder pattern).
+    #[must_use]
+    pub fn with_executor<F>(mut self, name: impl Into<String>, executor: F) -> Self
+    where
+        F: Fn(serde_json::Value) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.orchestrator.register_executor(name, executor);
+        self
+    }
+
+    /// Run scripts with `limits` (builder pattern).
+    #[must_use]
+    pub const fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Run scripts with `options` (builder pattern).
+    #[must_use]
+    pub fn with_options(mut self, options: ExecutionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The orchestrator, for setup the builder doesn't cover.
+    pub const fn orchestrator_mut(&mut self) -> &mut ToolOrchestrator {
+        &mut self.orchestrator
+    }
+
+    /// Run `script`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`OrchestratorError`] the execution failed with.
+    pub fn run(&self, script: &str) -> Result<OrchestratorResult, OrchestratorError> {
+        self.orchestrator.execute_with_options(script, self.limits, &self.options)
+    }
+
+    /// Run `script`, which is expected to succeed.
+    ///
+    /// # Panics
+    ///
+    /// If the execution fails, with the error in the message.
+    #[track_caller]
+    pub fn run_ok(&self, script: &str) -> OrchestratorResult {
+        match self.run(script) {
+            Ok(result) if result.success => result,
+            Ok(result) => panic!("script failed: {}", result.error.unwrap_or_default()),
+            Err(err) => panic!("script failed: {err}"),
+        }
+    }
+
+    /// Finish setup and take the orchestrator.
+    #[must_use]
+    pub fn build(self) -> ToolOrchestrator {
+        self.orchestrator
+    }
+}
+
+impl Default for OrchestratorTestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mock_tools_answer_and_record() {
+        let prices = MockTool::sequence(["3", "5"]);
+        let result = OrchestratorTestBuilder::new()
+            .with_tool("price", &prices)
+            .with_tool("notify", &MockTool::failing("mail server down"))
+            .with_tool("tax", &MockTool::returning("1"))
+            .run_ok(
+                r#"
+                let total = parse_int(price("a")) + parse_int(price(#{id: 2, qty: 1}));
+                notify(total);
+                [total + parse_int(tax(())), price("c")]
+                "#,
+            );
+
+        assert_eq!(
+            result.output,
+            r#"[9,"Tool error: mock tool has no more responses (call 3)"]"#
+        );
+        assert_eq!(prices.call_count(), 3);
+        assert_eq!(prices.calls(), [json!("a"), json!({"id": 2, "qty": 1}), json!("c")]);
+        crate::assert_calls!(result.tool_calls, [
+            ExpectedCall::new("price").with_input(json!("a")).succeeded(),
+            ExpectedCall::new("price").with_input_containing(json!({"id": 2})),
+            ExpectedCall::new("notify").with_input_matching(|input| input == 8).failed(),
+            "tax",
+            ExpectedCall::new("price").failed(),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "call 1 (b(2)): expected a call to `c`")]
+    fn test_wrong_tool_is_reported() {
+        let result = OrchestratorTestBuilder::new()
+            .with_tool("b", &MockTool::returning(""))
+            .run_ok("b(1); b(2)");
+        crate::assert_calls!(result.tool_calls, ["b", "c"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "input doesn't match b(containing {\"id\":3})")]
+    fn test_wrong_input_is_reported() {
+        let result = OrchestratorTestBuilder::new()
+            .with_tool("b", &MockTool::returning(""))
+            .run_ok("b(#{id: 2})");
+        crate::assert_calls!(result.tool_calls, [
+            ExpectedCall::new("b").with_input_containing(json!({"id": 3}))
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 1 tool calls, got 2")]
+    fn test_extra_calls_are_reported() {
+        let result = OrchestratorTestBuilder::new()
+            .with_tool("b", &MockTool::returning(""))
+            .run_ok("b(1); b(2)");
+        crate::assert_calls!(result.tool_calls, ["b"]);
+    }
+
+    #[test]
+    fn test_input_containment() {
+        let value = json!({"a": 1, "b": {"c": [1, 2], "d": null}});
+        assert!(contains(&value, &json!({"b": {"c": [1, 2]}})));
+        assert!(contains(&value, &json!({})));
+        assert!(!contains(&value, &json!({"b": {"c": [1]}})));
+        assert!(!contains(&value, &json!({"e": null})));
+    }
+
+    #[test]
+    #[should_panic(expected = "script failed: Script exceeded maximum operations (100)")]
+    fn test_run_ok_reports_failures() {
+        OrchestratorTestBuilder::new()
+            .with_limits(ExecutionLimits::default().with_max_operations(100))
+            .run_ok("loop {}");
+    }
+}
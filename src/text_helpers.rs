@@ -0,0 +1,124 @@
+//! Character-aware string functions for scripts.
+//!
+//! Registers `truncate_chars`, `truncate_bytes_lossy` and `snippet`, so
+//! scripts can shorten tool output without index arithmetic on
+//! `sub_string` that splits emoji or CJK text. Counts and offsets are in
+//! characters except for `truncate_bytes_lossy`, which drops a character
+//! rather than cut it in half. Out-of-range arguments are clamped instead of
+//! raising errors.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let page = fetch_page(url);
+//! let title = truncate_chars(page.title, 80);
+//! let body = truncate_bytes_lossy(page.body, 2_000);
+//! let context = snippet(page.body, page.body.index_of("error") - 40, 120);
+//! ```
+
+use rhai::{FuncRegistration, ImmutableString, Module, INT};
+
+use crate::util::truncate_utf8;
+
+/// Names of the functions registered by [`text_module`]
+pub(crate) const FUNCTION_NAMES: [&str; 3] = ["truncate_chars", "truncate_bytes_lossy", "snippet"];
+
+/// A count from a script; negative counts are zero.
+fn count(n: INT) -> usize {
+    usize::try_from(n.max(0)).unwrap_or(usize::MAX)
+}
+
+/// The first `n` characters of `s`.
+fn truncate_chars(s: &str, n: usize) -> &str {
+    match s.char_indices().nth(n) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+/// Up to `len` characters of `s` from character `start`, counted from the
+/// end when negative.
+fn snippet(s: &str, start: INT, len: usize) -> &str {
+    let start = if start < 0 {
+        let chars = s.chars().count();
+        chars.saturating_sub(count(start.saturating_neg()))
+    } else {
+        count(start)
+    };
+    let begin = s.char_indices().nth(start).map_or(s.len(), |(i, _)| i);
+    truncate_chars(&s[begin..], len)
+}
+
+/// Build a module with the text functions.
+pub(crate) fn text_module() -> Module {
+    let mut module = Module::new();
+    FuncRegistration::new("truncate_chars").set_into_module(
+        &mut module,
+        |s: ImmutableString, n: INT| -> ImmutableString { truncate_chars(&s, count(n)).into() },
+    );
+    FuncRegistration::new("truncate_bytes_lossy").set_into_module(
+        &mut module,
+        |s: ImmutableString, n: INT| -> ImmutableString { truncate_utf8(&s, count(n)).into() },
+    );
+    FuncRegistration::new("snippet").set_into_module(
+        &mut module,
+        |s: ImmutableString, start: INT, len: INT| -> ImmutableString {
+            snippet(&s, start, count(len)).into()
+        },
+    );
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecutionLimits, ToolOrchestrator};
+
+    const SAMPLES: [&str; 5] = ["", "ascii only", "héllo wörld", "東京と大阪の天気", "a😀b👍🏽c🇯🇵"];
+
+    fn run(script: &str) -> String {
+        ToolOrchestrator::new()
+            .execute(script, ExecutionLimits::default())
+            .unwrap()
+            .output
+    }
+
+    #[test]
+    fn test_text_functions_in_scripts() {
+        assert_eq!(run(r#"truncate_chars("東京と大阪", 2)"#), "東京");
+        assert_eq!(run(r#"truncate_chars("abc", -1)"#), "");
+        assert_eq!(run(r#"truncate_bytes_lossy("a😀b", 4)"#), "a");
+        assert_eq!(run(r#"truncate_bytes_lossy("a😀b", 5)"#), "a😀");
+        assert_eq!(run(r#"snippet("a😀b👍c", 1, 3)"#), "😀b👍");
+        assert_eq!(run(r#"snippet("a😀b👍c", -2, 5)"#), "👍c");
+        assert_eq!(run(r#"snippet("abc", 10, 2)"#), "");
+        assert!(ToolOrchestrator::new().validate_script(r#"snippet("a", 0, 1)"#).valid);
+    }
+
+    #[test]
+    fn test_truncate_chars_at_every_offset() {
+        for s in SAMPLES {
+            let chars: Vec<char> = s.chars().collect();
+            for n in 0..=chars.len() + 2 {
+                let expected: String = chars.iter().take(n).collect();
+                assert_eq!(truncate_chars(s, n), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_snippet_at_every_offset() {
+        for s in SAMPLES {
+            let chars: Vec<char> = s.chars().collect();
+            let total = INT::try_from(chars.len()).unwrap();
+            for start in -total - 2..=total + 2 {
+                for len in 0..=chars.len() + 1 {
+                    let from = if start < 0 { total + start } else { start };
+                    let from = usize::try_from(from.max(0)).unwrap();
+                    let expected: String = chars.iter().skip(from).take(len).collect();
+                    assert_eq!(snippet(s, start, len), expected, "{s:?} {start} {len}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Tool definitions for model APIs, built from registered tool metadata.
+//!
+//! [`ToolOrchestrator::describe_tools_anthropic`][anthropic] and
+//! [`ToolOrchestrator::describe_tools_openai`][openai] list the registered
+//! tools in the format each API expects, and
+//! [`ToolOrchestrator::code_execution_prompt`][prompt] renders instructions
+//! for a model that writes Rhai scripts against them, so neither has to be
+//! kept in step with the registrations by hand.
+//!
+//! A tool's description comes from [`Tool::description`] and its input
+//! schema from `register_executor_with_schema`. A tool without a schema is
+//! described as taking a single `input` of any type.
+//!
+//! [anthropic]: crate::ToolOrchestrator::describe_tools_anthropic
+//! [openai]: crate::ToolOrchestrator::describe_tools_openai
+//! [prompt]: crate::ToolOrchestrator::code_execution_prompt
+//! [`Tool::description`]: crate::Tool::description
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use serde_json::{json, Value};
+
+/// What is known about one registered tool.
+pub(crate) struct ToolDefinition<'a> {
+    /// Name scripts call the tool by, e.g. `fs::read`
+    pub(crate) name: &'a str,
+    /// Empty if the tool has none
+    pub(crate) description: &'a str,
+    pub(crate) input_schema: Option<&'a Value>,
+}
+
+/// Tools in the Anthropic Messages API format.
+pub(crate) fn anthropic(tools: &[ToolDefinition<'_>]) -> Value {
+    let tools = tools.iter().map(|tool| {
+        let mut definition = json!({ "name": api_name(tool.name) });
+        if !tool.description.is_empty() {
+            definition["description"] = json!(tool.description);
+        }
+        definition["input_schema"] = api_input_schema(tool.input_schema);
+        definition
+    });
+    Value::Array(tools.collect())
+}
+
+/// Tools in the OpenAI Chat Completions function-calling format.
+pub(crate) fn openai(tools: &[ToolDefinition<'_>]) -> Value {
+    let tools = tools.iter().map(|tool| {
+        let mut function = json!({ "name": api_name(tool.name) });
+        if !tool.description.is_empty() {
+            function["description"] = json!(tool.description);
+        }
+        function["parameters"] = api_input_schema(tool.input_schema);
+        json!({ "type": "function", "function": function })
+    });
+    Value::Array(tools.collect())
+}
+
+/// Instructions for writing a Rhai script that calls `tools`.
+pub(crate) fn code_execution_prompt(tools: &[ToolDefinition<'_>]) -> String {
+    let mut prompt = String::from(
+        "Write a Rhai script that completes the task by calling the functions below. \
+         The value of the script's last expression is its result.\n\nFunctions:\n",
+    );
+    if tools.is_empty() {
+        prompt.push_str("(none)\n");
+    }
+    for tool in tools {
+        let _ = write!(prompt, "- {}({})", tool.name, parameter(tool.input_schema));
+        if !tool.description.is_empty() {
+            let _ = write!(prompt, " - {}", tool.description);
+        }
+        prompt.push('\n');
+    }
+    prompt.push_str(
+        "\nCalling functions:\n\
+         - Pass one argument: a string, number, boolean, array, or map like #{ key: value }.\n\
+         - Every function returns a string; use parse_json(text) when it holds JSON.\n\
+         - A failed call returns a string starting with \"Tool error:\" instead of stopping \
+         the script.\n\
+         - Run independent calls together with \
+         call_parallel([#{ tool: \"name\", input: value }, ...]), \
+         which returns their outputs in order.\n",
+    );
+    prompt
+}
+
+/// `name` with every character the APIs reject replaced by `_`, so
+/// `fs::read` becomes `fs__read`.
+fn api_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// The APIs take an object schema; any other input is wrapped in an
+/// `input` property.
+fn api_input_schema(schema: Option<&Value>) -> Value {
+    match schema {
+        Some(schema) if schema.get("type") == Some(&json!("object")) => schema.clone(),
+        Some(schema) => json!({
+            "type": "object",
+            "properties": { "input": schema },
+            "required": ["input"],
+        }),
+        None => json!({
+            "type": "object",
+            "properties": { "input": { "description": "Any JSON value" } },
+        }),
+    }
+}
+
+/// The parameter of a script signature, e.g. `#{ id: integer, verbose?: boolean }`.
+fn parameter(schema: Option<&Value>) -> String {
+    let Some(schema) = schema else {
+        return "input".to_string();
+    };
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return match type_name(schema).as_str() {
+            "any" => "input".to_string(),
+            kind => format!("input: {kind}"),
+        };
+    };
+    let required: HashSet<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let fields: Vec<String> = properties
+        .iter()
+        .map(|(name, property)| {
+            let optional = if required.contains(name.as_str()) { "" } else { "?" };
+            format!("{name}{optional}: {}", type_name(property))
+        })
+        .collect();
+    if fields.is_empty() {
+        return "#{}".to_string();
+    }
+    format!("#{{ {} }}", fields.join(", "))
+}
+
+/// The JSON Schema type of `schema`, e.g. `string` or `string | null`.
+fn type_name(schema: &Value) -> String {
+    match schema.get("type") {
+        Some(Value::String(kind)) => kind.clone(),
+        Some(Value::Array(kinds)) if !kinds.is_empty() => {
+            kinds.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" | ")
+        }
+        _ => "any".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Value, Value) {
+        let user = json!({
+            "type": "object",
+            "required": ["user_id"],
+            "properties": {
+                "user_id": {"type": "integer"},
+                "verbose": {"type": ["boolean", "null"]}
+            }
+        });
+        (user, json!({"type": "string"}))
+    }
+
+    fn definitions<'a>(user: &'a Value, path: &'a Value) -> Vec<ToolDefinition<'a>> {
+        vec![
+            ToolDefinition { name: "fs::read", description: "", input_schema: Some(path) },
+            ToolDefinition {
+                name: "get_user",
+                description: "Look up a user",
+                input_schema: Some(user),
+            },
+            ToolDefinition { name: "ping", description: "Check the service", input_schema: None },
+        ]
+    }
+
+    #[test]
+    fn test_anthropic_format() {
+        let (user, path) = sample();
+        assert_eq!(
+            anthropic(&definitions(&user, &path)),
+            json!([
+                {
+                    "name": "fs__read",
+                    "input_schema": {
+                        "type": "object",
+                        "properties": {"input": {"type": "string"}},
+                        "required": ["input"]
+                    }
+                },
+                {"name": "get_user", "description": "Look up a user", "input_schema": user},
+                {
+                    "name": "ping",
+                    "description": "Check the service",
+                    "input_schema": {
+                        "type": "object",
+                        "properties": {"input": {"description": "Any JSON value"}}
+                    }
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_openai_format() {
+        let (user, path) = sample();
+        let tools = openai(&definitions(&user, &path));
+        assert_eq!(
+            tools[1],
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "get_user",
+                    "description": "Look up a user",
+                    "parameters": user
+                }
+            })
+        );
+        assert_eq!(tools[0]["function"]["name"], "fs__read");
+        assert_eq!(tools[0]["function"].get("description"), None);
+        let input = &tools[2]["function"]["parameters"]["properties"]["input"];
+        assert_eq!(input["description"], "Any JSON value");
+    }
+
+    #[test]
+    fn test_code_execution_prompt() {
+        let (user, path) = sample();
+        assert_eq!(
+            code_execution_prompt(&definitions(&user, &path)),
+            "Write a Rhai script that completes the task by calling the functions below. \
+             The value of the script's last expression is its result.\n\
+             \n\
+             Functions:\n\
+             - fs::read(input: string)\n\
+             - get_user(#{ user_id: integer, verbose?: boolean | null }) - Look up a user\n\
+             - ping(input) - Check the service\n\
+             \n\
+             Calling functions:\n\
+             - Pass one argument: a string, number, boolean, array, or map like #{ key: value }.\n\
+             - Every function returns a string; use parse_json(text) when it holds JSON.\n\
+             - A failed call returns a string starting with \"Tool error:\" instead of \
+             stopping the script.\n\
+             - Run independent calls together with \
+             call_parallel([#{ tool: \"name\", input: value }, ...]), \
+             which returns their outputs in order.\n"
+        );
+        assert!(code_execution_prompt(&[]).contains("Functions:\n(none)\n"));
+    }
+}
@@ -5,7 +5,13 @@
 //!
 //! - [`OrchestratorResult`] - The outcome of script execution
 //! - [`ToolCall`] - A record of each tool invocation
+//! - [`ExecutionWarning`] - A suspicious but non-fatal condition noticed during execution
 //! - [`OrchestratorError`] - Error types for various failure modes
+//! - [`ErrorReport`] - Serializable form of an [`OrchestratorError`]
+//! - [`LlmFormatOptions`] - Options for compact, token-efficient result rendering
+//! - [`ExecutionOptions`] - Per-execution settings that are not resource limits
+//! - [`ProgressInfo`] - Snapshot passed to the progress callback of a running script
+//! - [`ScriptValidation`] - The outcome of checking a script without running it
 //!
 //! # Example
 //!
@@ -22,9 +28,27 @@
 //! assert!(result.success);
 //! ```
 
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "native")]
+use std::sync::mpsc::Sender;
+#[cfg(feature = "native")]
+use std::sync::Arc;
+
+use rhai::Shared;
+
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+use std::rc::Rc;
+
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::sandbox::{known_name, LimitsError, StaticName};
+use crate::template::TemplateError;
+
 /// Result from executing an orchestration script.
 ///
 /// Contains the execution outcome including the script's output,
@@ -37,6 +61,12 @@ use thiserror::Error;
 /// - `tool_calls` - Complete log of every tool invocation
 /// - `execution_time_ms` - Total wall-clock time for execution
 /// - `error` - Error message if execution failed
+/// - `total_tool_output_bytes` - Cumulative bytes returned by all tool calls
+/// - `dry_run` - Whether tool calls were stubbed rather than executed
+/// - `structured_output` - The final value as JSON, for programmatic consumers
+/// - `execution_id` - Unique id of this execution, shared by its tool calls
+/// - `total_cost` - Summed cost of the tool calls made
+/// - `warnings` - Suspicious but non-fatal conditions noticed along the way
 ///
 /// # Example
 ///
@@ -54,10 +84,17 @@ use thiserror::Error;
 /// }
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
 pub struct OrchestratorResult {
     /// Whether execution completed successfully
     pub success: bool,
     /// Output from the script (final expression value)
+    ///
+    /// Strings are returned as-is, unit as an empty string, and every other
+    /// value as compact JSON (the serialized [`structured_output`]).
+    ///
+    /// [`structured_output`]: Self::structured_output
     pub output: String,
     /// All tool calls made during execution
     pub tool_calls: Vec<ToolCall>,
@@ -65,6 +102,55 @@ pub struct OrchestratorResult {
     pub execution_time_ms: u64,
     /// Error message if execution failed
     pub error: Option<String>,
+    /// Cumulative bytes returned by tools during execution
+    #[serde(default)]
+    pub total_tool_output_bytes: usize,
+    /// Whether tool calls were answered by stubs instead of real executors
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The script's final value as JSON (`None` if execution failed)
+    ///
+    /// Converted with [`dynamic_to_json`](crate::dynamic_to_json), so maps
+    /// become objects, unit becomes `null`, and values with no JSON
+    /// equivalent fall back to their debug representation as a string.
+    #[serde(default)]
+    pub structured_output: Option<serde_json::Value>,
+    /// Unique id of this execution (a UUID unless supplied by the caller)
+    ///
+    /// Also recorded on every [`ToolCall`] and on the `execute` tracing span,
+    /// so logs from concurrent executions can be correlated.
+    #[serde(default)]
+    pub execution_id: String,
+    /// Summed cost of the tool calls made (see [`ToolCall::cost`])
+    #[serde(default)]
+    pub total_cost: f64,
+    /// Named values the script recorded with `emit(name, value)`
+    ///
+    /// Converted like [`structured_output`](Self::structured_output); a
+    /// name emitted twice holds the later value.
+    #[serde(default)]
+    pub outputs: serde_json::Map<String, serde_json::Value>,
+    /// Conditions worth a look that did not fail the execution, each
+    /// reported once, in the order they were first noticed
+    #[serde(default)]
+    pub warnings: Vec<ExecutionWarning>,
+    /// Operations used, counting `tool_call_operation_cost` for each tool call
+    ///
+    /// This is the figure compared against
+    /// [`ExecutionLimits::max_operations`](crate::ExecutionLimits::max_operations).
+    #[serde(default)]
+    pub operations: u64,
+    /// Where the execution time went: tool calls, compiling and evaluating
+    #[serde(default)]
+    pub timing: ExecutionTiming,
+    /// [`script_hash`] of the script that ran, to tell which script
+    /// produced a result without storing its source
+    #[serde(default)]
+    pub script_hash: String,
+    /// The script's source, set when the execution ran with
+    /// [`with_include_script`](ExecutionOptions::with_include_script)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
 }
 
 impl OrchestratorResult {
@@ -78,6 +164,17 @@ impl OrchestratorResult {
             tool_calls,
             execution_time_ms,
             error: None,
+            total_tool_output_bytes: 0,
+            dry_run: false,
+            structured_output: None,
+            execution_id: String::new(),
+            total_cost: 0.0,
+            outputs: serde_json::Map::new(),
+            warnings: Vec::new(),
+            operations: 0,
+            timing: ExecutionTiming::default(),
+            script_hash: String::new(),
+            script: None,
         }
     }
 
@@ -91,8 +188,421 @@ impl OrchestratorResult {
             tool_calls,
             execution_time_ms,
             error: Some(error),
+            total_tool_output_bytes: 0,
+            dry_run: false,
+            structured_output: None,
+            execution_id: String::new(),
+            total_cost: 0.0,
+            outputs: serde_json::Map::new(),
+            warnings: Vec::new(),
+            operations: 0,
+            timing: ExecutionTiming::default(),
+            script_hash: String::new(),
+            script: None,
+        }
+    }
+
+    /// JSON Schema describing the serialized form of a result.
+    ///
+    /// Fields added after the initial release are optional in the schema,
+    /// matching their `#[serde(default)]` deserialization.
+    #[cfg(feature = "schema")]
+    #[must_use]
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(Self).to_value()
+    }
+}
+
+impl OrchestratorResult {
+    /// Render a compact representation for feeding back to an LLM.
+    ///
+    /// Unlike the full serde JSON, this omits tool inputs and outputs and
+    /// produces only the status, the error (if any), the script output and,
+    /// optionally, a one-line-per-call digest such as `greet(ok, 12ms)`.
+    ///
+    /// The rendered string never exceeds [`LlmFormatOptions::max_chars`]
+    /// characters. When space runs out the output keeps its head and tail
+    /// with an omission marker in between, and trailing digest lines are
+    /// replaced by a `… N more calls` summary.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let result = orchestrator.execute(script, limits)?;
+    /// let compact = result.to_llm_string(LlmFormatOptions::default().with_max_chars(500));
+    /// messages.push(tool_result_message(compact));
+    /// ```
+    #[must_use]
+    pub fn to_llm_string(&self, opts: LlmFormatOptions) -> String {
+        let budget = opts.max_chars;
+        let mut text = String::from(if self.success { "status: ok" } else { "status: error" });
+
+        if let Some(error) = &self.error {
+            let room = budget.saturating_sub(char_len(&text) + "\nerror: ".len());
+            text.push_str("\nerror: ");
+            text.push_str(&truncate_middle(error, room));
+        }
+
+        if !self.output.is_empty() {
+            let room = budget.saturating_sub(char_len(&text) + "\noutput: ".len());
+            if room > 0 {
+                text.push_str("\noutput: ");
+                text.push_str(&truncate_middle(&self.output, room));
+            }
+        }
+
+        if opts.include_warnings && !self.warnings.is_empty() {
+            let summary: Vec<String> = self.warnings.iter().map(ToString::to_string).collect();
+            let room = budget.saturating_sub(char_len(&text) + "\nwarnings: ".len());
+            if room > 0 {
+                text.push_str("\nwarnings: ");
+                text.push_str(&truncate_middle(&summary.join("; "), room));
+            }
+        }
+
+        if opts.include_tool_calls {
+            for (i, call) in self.tool_calls.iter().enumerate() {
+                let status = if call.success { "ok" } else { "failed" };
+                let line = format!("\n{}({status}, {}ms)", call.tool_name, call.duration_ms);
+                let remaining = self.tool_calls.len() - i;
+                let summary = format!("\n… {remaining} more calls");
+                // Keep room for the summary unless this is the last line
+                let reserve = if remaining > 1 { char_len(&summary) } else { 0 };
+                if char_len(&text) + char_len(&line) + reserve <= budget {
+                    text.push_str(&line);
+                } else {
+                    if char_len(&text) + char_len(&summary) <= budget {
+                        text.push_str(&summary);
+                    }
+                    break;
+                }
+            }
+        }
+
+        // The status line alone may exceed a tiny budget
+        if char_len(&text) > budget {
+            text = text.chars().take(budget).collect();
+        }
+        text
+    }
+
+    /// Estimate how many LLM tokens the script output will consume.
+    ///
+    /// Uses the same rough 4-bytes-per-token heuristic as the token
+    /// comparison benchmark; see [`estimate_tokens`].
+    #[must_use]
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.output)
+    }
+}
+
+/// How many of the slowest calls [`ExecutionTiming`] lists.
+const SLOWEST_CALLS_REPORTED: usize = 3;
+
+/// Breakdown of an execution's wall time, in milliseconds.
+///
+/// Zero throughout in deterministic mode, like the other timing fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ExecutionTiming {
+    /// Total execution time, as in `execution_time_ms`
+    pub total_ms: u64,
+    /// Summed duration of the tool calls; calls made together with
+    /// `call_parallel` overlap, so this can exceed `total_ms`
+    pub tool_time_ms: u64,
+    /// `total_ms` minus `tool_time_ms`: time spent compiling and evaluating
+    /// the script itself
+    pub script_time_ms: u64,
+    /// Time spent compiling the script
+    pub compile_ms: u64,
+    /// Up to three `(tool name, duration)` pairs for the slowest calls,
+    /// slowest first
+    pub slowest_calls: Vec<(String, u64)>,
+}
+
+impl ExecutionTiming {
+    /// Break `total_ms` down by the durations of `tool_calls`.
+    #[must_use]
+    pub fn new(total_ms: u64, compile_ms: u64, tool_calls: &[ToolCall]) -> Self {
+        let tool_time_ms =
+            tool_calls.iter().fold(0_u64, |sum, call| sum.saturating_add(call.duration_ms));
+        let mut slowest: Vec<&ToolCall> = tool_calls.iter().collect();
+        // Stable, so equally slow calls stay in call order
+        slowest.sort_by_key(|call| std::cmp::Reverse(call.duration_ms));
+        let slowest_calls = slowest
+            .into_iter()
+            .take(SLOWEST_CALLS_REPORTED)
+            .map(|call| (call.tool_name.clone(), call.duration_ms))
+            .collect();
+        Self {
+            total_ms,
+            tool_time_ms,
+            script_time_ms: total_ms.saturating_sub(tool_time_ms),
+            compile_ms,
+            slowest_calls,
+        }
+    }
+}
+
+/// Estimate the number of LLM tokens in `text` (roughly 4 bytes per token).
+#[must_use]
+pub const fn estimate_tokens(text: &str) -> usize {
+    text.len() / 4
+}
+
+/// Hash `value` in a canonical form, so equal JSON hashes equally.
+///
+/// Object keys are sorted and numbers normalized (`1`, `1.0` and `1e0` are
+/// the same number) before hashing with 64-bit FNV-1a, which doesn't change
+/// between Rust versions or runs; the result can be stored and compared
+/// later, e.g. to deduplicate tool calls in a host's own logs.
+///
+/// # Example
+///
+/// ```ignore
+/// let a = canonical_json_hash(&json!({"query": "rust", "limit": 10}));
+/// let b = canonical_json_hash(&json!({"limit": 10.0, "query": "rust"}));
+/// assert_eq!(a, b);
+/// ```
+#[must_use]
+pub fn canonical_json_hash(value: &serde_json::Value) -> u64 {
+    let mut canonical = String::new();
+    write_canonical_json(value, &mut canonical);
+    fnv1a(FNV_OFFSET_BASIS, canonical.as_bytes())
+}
+
+/// Identify a script by the SHA-256 of its source, as 64 hex digits.
+///
+/// This is the [`OrchestratorResult::script_hash`] of every execution of
+/// `script`, so results can be matched to a script without keeping its
+/// source, which may hold data the caller interpolated. The hash covers the
+/// exact text: a script differing only in whitespace hashes differently.
+///
+/// # Example
+///
+/// ```ignore
+/// let result = orchestrator.execute(script, limits)?;
+/// assert_eq!(result.script_hash, script_hash(script));
+/// ```
+#[must_use]
+pub fn script_hash(script: &str) -> String {
+    crate::sha256::sha256_hex(script.as_bytes())
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Append `value` to `out` as compact JSON with sorted keys and integral
+/// numbers written as integers.
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) {
+    use serde_json::Value;
+
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => out.push_str(&value.to_string()),
+        Value::Number(number) => {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+            let integral = number.as_f64().filter(|f| {
+                number.is_f64() && f.fract() == 0.0 && f.abs() < i64::MAX as f64
+            });
+            match integral {
+                // Also turns -0.0 into 0
+                #[allow(clippy::cast_possible_truncation)]
+                Some(f) => out.push_str(&(f as i64).to_string()),
+                None => out.push_str(&number.to_string()),
+            }
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(item, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Options for [`OrchestratorResult::to_llm_string`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LlmFormatOptions {
+    /// Hard upper bound on the rendered length, in characters
+    pub max_chars: usize,
+    /// Append a `name(status, Xms)` line for each tool call
+    pub include_tool_calls: bool,
+    /// Add a one-line summary of the result's warnings, if it has any
+    pub include_warnings: bool,
+}
+
+impl Default for LlmFormatOptions {
+    fn default() -> Self {
+        Self {
+            max_chars: 4_000,
+            include_tool_calls: true,
+            include_warnings: true,
+        }
+    }
+}
+
+impl LlmFormatOptions {
+    /// Set the character budget (builder pattern).
+    #[must_use]
+    pub const fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Include or omit the per-call digest (builder pattern).
+    #[must_use]
+    pub const fn with_tool_calls(mut self, include: bool) -> Self {
+        self.include_tool_calls = include;
+        self
+    }
+
+    /// Include or omit the warning summary (builder pattern).
+    #[must_use]
+    pub const fn with_warnings(mut self, include: bool) -> Self {
+        self.include_warnings = include;
+        self
+    }
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Shorten `s` to at most `max_chars` characters, keeping its head and tail.
+fn truncate_middle(s: &str, max_chars: usize) -> String {
+    let len = char_len(s);
+    if len <= max_chars {
+        return s.to_string();
+    }
+    // The marker consumes budget too, so shrink the kept portion until the
+    // reported omission count matches what is actually dropped.
+    let mut keep = max_chars;
+    let marker = loop {
+        let marker = format!("…[{} chars omitted]…", len - keep);
+        let fitted = max_chars.saturating_sub(char_len(&marker));
+        if fitted >= keep {
+            break marker;
+        }
+        keep = fitted;
+    };
+    if char_len(&marker) > max_chars {
+        return s.chars().take(max_chars).collect();
+    }
+    let head = keep.div_ceil(2);
+    let tail = keep - head;
+    let mut out: String = s.chars().take(head).collect();
+    out.push_str(&marker);
+    out.extend(s.chars().skip(len - tail));
+    out
+}
+
+/// How a tool call ended, recorded in [`ToolCall::status`].
+///
+/// Serialized in `snake_case`, e.g. `"timed_out"`. Records written before
+/// the status existed deserialize as [`Ok`](Self::Ok).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallStatus {
+    /// The tool ran and returned its output
+    #[default]
+    Ok,
+    /// The output was reused from the tool result cache; the tool didn't run
+    Cached,
+    /// The executor returned an error or panicked
+    Error,
+    /// The executor missed the tool's own timeout
+    TimedOut,
+    /// The input failed the tool's JSON Schema; the tool didn't run
+    InvalidInput,
+    /// The tool's rate limit was reached; the tool didn't run
+    RateLimited,
+    /// Too little of the execution's timeout was left to start the call
+    DeadlineExceeded,
+    /// The tool is not allowed in this execution; the tool didn't run
+    NotAllowed,
+    /// The execution was cancelled before the call started, or the script
+    /// ended without joining the call it started with `spawn_tool`
+    Cancelled,
+    /// The output was discarded for exceeding `max_tool_output_bytes` or
+    /// `max_total_tool_output_bytes`, or the input exceeded
+    /// `max_tool_input_bytes` or the output budget was already spent, and
+    /// the tool didn't run
+    LimitExceeded,
+    /// The execution had made `max_tool_calls` calls; the tool didn't run
+    CallLimitExceeded,
+    /// The call's cost would take the total over `max_total_cost`; the tool
+    /// didn't run
+    CostLimitExceeded,
+}
+
+impl ToolCallStatus {
+    /// The `snake_case` name of the status, as serialized.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Cached => "cached",
+            Self::Error => "error",
+            Self::TimedOut => "timed_out",
+            Self::InvalidInput => "invalid_input",
+            Self::RateLimited => "rate_limited",
+            Self::DeadlineExceeded => "deadline_exceeded",
+            Self::NotAllowed => "not_allowed",
+            Self::Cancelled => "cancelled",
+            Self::LimitExceeded => "limit_exceeded",
+            Self::CallLimitExceeded => "call_limit_exceeded",
+            Self::CostLimitExceeded => "cost_limit_exceeded",
         }
     }
+
+    /// Whether a call with this status returned an output to the script.
+    #[must_use]
+    pub const fn is_success(self) -> bool {
+        matches!(self, Self::Ok | Self::Cached)
+    }
+
+    /// Whether the call was refused before the tool could run.
+    #[must_use]
+    pub const fn is_refusal(self) -> bool {
+        matches!(
+            self,
+            Self::InvalidInput
+                | Self::RateLimited
+                | Self::DeadlineExceeded
+                | Self::NotAllowed
+                | Self::Cancelled
+                | Self::CallLimitExceeded
+                | Self::CostLimitExceeded
+        )
+    }
 }
 
 /// Record of a single tool call during script execution.
@@ -107,6 +617,7 @@ impl OrchestratorResult {
 /// - `input` - Arguments passed to the tool (serialized as JSON)
 /// - `output` - The tool's return value (or error message)
 /// - `success` - Whether the tool executed without error
+/// - `status` - How the call ended, telling failures apart
 /// - `duration_ms` - How long the tool took to execute
 ///
 /// # Example
@@ -116,11 +627,13 @@ impl OrchestratorResult {
 /// for call in &result.tool_calls {
 ///     println!("Tool: {} took {}ms", call.tool_name, call.duration_ms);
 ///     if !call.success {
-///         println!("  Failed: {}", call.output);
+///         println!("  Failed ({:?}): {}", call.status, call.output);
 ///     }
 /// }
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
 pub struct ToolCall {
     /// Name of the tool that was called
     pub tool_name: String,
@@ -128,14 +641,47 @@ pub struct ToolCall {
     pub input: serde_json::Value,
     /// Output returned by the tool
     pub output: String,
-    /// Whether the call succeeded
+    /// Whether the call succeeded; derived from `status`
     pub success: bool,
+    /// How the call ended
+    #[serde(default)]
+    pub status: ToolCallStatus,
     /// Execution time for this call in milliseconds
     pub duration_ms: u64,
+    /// Id of the execution that made this call
+    #[serde(default)]
+    pub execution_id: String,
+    /// Cost the tool was registered with, charged against `max_total_cost`
+    #[serde(default)]
+    pub cost: f64,
+    /// The call was refused before dispatch, because too little of the
+    /// execution's timeout was left, the tool's rate limit was reached or its
+    /// input failed validation; the tool itself never ran
+    #[serde(default)]
+    pub refused: bool,
+    /// The output was reused from an earlier call through the orchestrator's
+    /// tool result cache; the tool itself didn't run
+    #[serde(default)]
+    pub cached: bool,
+    /// The script called the tool through `call_tool(name, input)`, with
+    /// the name computed at runtime, rather than by its literal name
+    #[serde(default)]
+    pub invoked_dynamically: bool,
+    /// Length in bytes of a binary output, which `output` holds base64-encoded
+    #[serde(default)]
+    pub binary_len: Option<usize>,
+    /// [`input_fingerprint`](Self::input_fingerprint) as 16 hex digits, set
+    /// when the execution ran with
+    /// [`with_call_fingerprints`](ExecutionOptions::with_call_fingerprints)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
 }
 
 impl ToolCall {
     /// Create a new tool call record.
+    ///
+    /// The status is [`ToolCallStatus::Ok`] or [`ToolCallStatus::Error`]
+    /// according to `success`; see [`with_status`](Self::with_status).
     #[must_use]
     #[allow(clippy::missing_const_for_fn)] // serde_json::Value cannot be used in const fn
     pub fn new(
@@ -150,9 +696,772 @@ impl ToolCall {
             input,
             output,
             success,
+            status: if success { ToolCallStatus::Ok } else { ToolCallStatus::Error },
             duration_ms,
+            execution_id: String::new(),
+            cost: 0.0,
+            refused: false,
+            cached: false,
+            invoked_dynamically: false,
+            binary_len: None,
+            fingerprint: None,
+        }
+    }
+
+    /// Stable identity of the invocation: the tool name and the input.
+    ///
+    /// Calls to the same tool with equal inputs have equal fingerprints,
+    /// whatever the order of the input's object keys; see
+    /// [`canonical_json_hash`]. The output and outcome are not included.
+    #[must_use]
+    pub fn input_fingerprint(&self) -> u64 {
+        let hash = fnv1a(FNV_OFFSET_BASIS, self.tool_name.as_bytes());
+        // Separates the name from the input
+        let hash = fnv1a(hash, &[0]);
+        fnv1a(hash, &canonical_json_hash(&self.input).to_le_bytes())
+    }
+
+    /// Set how the call ended, and `success` to match (builder pattern).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let call = ToolCall::new(name, input, output, false, 0)
+    ///     .with_status(ToolCallStatus::TimedOut);
+    /// ```
+    #[must_use]
+    pub fn with_status(mut self, status: ToolCallStatus) -> Self {
+        self.status = status;
+        self.success = status.is_success();
+        self
+    }
+
+    /// JSON Schema describing the serialized form of a tool call record.
+    #[cfg(feature = "schema")]
+    #[must_use]
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(Self).to_value()
+    }
+}
+
+/// A suspicious but non-fatal condition noticed during an execution.
+///
+/// Collected in [`OrchestratorResult::warnings`]. Serialized with a `kind`
+/// tag next to the variant's fields, e.g.
+/// `{"kind": "tool_call_limit_reached", "limit": 50}`; the [`Display`]
+/// form is a one-line message.
+///
+/// [`Display`]: std::fmt::Display
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExecutionWarning {
+    /// Tool calls were refused once `max_tool_calls` was reached; the script
+    /// only saw an error string and may have carried on regardless
+    ToolCallLimitReached {
+        /// The `max_tool_calls` limit
+        limit: usize,
+    },
+    /// The output used at least 90% of `max_output_bytes`
+    OutputNearLimit {
+        /// Size of the output in bytes
+        bytes: usize,
+        /// The `max_output_bytes` limit
+        limit: usize,
+    },
+    /// The output exceeded `max_output_bytes` and was truncated
+    OutputTruncated {
+        /// Size of the output before truncation, in bytes
+        bytes: usize,
+        /// The `max_output_bytes` limit
+        limit: usize,
+    },
+    /// A NaN or infinite number became `null` when converted to JSON
+    NonFiniteNumber {
+        /// Where the number was: a tool's input, an emitted output or the
+        /// script output
+        context: String,
+    },
+    /// `emit` replaced an earlier output of the same name
+    OutputReplaced {
+        /// Name of the output
+        name: String,
+    },
+    /// Collections were cut to `max_array_size` or `max_map_size` under
+    /// `SizeLimitBehavior::TruncateWithWarning`, losing their later entries
+    CollectionTruncated {
+        /// Where the collections were: a variable, a `parse_json` result or
+        /// an array grown by `push` or `insert`
+        context: String,
+        /// The `max_array_size` or `max_map_size` limit
+        limit: usize,
+    },
+    /// A tool returned more than the script may receive, so the call failed
+    /// instead
+    ToolOutputTooLarge {
+        /// Name of the tool
+        tool: String,
+        /// Size of the output in bytes
+        bytes: usize,
+        /// The `max_tool_output_bytes`, `max_string_size` or, for a blob,
+        /// `max_array_size` limit, whichever was exceeded
+        limit: usize,
+    },
+    /// A call started with `spawn_tool` was never joined, so it was recorded
+    /// as cancelled and its output discarded
+    TaskNotJoined {
+        /// Name of the tool
+        tool: String,
+    },
+}
+
+impl fmt::Display for ExecutionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ToolCallLimitReached { limit } => {
+                write!(f, "tool calls were refused after reaching the limit of {limit}")
+            }
+            Self::OutputNearLimit { bytes, limit } => {
+                write!(f, "output is close to the size limit ({bytes} of {limit} bytes)")
+            }
+            Self::OutputTruncated { bytes, limit } => {
+                write!(f, "output was truncated from {bytes} to {limit} bytes")
+            }
+            Self::NonFiniteNumber { context } => {
+                write!(f, "a NaN or infinite number in {context} became null")
+            }
+            Self::OutputReplaced { name } => {
+                write!(f, "output '{name}' was emitted more than once")
+            }
+            Self::CollectionTruncated { context, limit } => {
+                write!(f, "collections in {context} were truncated to the size limit of {limit}")
+            }
+            Self::ToolOutputTooLarge { tool, bytes, limit } => {
+                write!(f, "output of '{tool}' was rejected ({bytes} bytes, limit {limit})")
+            }
+            Self::TaskNotJoined { tool } => {
+                write!(f, "a spawned call to '{tool}' was never joined and was cancelled")
+            }
+        }
+    }
+}
+
+/// Default number of Rhai operations between progress callbacks
+pub const DEFAULT_PROGRESS_STRIDE: u64 = 1_000;
+
+/// Default limit on how deeply executions may nest
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Snapshot of a running script, passed to the progress callback.
+///
+/// See [`ExecutionOptions::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressInfo {
+    /// Rhai operations performed so far
+    pub operations: u64,
+    /// Wall-clock time since the script started, in milliseconds
+    pub elapsed_ms: u64,
+    /// Tool calls made so far, including refused ones
+    pub tool_calls_so_far: usize,
+}
+
+/// Callback observing a running script (native: `Send + Sync`)
+///
+/// Returning [`ControlFlow::Break`] cancels the execution.
+#[cfg(feature = "native")]
+pub type ProgressCallback = Arc<dyn Fn(ProgressInfo) -> ControlFlow<()> + Send + Sync>;
+
+/// Callback observing a running script (WASM: single-threaded)
+///
+/// Returning [`ControlFlow::Break`] cancels the execution.
+#[cfg(all(feature = "wasm", not(feature = "native")))]
+pub type ProgressCallback = Rc<dyn Fn(ProgressInfo) -> ControlFlow<()>>;
+
+/// What a script sees when one of its tool calls fails.
+///
+/// Covers calls that ran and failed as well as calls refused before
+/// running, e.g. over a limit or outside the allowed tools. Every failed
+/// call is recorded in the result's tool calls either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToolErrorMode {
+    /// The call returns its error text, e.g. `Tool error: not found`, and
+    /// the script carries on
+    #[default]
+    ReturnString,
+    /// The call throws the error text as a runtime error, which the script
+    /// can `catch`; uncaught, it fails the execution
+    Throw,
+}
+
+/// What a tool registered with
+/// [`register_binary_executor`](crate::ToolOrchestrator::register_binary_executor)
+/// returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolOutput {
+    /// Text, which the script receives as a string
+    Text(String),
+    /// Raw bytes, such as an image or output that isn't valid UTF-8; see
+    /// [`BinaryOutputMode`] for what the script receives
+    Bytes(Vec<u8>),
+}
+
+impl From<String> for ToolOutput {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Vec<u8>> for ToolOutput {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+/// What a script receives for a [`ToolOutput::Bytes`] output.
+///
+/// The [`ToolCall`] log always holds the bytes base64-encoded, with their
+/// length in [`ToolCall::binary_len`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryOutputMode {
+    /// A Rhai `Blob`, which Rhai limits to `max_array_size` bytes
+    #[default]
+    Blob,
+    /// A base64 string with padding
+    Base64,
+}
+
+/// How a script's final value is rendered as [`OrchestratorResult::output`].
+///
+/// Strings are always used verbatim and unit always becomes an empty string.
+/// In every format, output over `max_output_bytes` under
+/// [`OutputPolicy::Truncate`](crate::OutputPolicy::Truncate) first has its
+/// arrays elided to their first and last elements around a
+/// `"... N more items ..."` marker, so it stays parseable; only output that
+/// still doesn't fit is cut as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Compact JSON, e.g. `{"ids":[1,2]}`
+    #[default]
+    Json,
+    /// JSON indented by two spaces
+    PrettyJson,
+    /// Rhai's debug representation, e.g. `#{"ids": [1, 2]}`
+    Debug,
+}
+
+/// Cancels a running execution from outside of it.
+///
+/// Clones share the same flag. Once [`cancel`](Self::cancel) is called, the
+/// script stops at its next operation and fails with
+/// [`OrchestratorError::Cancelled`]; tool calls that haven't started are
+/// refused, and one that is already running is left to finish.
+///
+/// # Example
+///
+/// ```ignore
+/// let token = CancellationToken::new();
+/// let options = ExecutionOptions::new().with_cancellation(token.clone());
+/// std::thread::spawn(move || orchestrator.execute_with_options(script, limits, &options));
+///
+/// // Later, e.g. when the user closes the request
+/// token.cancel();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Shared<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that has not been cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every execution using this token or one of its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// An event sent to the channel of
+/// [`ExecutionOptions::with_event_sender`] (native only).
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub enum ExecutionEvent {
+    /// A tool call completed; sent as soon as its record exists
+    ToolCall(ToolCall),
+    /// The execution finished; always the last event of an execution
+    Finished {
+        /// The execution's [`execution_id`](OrchestratorResult::execution_id)
+        execution_id: String,
+        /// Whether the execution succeeded
+        success: bool,
+    },
+}
+
+/// Per-execution settings that are not resource limits.
+///
+/// Passed to [`ToolOrchestrator::execute_with_options`]; `execute` uses the
+/// defaults.
+///
+/// [`ToolOrchestrator::execute_with_options`]: crate::ToolOrchestrator::execute_with_options
+///
+/// # Example
+///
+/// ```ignore
+/// let options = ExecutionOptions::new().with_execution_id(request_id);
+/// let result = orchestrator.execute_with_options(script, limits, &options)?;
+/// assert_eq!(result.execution_id, request_id);
+/// ```
+#[derive(Clone)]
+pub struct ExecutionOptions {
+    /// Id to report for the execution instead of a generated UUID
+    pub execution_id: Option<String>,
+    /// Called every `progress_stride` operations while the script runs
+    pub progress: Option<ProgressCallback>,
+    /// Rhai operations between progress callbacks
+    pub progress_stride: u64,
+    /// How many executions may be nested through
+    /// [`execute_nested`](crate::ToolOrchestrator::execute_nested), counting
+    /// this one
+    pub max_depth: usize,
+    /// Whether tool inputs are checked against the schemas tools were
+    /// registered with (`input-validation` feature)
+    pub validate_inputs: bool,
+    /// Tool patterns the script may call (`None` for every tool); see
+    /// [`tool_matches`](crate::tool_matches) for the pattern syntax
+    pub allowed_tools: Option<Vec<String>>,
+    /// Tool patterns the script may not call, even if allowed
+    pub denied_tools: Vec<String>,
+    /// What a script sees when a tool call fails
+    pub tool_error_mode: ToolErrorMode,
+    /// What a script receives for a binary tool output
+    pub binary_output_mode: BinaryOutputMode,
+    /// How a final value that isn't a string is rendered as the output
+    pub output_format: OutputFormat,
+    /// Decimal places floats are written with, `None` for Rhai's default
+    pub float_precision: Option<usize>,
+    /// Canned tool responses by tool name; when set, no tool runs
+    pub dry_run_stubs: Option<HashMap<String, String>>,
+    /// Stops the execution once cancelled
+    pub cancellation: Option<CancellationToken>,
+    /// Variables defined before the script starts, by name
+    pub variables: BTreeMap<String, serde_json::Value>,
+    /// Whether tool call records carry their input fingerprint
+    pub call_fingerprints: bool,
+    /// Whether the result carries the script's source
+    pub include_script: bool,
+    /// Receives each completed tool call and a final event (native only)
+    #[cfg(feature = "native")]
+    pub event_sender: Option<Sender<ExecutionEvent>>,
+}
+
+impl ExecutionOptions {
+    /// Create options with every setting at its default.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            execution_id: None,
+            progress: None,
+            progress_stride: DEFAULT_PROGRESS_STRIDE,
+            max_depth: DEFAULT_MAX_DEPTH,
+            validate_inputs: true,
+            allowed_tools: None,
+            denied_tools: Vec::new(),
+            tool_error_mode: ToolErrorMode::ReturnString,
+            binary_output_mode: BinaryOutputMode::Blob,
+            output_format: OutputFormat::Json,
+            float_precision: None,
+            dry_run_stubs: None,
+            cancellation: None,
+            variables: BTreeMap::new(),
+            call_fingerprints: false,
+            include_script: false,
+            #[cfg(feature = "native")]
+            event_sender: None,
         }
     }
+
+    /// Use `id` as the execution id, e.g. to match a request id.
+    #[must_use]
+    pub fn with_execution_id(mut self, id: impl Into<String>) -> Self {
+        self.execution_id = Some(id.into());
+        self
+    }
+
+    /// Observe the script while it runs, e.g. to drive a progress bar.
+    ///
+    /// The callback runs on the executing thread every
+    /// [`progress_stride`](Self::with_progress_stride) operations, so keep it
+    /// cheap. Returning [`ControlFlow::Break`] stops the script, which then
+    /// fails with [`OrchestratorError::Cancelled`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let budget = limits.max_operations;
+    /// let options = ExecutionOptions::new().on_progress(move |info| {
+    ///     if info.operations > budget / 2 {
+    ///         tracing::warn!(ops = info.operations, "script past half its operation budget");
+    ///     }
+    ///     ControlFlow::Continue(())
+    /// });
+    /// ```
+    #[cfg(feature = "native")]
+    #[must_use]
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(ProgressInfo) -> ControlFlow<()> + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Observe the script while it runs (WASM version).
+    ///
+    /// See the native version for full documentation.
+    #[cfg(all(feature = "wasm", not(feature = "native")))]
+    #[must_use]
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(ProgressInfo) -> ControlFlow<()> + 'static,
+    {
+        self.progress = Some(Rc::new(callback));
+        self
+    }
+
+    /// Set how many Rhai operations pass between progress callbacks (builder pattern).
+    ///
+    /// Defaults to [`DEFAULT_PROGRESS_STRIDE`]; zero is treated as one.
+    #[must_use]
+    pub const fn with_progress_stride(mut self, stride: u64) -> Self {
+        self.progress_stride = stride;
+        self
+    }
+
+    /// Set how many executions may nest below and including this one (builder pattern).
+    ///
+    /// A tool whose executor runs scripts through
+    /// [`execute_nested`](crate::ToolOrchestrator::execute_nested) adds a
+    /// level each time it is called; the call that would go past `max_depth`
+    /// fails with [`OrchestratorError::MaxDepthExceeded`]. Defaults to
+    /// [`DEFAULT_MAX_DEPTH`]; zero is treated as one.
+    #[must_use]
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Turn checking tool inputs against their schemas on or off (builder pattern).
+    ///
+    /// On by default. Turning it off saves the validation cost for
+    /// executions whose input is already known to be well-formed, e.g.
+    /// replays of scripts that passed before.
+    #[must_use]
+    pub const fn with_input_validation(mut self, enabled: bool) -> Self {
+        self.validate_inputs = enabled;
+        self
+    }
+
+    /// Only let the script call tools matching one of `patterns` (builder pattern).
+    ///
+    /// Patterns are tool names, namespace globs such as `fs::*`, or `*`.
+    /// A call to any other tool is refused without running: it returns a
+    /// tool error and is recorded as a [`refused`](ToolCall::refused) call.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // A read-only run of an agent that may also write
+    /// let options = ExecutionOptions::new().with_allowed_tools(["fs::read", "search"]);
+    /// ```
+    #[must_use]
+    pub fn with_allowed_tools<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_tools = Some(patterns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Refuse calls to tools matching one of `patterns` (builder pattern).
+    ///
+    /// Takes precedence over [`with_allowed_tools`](Self::with_allowed_tools),
+    /// so `fs::*` can be allowed with `fs::delete` denied.
+    #[must_use]
+    pub fn with_denied_tools<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.denied_tools = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Choose what a script sees when a tool call fails (builder pattern).
+    ///
+    /// Defaults to [`ToolErrorMode::ReturnString`]. `call_parallel` always
+    /// returns failures in place, so one failed call doesn't lose the others.
+    #[must_use]
+    pub const fn with_tool_error_mode(mut self, mode: ToolErrorMode) -> Self {
+        self.tool_error_mode = mode;
+        self
+    }
+
+    /// Choose what a script receives for a binary tool output (builder
+    /// pattern).
+    ///
+    /// Defaults to [`BinaryOutputMode::Blob`]. Use
+    /// [`BinaryOutputMode::Base64`] for outputs larger than `max_array_size`
+    /// or for scripts that pass them on as text.
+    #[must_use]
+    pub const fn with_binary_output_mode(mut self, mode: BinaryOutputMode) -> Self {
+        self.binary_output_mode = mode;
+        self
+    }
+
+    /// Choose how a final value that isn't a string is rendered (builder
+    /// pattern).
+    ///
+    /// Defaults to [`OutputFormat::Json`]. [`structured_output`] holds the
+    /// value as JSON whatever the format.
+    ///
+    /// [`structured_output`]: OrchestratorResult::structured_output
+    #[must_use]
+    pub const fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Write floats with `precision` decimal places, rounding half to even
+    /// (builder pattern).
+    ///
+    /// Applies to the output, to floats nested in structured output, and to
+    /// `${...}` interpolation and `to_string` in the script, so a total
+    /// doesn't come out as `2384.4900000000002`. `None`, the default, keeps
+    /// Rhai's formatting.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let options = ExecutionOptions::new().with_float_precision(Some(2));
+    /// let result = orchestrator.execute_with_options("`total: ${0.1 + 0.2}`", limits, &options)?;
+    /// assert_eq!(result.output, "total: 0.30");
+    /// ```
+    #[must_use]
+    pub const fn with_float_precision(mut self, precision: Option<usize>) -> Self {
+        self.float_precision = precision;
+        self
+    }
+
+    /// Answer every tool call from `stubs` instead of running the tool
+    /// (builder pattern).
+    ///
+    /// Same as [`execute_dry_run`](crate::ToolOrchestrator::execute_dry_run),
+    /// combined with the other options.
+    #[must_use]
+    pub fn with_dry_run(mut self, stubs: HashMap<String, String>) -> Self {
+        self.dry_run_stubs = Some(stubs);
+        self
+    }
+
+    /// Stop the execution when `token` is cancelled (builder pattern).
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Define the variable `name` before the script starts (builder pattern).
+    ///
+    /// The value is converted like tool output passed through `parse_json`,
+    /// and checked against the execution's size limits. Unlike
+    /// [`ScriptTemplate`](crate::ScriptTemplate) placeholders, the script
+    /// source stays the same for every value, so compiled scripts can be
+    /// reused across requests.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let options = ExecutionOptions::new().with_variable("customer", json!({"id": "c-42"}));
+    /// orchestrator.execute_with_options("get_orders(customer.id).len()", limits, &options)?;
+    /// ```
+    #[must_use]
+    pub fn with_variable(mut self, name: impl Into<String>, value: serde_json::Value) -> Self {
+        self.variables.insert(name.into(), value);
+        self
+    }
+
+    /// Record each tool call's input fingerprint in its
+    /// [`fingerprint`](ToolCall::fingerprint) field (builder pattern).
+    ///
+    /// Off by default, which keeps the field out of serialized results.
+    #[must_use]
+    pub const fn with_call_fingerprints(mut self, enabled: bool) -> Self {
+        self.call_fingerprints = enabled;
+        self
+    }
+
+    /// Store the script's source in the result's
+    /// [`script`](OrchestratorResult::script) field (builder pattern).
+    ///
+    /// Off by default, since the source may hold sensitive data the caller
+    /// interpolated; [`script_hash`](OrchestratorResult::script_hash)
+    /// identifies the script either way.
+    #[must_use]
+    pub const fn with_include_script(mut self, enabled: bool) -> Self {
+        self.include_script = enabled;
+        self
+    }
+
+    /// Stream the execution's tool calls to a channel (builder pattern).
+    ///
+    /// Each completed [`ToolCall`] is sent as [`ExecutionEvent::ToolCall`]
+    /// while the script is still running, then [`ExecutionEvent::Finished`]
+    /// once the execution is over, so a consumer on another thread can ship
+    /// audit events without waiting for the result. Events from every
+    /// execution using these options go to the same channel. A dropped
+    /// receiver is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let (sender, receiver) = std::sync::mpsc::channel();
+    /// std::thread::spawn(move || {
+    ///     for event in receiver {
+    ///         if let ExecutionEvent::ToolCall(call) = event {
+    ///             audit_log.publish(&call);
+    ///         }
+    ///     }
+    /// });
+    /// let options = ExecutionOptions::new().with_event_sender(sender);
+    /// ```
+    #[cfg(feature = "native")]
+    #[must_use]
+    pub fn with_event_sender(mut self, sender: Sender<ExecutionEvent>) -> Self {
+        self.event_sender = Some(sender);
+        self
+    }
+}
+
+impl Default for ExecutionOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ExecutionOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ExecutionOptions");
+        debug
+            .field("execution_id", &self.execution_id)
+            .field("progress", &self.progress.as_ref().map(|_| "<callback>"))
+            .field("progress_stride", &self.progress_stride)
+            .field("max_depth", &self.max_depth)
+            .field("validate_inputs", &self.validate_inputs)
+            .field("allowed_tools", &self.allowed_tools)
+            .field("denied_tools", &self.denied_tools)
+            .field("tool_error_mode", &self.tool_error_mode)
+            .field("binary_output_mode", &self.binary_output_mode)
+            .field("output_format", &self.output_format)
+            .field("float_precision", &self.float_precision)
+            .field("dry_run_stubs", &self.dry_run_stubs)
+            .field("cancellation", &self.cancellation)
+            .field("variables", &self.variables)
+            .field("call_fingerprints", &self.call_fingerprints)
+            .field("include_script", &self.include_script);
+        #[cfg(feature = "native")]
+        debug.field("event_sender", &self.event_sender);
+        debug.finish()
+    }
+}
+
+/// Outcome of checking a script without running it.
+///
+/// Returned by [`ToolOrchestrator::validate_script`]. A script is `valid`
+/// when it compiles, every function it calls can be resolved and the tools
+/// it calls have the tools they require.
+///
+/// [`ToolOrchestrator::validate_script`]: crate::ToolOrchestrator::validate_script
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScriptValidation {
+    /// Whether the script compiles and calls no unknown functions
+    pub valid: bool,
+    /// Compilation errors (Rhai stops at the first one)
+    pub errors: Vec<ScriptDiagnostic>,
+    /// Registered tools the script calls, in order of first appearance
+    pub referenced_tools: Vec<String>,
+    /// Called functions that are neither tools, script or prelude functions,
+    /// nor part of the Rhai standard library
+    pub unknown_tools: Vec<String>,
+    /// Registered tools with names close to each unknown one, closest first;
+    /// unknown functions without a close tool are left out
+    #[serde(default)]
+    pub suggestions: BTreeMap<String, Vec<String>>,
+    /// Functions the script defines, in order of definition; an overloaded
+    /// name is listed once per arity
+    #[serde(default)]
+    pub defined_functions: Vec<FunctionInfo>,
+    /// Tools the script depends on, directly or through the tools they
+    /// require, whose requirements aren't registered
+    #[serde(default)]
+    pub missing_dependencies: Vec<MissingDependency>,
+}
+
+/// A problem found while validating a script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScriptDiagnostic {
+    /// What is wrong, without the position
+    pub message: String,
+    /// 1-based line, if known
+    pub line: Option<usize>,
+    /// 1-based column, if known
+    pub column: Option<usize>,
+}
+
+/// A tool whose required tools are not all registered.
+///
+/// Tools declare what they require with
+/// [`register_executor_with_requires`].
+///
+/// [`register_executor_with_requires`]: crate::ToolOrchestrator::register_executor_with_requires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MissingDependency {
+    /// The tool that declared the requirements
+    pub tool: String,
+    /// Its required tools that aren't registered, in declaration order
+    pub missing: Vec<String>,
+}
+
+/// A function defined by a script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FunctionInfo {
+    /// Function name
+    pub name: String,
+    /// Number of parameters
+    pub params: usize,
+    /// Statements in the body, nested ones included; a rough measure of size
+    pub statements: usize,
+}
+
+/// A tool a [`ToolProvider`](crate::ToolProvider) can resolve.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ToolInfo {
+    /// Name scripts call the tool by
+    pub name: String,
+    /// What the tool does
+    pub description: String,
 }
 
 /// Errors that can occur during orchestration.
@@ -180,7 +1489,20 @@ impl ToolCall {
 ///     Err(e) => eprintln!("Error: {}", e),
 /// }
 /// ```
-#[derive(Debug, Error)]
+///
+/// # Serialization
+///
+/// Errors serialize as a tagged object so the variant and its payload
+/// survive transport, with `type` set to [`error_code`](Self::error_code):
+///
+/// ```json
+/// {"type": "timeout", "details": 30000}
+/// {"type": "script_too_large", "details": {"bytes": 2000000, "limit": 1000000}}
+/// {"type": "invalid_limits", "details": {"type": "invalid_cost"}}
+/// ```
+#[derive(Debug, Error, Serialize, Deserialize)]
+#[serde(tag = "type", content = "details", rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum OrchestratorError {
     /// Script failed to compile due to syntax errors.
     #[error("Script compilation failed: {0}")]
@@ -217,6 +1539,202 @@ pub enum OrchestratorError {
     /// A registered tool returned an error during execution.
     #[error("Tool execution failed: {0}")]
     ToolError(String),
+
+    /// The script's final output exceeded `max_output_bytes` under
+    /// [`OutputPolicy::Error`](crate::OutputPolicy::Error).
+    ///
+    /// The contained value is the limit that was exceeded.
+    #[error("Script output exceeded maximum size ({0} bytes)")]
+    OutputTooLarge(usize),
+
+    /// Registering tools would silently replace existing ones, e.g. when
+    /// attaching a [`ToolSet`](crate::ToolSet), or a tool's name collides
+    /// with a keyword or built-in function.
+    #[error("Tool registration conflict: {0}")]
+    ToolConflict(String),
+
+    /// A JSON value passed into a script exceeds the size limits; see
+    /// [`json_to_dynamic_checked`](crate::json_to_dynamic_checked).
+    #[error("Value exceeds size limits: {0}")]
+    ValueTooLarge(String),
+
+    /// The [`ExecutionLimits`](crate::ExecutionLimits) would not let the
+    /// script run; see [`LimitsError`].
+    #[error("Invalid execution limits: {0}")]
+    InvalidLimits(#[from] LimitsError),
+
+    /// The script source exceeded `max_script_bytes`; it was rejected
+    /// before compilation.
+    #[error("Script is too large ({bytes} bytes, limit {limit})")]
+    ScriptTooLarge {
+        /// Size of the script in bytes
+        bytes: usize,
+        /// The `max_script_bytes` limit
+        limit: usize,
+    },
+
+    /// The script exceeded `max_script_lines`, `max_functions` or
+    /// `max_statements`.
+    #[error("Script has too many {measure} ({count}, limit {limit})")]
+    ScriptTooComplex {
+        /// What was counted: `"lines"`, `"functions"` or `"statements"`
+        #[serde(deserialize_with = "deserialize_measure")]
+        measure: StaticName,
+        /// How many the script has
+        count: usize,
+        /// The limit that was exceeded
+        limit: usize,
+    },
+
+    /// The progress callback returned [`ControlFlow::Break`]; see
+    /// [`ExecutionOptions::on_progress`].
+    #[error("Script execution was cancelled")]
+    Cancelled,
+
+    /// A nested execution would exceed the depth allowed by
+    /// [`ExecutionOptions::max_depth`]; see
+    /// [`ToolOrchestrator::execute_nested`](crate::ToolOrchestrator::execute_nested).
+    #[error("Maximum execution depth ({0}) exceeded")]
+    MaxDepthExceeded(usize),
+
+    /// A [`ScriptTemplate`](crate::ScriptTemplate) could not be rendered.
+    #[error("Invalid script template: {0}")]
+    #[serde(rename = "template_error")]
+    Template(#[from] TemplateError),
+
+    /// The JSON Schema given to `register_executor_with_schema` is not
+    /// valid (`input-validation` feature).
+    #[error("Invalid input schema for tool {0}")]
+    InvalidToolSchema(String),
+
+    /// The script kept repeating a tool call or failing, and was stopped by
+    /// the guard set with
+    /// [`ExecutionLimits::with_no_progress_guard`](crate::ExecutionLimits::with_no_progress_guard).
+    ///
+    /// The contained value describes the pattern that was detected.
+    #[error("Script stopped making progress: {0}")]
+    NoProgress(String),
+
+    /// Tools the script calls require tools that aren't registered, so the
+    /// script was rejected before it ran; see [`MissingDependency`].
+    #[error("Missing tool dependencies: {}", describe_missing(.0))]
+    MissingDependencies(Vec<MissingDependency>),
+
+    /// The state held for the execution (tool calls, variables, outputs and
+    /// the key-value store) grew past `max_total_state_bytes`; see
+    /// [`ExecutionLimits::with_max_total_state_bytes`](crate::ExecutionLimits::with_max_total_state_bytes).
+    ///
+    /// The contained value is the limit that was exceeded.
+    #[error("Script exceeded its memory budget ({0} bytes)")]
+    MemoryBudgetExceeded(usize),
+}
+
+/// `a requires b, c; d requires e`, for the error message.
+fn describe_missing(gaps: &[MissingDependency]) -> String {
+    let gaps: Vec<_> = gaps
+        .iter()
+        .map(|gap| format!("{} requires {}", gap.tool, gap.missing.join(", ")))
+        .collect();
+    gaps.join("; ")
+}
+
+impl OrchestratorError {
+    /// Stable, `snake_case` name of the error variant.
+    ///
+    /// Suitable for matching on in non-Rust consumers, where the
+    /// human-readable message may change between releases.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::CompilationError(_) => "compilation_error",
+            Self::ExecutionError(_) => "execution_error",
+            Self::MaxOperationsExceeded(_) => "max_operations_exceeded",
+            Self::MaxToolCallsExceeded(_) => "max_tool_calls_exceeded",
+            Self::Timeout(_) => "timeout",
+            Self::ToolNotFound(_) => "tool_not_found",
+            Self::ToolError(_) => "tool_error",
+            Self::OutputTooLarge(_) => "output_too_large",
+            Self::ToolConflict(_) => "tool_conflict",
+            Self::ValueTooLarge(_) => "value_too_large",
+            Self::InvalidLimits(_) => "invalid_limits",
+            Self::ScriptTooLarge { .. } => "script_too_large",
+            Self::ScriptTooComplex { .. } => "script_too_complex",
+            Self::Cancelled => "cancelled",
+            Self::MaxDepthExceeded(_) => "max_depth_exceeded",
+            Self::Template(_) => "template_error",
+            Self::InvalidToolSchema(_) => "invalid_tool_schema",
+            Self::NoProgress(_) => "no_progress",
+            Self::MissingDependencies(_) => "missing_dependencies",
+            Self::MemoryBudgetExceeded(_) => "memory_budget_exceeded",
+        }
+    }
+
+    /// Stable identifier of the error, e.g. `"timeout"`.
+    ///
+    /// The same value as [`kind`](Self::kind), and the `type` tag of the
+    /// serialized form.
+    #[must_use]
+    pub const fn error_code(&self) -> &'static str {
+        self.kind()
+    }
+
+    /// Convert into a serializable [`ErrorReport`].
+    #[must_use]
+    pub fn to_report(&self) -> ErrorReport {
+        ErrorReport {
+            kind: self.kind().to_string(),
+            message: self.to_string(),
+        }
+    }
+}
+
+/// Serializable form of an [`OrchestratorError`].
+///
+/// Flattens an error into its [`kind`](OrchestratorError::kind) and display
+/// message, for consumers that only show the message. Serialize the
+/// `OrchestratorError` itself to keep its payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ErrorReport {
+    /// Variant name, e.g. `"timeout"`; see [`OrchestratorError::kind`].
+    pub kind: String,
+    /// Human-readable error message.
+    pub message: String,
+}
+
+impl ErrorReport {
+    /// JSON Schema describing the serialized form of an error report.
+    #[cfg(feature = "schema")]
+    #[must_use]
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(Self).to_value()
+    }
+}
+
+impl From<&OrchestratorError> for ErrorReport {
+    fn from(error: &OrchestratorError) -> Self {
+        error.to_report()
+    }
+}
+
+/// The tagged serialized form, e.g.
+/// `{"type": "timeout", "details": 30000}`.
+impl From<&OrchestratorError> for serde_json::Value {
+    fn from(error: &OrchestratorError) -> Self {
+        serde_json::to_value(error).unwrap_or_default()
+    }
+}
+
+/// Measures reported by [`OrchestratorError::ScriptTooComplex`]
+const COMPLEXITY_MEASURES: [&str; 3] = ["lines", "functions", "statements"];
+
+/// Deserialize the measure of [`OrchestratorError::ScriptTooComplex`].
+fn deserialize_measure<'de, D>(deserializer: D) -> Result<&'static str, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let measure = String::deserialize(deserializer)?;
+    known_name(&measure, &COMPLEXITY_MEASURES)
 }
 
 #[cfg(test)]
@@ -246,6 +1764,175 @@ mod tests {
         assert_eq!(result.error, Some("failed".to_string()));
     }
 
+    fn sample_result(output: &str, calls: usize) -> OrchestratorResult {
+        let tool_calls = (0..calls)
+            .map(|i| {
+                ToolCall::new(
+                    format!("tool_{i}"),
+                    serde_json::json!({"large": "x".repeat(500)}),
+                    "y".repeat(500),
+                    i % 2 == 0,
+                    i as u64,
+                )
+            })
+            .collect();
+        OrchestratorResult::success(output.to_string(), tool_calls, 10)
+    }
+
+    #[test]
+    fn test_to_llm_string_compact() {
+        let result = sample_result("Total: 42", 2);
+        let text = result.to_llm_string(LlmFormatOptions::default());
+        assert_eq!(text, "status: ok\noutput: Total: 42\ntool_0(ok, 0ms)\ntool_1(failed, 1ms)");
+        // Tool inputs/outputs are never echoed
+        assert!(!text.contains("xxxx"));
+    }
+
+    #[test]
+    fn test_to_llm_string_never_exceeds_budget() {
+        let result = sample_result(&"abcdefghij".repeat(1_000), 50);
+        for budget in [0, 5, 20, 40, 100, 333, 1_000, 20_000] {
+            let text = result.to_llm_string(LlmFormatOptions::default().with_max_chars(budget));
+            assert!(
+                text.chars().count() <= budget,
+                "budget {budget} exceeded: {}",
+                text.chars().count()
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_llm_string_keeps_head_and_tail() {
+        let output = format!("HEAD{}TAIL", "-".repeat(5_000));
+        let result = sample_result(&output, 0);
+        let text = result.to_llm_string(LlmFormatOptions::default().with_max_chars(200));
+        assert!(text.contains("output: HEAD"));
+        assert!(text.ends_with("TAIL"));
+        assert!(text.contains("chars omitted"));
+        assert_eq!(text.chars().count(), 200);
+    }
+
+    #[test]
+    fn test_truncate_middle_reports_exact_omission() {
+        let s = "0123456789".repeat(10);
+        let t = truncate_middle(&s, 40);
+        assert_eq!(t.chars().count(), 40);
+
+        let marker_start = t.find("…[").unwrap();
+        let marker_end = t.find("]…").unwrap() + "]…".len();
+        let omitted: usize = t[marker_start + "…[".len()..]
+            .split(' ')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let kept = t.chars().count() - t[marker_start..marker_end].chars().count();
+        assert_eq!(omitted + kept, 100);
+    }
+
+    #[test]
+    fn test_to_llm_string_summarizes_dropped_calls() {
+        let result = sample_result("ok", 100);
+        let text = result.to_llm_string(LlmFormatOptions::default().with_max_chars(120));
+        assert!(text.contains("more calls"));
+        let without = result.to_llm_string(LlmFormatOptions::default().with_tool_calls(false));
+        assert_eq!(without, "status: ok\noutput: ok");
+    }
+
+    #[test]
+    fn test_to_llm_string_summarizes_warnings() {
+        let mut result = sample_result("ok", 0);
+        result.warnings = vec![
+            ExecutionWarning::ToolCallLimitReached { limit: 5 },
+            ExecutionWarning::OutputReplaced { name: "ids".to_string() },
+        ];
+        let text = result.to_llm_string(LlmFormatOptions::default());
+        assert_eq!(
+            text,
+            "status: ok\noutput: ok\nwarnings: tool calls were refused after reaching the \
+             limit of 5; output 'ids' was emitted more than once"
+        );
+        let without = result.to_llm_string(LlmFormatOptions::default().with_warnings(false));
+        assert_eq!(without, "status: ok\noutput: ok");
+    }
+
+    #[test]
+    fn test_tool_call_status() {
+        let input = serde_json::json!(1);
+        let call = ToolCall::new("fetch".to_string(), input, String::new(), false, 0);
+        assert_eq!(call.status, ToolCallStatus::Error);
+        let call = call.with_status(ToolCallStatus::Cached);
+        assert!(call.success);
+
+        let mut json = serde_json::to_value(call.with_status(ToolCallStatus::TimedOut)).unwrap();
+        assert_eq!(json["status"], "timed_out");
+        assert_eq!(json["success"], false);
+        assert_eq!(ToolCallStatus::TimedOut.as_str(), "timed_out");
+
+        // Records serialized before the status existed still deserialize
+        json.as_object_mut().unwrap().remove("status");
+        let call: ToolCall = serde_json::from_value(json).unwrap();
+        assert_eq!(call.status, ToolCallStatus::Ok);
+        assert!(ToolCallStatus::RateLimited.is_refusal());
+        assert!(!ToolCallStatus::LimitExceeded.is_refusal());
+        assert!(ToolCallStatus::CallLimitExceeded.is_refusal());
+        assert_eq!(ToolCallStatus::CostLimitExceeded.as_str(), "cost_limit_exceeded");
+    }
+
+    #[test]
+    fn test_warning_serialization() {
+        let warning = ExecutionWarning::OutputNearLimit { bytes: 95, limit: 100 };
+        let json = serde_json::to_value(&warning).unwrap();
+        let expected = serde_json::json!({"kind": "output_near_limit", "bytes": 95, "limit": 100});
+        assert_eq!(json, expected);
+        assert_eq!(serde_json::from_value::<ExecutionWarning>(json).unwrap(), warning);
+
+        // Results serialized before warnings existed still deserialize
+        let mut json = serde_json::to_value(sample_result("ok", 0)).unwrap();
+        json.as_object_mut().unwrap().remove("warnings");
+        let result: OrchestratorResult = serde_json::from_value(json).unwrap();
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_to_llm_string_includes_error() {
+        let result = OrchestratorResult::error("Tool not found: frobnicate".to_string(), vec![], 3);
+        let text = result.to_llm_string(LlmFormatOptions::default());
+        assert_eq!(text, "status: error\nerror: Tool not found: frobnicate");
+    }
+
+    #[test]
+    fn test_estimated_tokens() {
+        let result = sample_result(&"a".repeat(400), 0);
+        assert_eq!(result.estimated_tokens(), 100);
+        assert_eq!(estimate_tokens("abc"), 0);
+    }
+
+    #[test]
+    fn test_execution_timing_breakdown() {
+        let call = |name: &str, duration_ms| {
+            ToolCall::new(name.to_string(), serde_json::json!({}), String::new(), true, duration_ms)
+        };
+        let calls = [call("a", 5), call("b", 40), call("c", 5), call("d", 20)];
+        let timing = ExecutionTiming::new(100, 3, &calls);
+        assert_eq!(timing.tool_time_ms, 70);
+        assert_eq!(timing.script_time_ms, 30);
+        assert_eq!(timing.compile_ms, 3);
+        let slowest = [("b".to_string(), 40), ("d".to_string(), 20), ("a".to_string(), 5)];
+        assert_eq!(timing.slowest_calls, slowest);
+
+        // Overlapping parallel calls can add up to more than the total
+        let timing = ExecutionTiming::new(50, 0, &calls);
+        assert_eq!(timing.script_time_ms, 0);
+
+        let result: OrchestratorResult = serde_json::from_str(
+            r#"{"success": true, "output": "", "tool_calls": [], "execution_time_ms": 0,
+                "error": null}"#,
+        )
+        .unwrap();
+        assert_eq!(result.timing, ExecutionTiming::default());
+    }
+
     #[test]
     fn test_tool_call_new() {
         let call = ToolCall::new(
@@ -258,4 +1945,248 @@ mod tests {
         assert_eq!(call.tool_name, "test_tool");
         assert!(call.success);
     }
+
+    #[test]
+    fn test_canonical_json_hash_normalizes_numbers() {
+        use serde_json::json;
+
+        let hash = |value| canonical_json_hash(&value);
+        assert_eq!(hash(json!({"n": 1})), hash(json!({"n": 1.0})));
+        assert_eq!(hash(json!([0])), hash(json!([-0.0])));
+        assert_ne!(hash(json!({"n": 1})), hash(json!({"n": 1.5})));
+        assert_ne!(hash(json!({"n": 1})), hash(json!({"n": "1"})));
+        assert_ne!(hash(json!([1, 2])), hash(json!([2, 1])));
+        // Fixed across runs and Rust versions
+        assert_eq!(hash(json!(null)), 0x5b9b_c4ba_5281_08e4);
+    }
+
+    #[test]
+    fn test_input_fingerprint_covers_tool_name_and_input() {
+        use serde_json::json;
+
+        let call = |name: &str, input| {
+            ToolCall::new(name.to_string(), input, String::new(), true, 0)
+        };
+        let fingerprint = call("search", json!({"q": "rust"})).input_fingerprint();
+        let failed = call("search", json!({"q": "rust"})).with_status(ToolCallStatus::Error);
+        assert_eq!(failed.input_fingerprint(), fingerprint);
+        assert_ne!(call("lookup", json!({"q": "rust"})).input_fingerprint(), fingerprint);
+        assert_ne!(call("search", json!({"q": "go"})).input_fingerprint(), fingerprint);
+
+        let json = serde_json::to_value(call("search", json!({}))).unwrap();
+        assert!(json.get("fingerprint").is_none());
+    }
+
+    // proptest is a native-only dev-dependency
+    #[cfg(not(target_arch = "wasm32"))]
+    mod fingerprint_properties {
+        use super::super::{canonical_json_hash, write_canonical_json};
+        use proptest::prelude::*;
+        use serde_json::{Map, Value};
+
+        /// JSON text of an object with `members`, keys and value texts, in
+        /// that order.
+        fn object_text<'a>(members: impl Iterator<Item = (&'a String, String)>) -> String {
+            let members: Vec<_> = members
+                .map(|(key, value)| format!("{}:{value}", Value::from(key.as_str())))
+                .collect();
+            format!("{{{}}}", members.join(","))
+        }
+
+        fn canonical(value: &Value) -> String {
+            let mut text = String::new();
+            write_canonical_json(value, &mut text);
+            text
+        }
+
+        fn scalar() -> impl Strategy<Value = Value> {
+            prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::Bool),
+                any::<i64>().prop_map(Value::from),
+                "[a-z0-9 ]{0,8}".prop_map(Value::from),
+            ]
+        }
+
+        fn json() -> impl Strategy<Value = Value> {
+            scalar().prop_recursive(3, 32, 6, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+                    prop::collection::btree_map("[a-z]{1,4}", inner, 0..6)
+                        .prop_map(|map| Value::Object(map.into_iter().collect())),
+                ]
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn key_order_does_not_matter(
+                entries in prop::collection::btree_map("[a-z]{1,6}", json(), 1..8),
+            ) {
+                // Parsed from text, so the maps hold the keys in the order
+                // written when serde_json preserves it
+                let forward = object_text(entries.iter().map(|(k, v)| (k, v.to_string())));
+                let backward = object_text(entries.iter().rev().map(|(k, v)| (k, v.to_string())));
+                let forward: Value = serde_json::from_str(&forward).unwrap();
+                let backward: Value = serde_json::from_str(&backward).unwrap();
+                prop_assert_eq!(canonical_json_hash(&forward), canonical_json_hash(&backward));
+
+                // Whatever order the map holds, members are written sorted by key
+                let sorted = object_text(entries.iter().map(|(k, v)| (k, canonical(v))));
+                prop_assert_eq!(canonical(&backward), sorted);
+            }
+
+            #[test]
+            fn changing_a_value_changes_the_hash(
+                entries in prop::collection::btree_map("[a-z]{1,6}", json(), 1..8),
+                replacement in json(),
+                index in any::<prop::sample::Index>(),
+            ) {
+                let mut changed: Map<String, Value> = entries.clone().into_iter().collect();
+                let key = index.get(&entries.keys().collect::<Vec<_>>()).to_string();
+                prop_assume!(changed[&key] != replacement);
+                changed.insert(key, replacement);
+                let original = Value::Object(entries.into_iter().collect());
+                prop_assert_ne!(
+                    canonical_json_hash(&original),
+                    canonical_json_hash(&Value::Object(changed))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_kind_and_report() {
+        let err = OrchestratorError::Timeout(500);
+        assert_eq!(err.kind(), "timeout");
+        let report = ErrorReport::from(&err);
+        assert_eq!(report.kind, "timeout");
+        assert_eq!(report.message, "Script execution timed out after 500ms");
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<ErrorReport>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn test_every_error_round_trips_through_serde() {
+        let errors = [
+            OrchestratorError::CompilationError("bad token".to_string()),
+            OrchestratorError::ExecutionError("oops".to_string()),
+            OrchestratorError::MaxOperationsExceeded(100),
+            OrchestratorError::MaxToolCallsExceeded(5),
+            OrchestratorError::Timeout(30_000),
+            OrchestratorError::ToolNotFound("fetch".to_string()),
+            OrchestratorError::ToolError("boom".to_string()),
+            OrchestratorError::OutputTooLarge(1_024),
+            OrchestratorError::ToolConflict("fs::read".to_string()),
+            OrchestratorError::ValueTooLarge("array of 20000".to_string()),
+            OrchestratorError::InvalidLimits(LimitsError::ZeroLimit { field: "timeout_ms" }),
+            OrchestratorError::ScriptTooLarge { bytes: 2_000, limit: 1_000 },
+            OrchestratorError::ScriptTooComplex { measure: "functions", count: 3, limit: 2 },
+            OrchestratorError::Cancelled,
+            OrchestratorError::MaxDepthExceeded(3),
+            OrchestratorError::Template(TemplateError::VarsNotObject),
+            OrchestratorError::InvalidToolSchema("fetch: bad type".to_string()),
+            OrchestratorError::NoProgress("the last 3 tool calls failed".to_string()),
+            OrchestratorError::MissingDependencies(vec![MissingDependency {
+                tool: "summarize".to_string(),
+                missing: vec!["fetch_document".to_string()],
+            }]),
+            OrchestratorError::MemoryBudgetExceeded(1_000_000),
+        ];
+        for err in &errors {
+            let json = serde_json::Value::from(err);
+            assert_eq!(json["type"], err.error_code());
+            let back: OrchestratorError = serde_json::from_str(&json.to_string()).unwrap();
+            assert_eq!(back.to_string(), err.to_string());
+            assert_eq!(serde_json::Value::from(&back), json);
+        }
+        let codes: std::collections::HashSet<&str> =
+            errors.iter().map(OrchestratorError::error_code).collect();
+        assert_eq!(codes.len(), errors.len(), "every variant is covered once");
+
+        assert_eq!(
+            serde_json::Value::from(&OrchestratorError::Timeout(500)),
+            serde_json::json!({"type": "timeout", "details": 500})
+        );
+        assert_eq!(
+            serde_json::Value::from(&errors[12]),
+            serde_json::json!({
+                "type": "script_too_complex",
+                "details": {"measure": "functions", "count": 3, "limit": 2},
+            })
+        );
+        assert_eq!(
+            serde_json::Value::from(&errors[10]),
+            serde_json::json!({
+                "type": "invalid_limits",
+                "details": {"type": "zero_limit", "details": {"field": "timeout_ms"}},
+            })
+        );
+
+        let unknown = r#"{"type": "script_too_complex", "details":
+            {"measure": "loops", "count": 3, "limit": 2}}"#;
+        assert!(serde_json::from_str::<OrchestratorError>(unknown).is_err());
+    }
+
+    /// Check that `value` only uses properties declared by `schema` and
+    /// contains all of its required properties.
+    #[cfg(feature = "schema")]
+    fn assert_matches_schema(value: &serde_json::Value, schema: &serde_json::Value) {
+        let props = schema["properties"].as_object().expect("schema has properties");
+        let object = value.as_object().expect("value is an object");
+        for key in object.keys() {
+            assert!(props.contains_key(key), "`{key}` missing from schema");
+        }
+        for key in schema["required"].as_array().into_iter().flatten() {
+            let key = key.as_str().unwrap();
+            assert!(object.contains_key(key), "required `{key}` not serialized");
+        }
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_json_schema_round_trips_serialized_result() {
+        let mut result = sample_result("done", 2);
+        result.structured_output = Some(serde_json::json!({"n": 1}));
+        let value = serde_json::to_value(&result).unwrap();
+        let schema = OrchestratorResult::json_schema();
+        assert_matches_schema(&value, &schema);
+
+        let call_schema = ToolCall::json_schema();
+        for call in value["tool_calls"].as_array().unwrap() {
+            assert_matches_schema(call, &call_schema);
+        }
+
+        let report = serde_json::to_value(OrchestratorError::ToolNotFound("x".into()).to_report())
+            .unwrap();
+        assert_matches_schema(&report, &ErrorReport::json_schema());
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_json_schema_marks_defaulted_fields_optional() {
+        use crate::sandbox::ExecutionLimits;
+
+        let schema = OrchestratorResult::json_schema();
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap())
+            .collect();
+        for field in [
+            "total_tool_output_bytes",
+            "dry_run",
+            "structured_output",
+            "execution_id",
+            "total_cost",
+        ] {
+            assert!(!required.contains(&field), "`{field}` should be optional");
+        }
+        assert!(required.contains(&"success"));
+        assert!(required.contains(&"output"));
+
+        let limits = ExecutionLimits::json_schema();
+        assert!(limits.get("required").is_none());
+    }
 }
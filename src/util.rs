@@ -0,0 +1,47 @@
+//! String helpers for hosts and the orchestrator itself.
+//!
+//! Tool output is arbitrary UTF-8, so slicing it at a byte offset such as
+//! `&content[..1000]` panics whenever the offset lands inside a multibyte
+//! character. [`truncate_utf8`] cuts at the nearest character boundary
+//! instead; the orchestrator uses it wherever it caps text by size.
+
+/// The longest prefix of `s` that is at most `max_bytes` long and doesn't
+/// split a character.
+///
+/// # Example
+///
+/// ```ignore
+/// assert_eq!(truncate_utf8("héllo", 2), "h");
+/// assert_eq!(truncate_utf8("héllo", 3), "hé");
+/// ```
+#[must_use]
+pub fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    // A character is at most 4 bytes, so this takes at most 3 steps
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_utf8_at_every_offset() {
+        for s in ["", "plain ascii", "héllo wörld", "日本語のテキスト", "a😀b👍🏽c", "👨‍👩‍👧"] {
+            for max_bytes in 0..=s.len() + 2 {
+                let cut = truncate_utf8(s, max_bytes);
+                assert!(s.starts_with(cut));
+                assert!(cut.len() <= max_bytes);
+                // Nothing that would have fit was dropped
+                let next = s[cut.len()..].chars().next().map_or(0, char::len_utf8);
+                assert!(cut.len() == s.len() || cut.len() + next > max_bytes, "{s:?} {max_bytes}");
+            }
+        }
+    }
+}
@@ -3,26 +3,50 @@
 //! This module provides JavaScript-compatible bindings for the tool orchestrator,
 //! allowing AI models to execute Rhai scripts that call registered tools from the browser.
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use rhai::packages::{Package, StandardPackage};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
-use crate::engine::dynamic_to_json;
-use crate::sandbox::ExecutionLimits as CoreExecutionLimits;
+use crate::engine::{
+    add_warning, budget_module, call_state_bytes, call_tool_module, charge_tool_output,
+    check_language_restrictions, check_script_complexity, check_script_size,
+    compile_error_message, configure_engine, count_tool_call, deadline_refusal, did_you_mean,
+    dynamic_to_json, early_exit, eval_error, execution_function_names, exhausted_limit,
+    exit_module, filter_strings, first_tool_call, fit_output, float_format_module,
+    for_each_call, has_non_finite, history_module, is_builtin_call, kv_module,
+    map_with_tool_module, module_function_names,
+    new_execution_id, output_size_warning, oversized_output, outputs_module, read_outputs,
+    script_output, sequential_call_parallel_module, sequential_spawn_tool_module,
+    set_tool_overloads, size_limit_module, task_module, tool_input_refusal, EarlyExit,
+    OperationMeter, ProgressWatch, SharedKvStore, SharedOutputs, SharedWarnings, StateMeter,
+    TaskHandle, MAX_CALL_DEPTH, MAX_EXPR_DEPTH,
+};
+use crate::output_filters;
+use crate::sandbox::{
+    ExecutionLimits as CoreExecutionLimits, LanguageRestrictions, LimitsError, NoProgressConfig,
+    OutputPolicy, ProfileRegistry, SizeLimitBehavior,
+};
 
 // ============================================================================
 // Engine Configuration Constants
 // ============================================================================
 
-/// Maximum expression nesting depth (prevents stack overflow from deeply nested expressions)
-const MAX_EXPR_DEPTH: usize = 64;
+/// Number of operations between progress events sent to the event callback
+const PROGRESS_EVENT_INTERVAL: u64 = 1_000;
 
-/// Maximum function call nesting depth (prevents stack overflow from deep recursion)
-const MAX_CALL_DEPTH: usize = 64;
+/// Maximum characters of tool input included in `tool_call_start` events
+const EVENT_INPUT_PREVIEW_CHARS: usize = 200;
 
-use crate::types::{OrchestratorResult as CoreOrchestratorResult, ToolCall as CoreToolCall};
+use crate::types::{
+    script_hash, ExecutionTiming, ExecutionWarning, OrchestratorError,
+    OrchestratorResult as CoreOrchestratorResult, OutputFormat, ScriptDiagnostic,
+    ToolCall as CoreToolCall, ToolCallStatus,
+};
 
 // ============================================================================
 // WASM-compatible ExecutionLimits wrapper
@@ -64,6 +88,51 @@ impl ExecutionLimits {
         }
     }
 
+    /// Create limits that never stop a script (fully trusted scripts only).
+    #[wasm_bindgen]
+    #[must_use]
+    pub fn unlimited() -> Self {
+        Self {
+            inner: CoreExecutionLimits::unlimited(),
+        }
+    }
+
+    /// Create the limits of a preset profile: `"quick"`, `"default"`,
+    /// `"extended"` or `"unlimited"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the known profiles for any other name.
+    #[wasm_bindgen(js_name = fromProfile)]
+    pub fn from_profile(name: &str) -> Result<ExecutionLimits, JsValue> {
+        ProfileRegistry::new()
+            .resolve(name)
+            .map(|inner| Self { inner })
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Create limits from a plain object, e.g. `{ maxToolCalls: 5, timeoutMs: 3000 }`.
+    ///
+    /// Keys may be camelCase or snake_case, in nested objects such as
+    /// `language` and `noProgressGuard` too. Limits left out keep their
+    /// defaults, or those of a preset named by a `profile` key, so
+    /// `{ profile: "quick", timeoutMs: 1000 }` is `quick()` with a shorter
+    /// timeout; `undefined` and `null` give the default limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming any unknown key, with the closest known ones,
+    /// so a typo like `timoutMs` isn't silently ignored, and for values of
+    /// the wrong type.
+    #[wasm_bindgen(js_name = fromObject)]
+    pub fn from_object(options: JsValue) -> Result<ExecutionLimits, JsValue> {
+        let options: serde_json::Value = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid limits: {e}")))?;
+        limits_from_json(options, &ProfileRegistry::new())
+            .map(|inner| Self { inner })
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
     /// Get max operations.
     #[wasm_bindgen(getter)]
     #[must_use]
@@ -138,6 +207,334 @@ impl ExecutionLimits {
     pub fn set_max_array_size(&mut self, value: usize) {
         self.inner.max_array_size = value;
     }
+
+    /// Get max bytes a single tool call may return.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_tool_output_bytes(&self) -> usize {
+        self.inner.max_tool_output_bytes
+    }
+
+    /// Set max bytes a single tool call may return.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_tool_output_bytes(&mut self, value: usize) {
+        self.inner.max_tool_output_bytes = value;
+    }
+
+    /// Get max serialized size of a single tool call's input in bytes.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_tool_input_bytes(&self) -> usize {
+        self.inner.max_tool_input_bytes
+    }
+
+    /// Set max serialized size of a single tool call's input in bytes.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_tool_input_bytes(&mut self, value: usize) {
+        self.inner.max_tool_input_bytes = value;
+    }
+
+    /// Get max cumulative tool output in bytes.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_total_tool_output_bytes(&self) -> usize {
+        self.inner.max_total_tool_output_bytes
+    }
+
+    /// Set max cumulative tool output in bytes.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_total_tool_output_bytes(&mut self, value: usize) {
+        self.inner.max_total_tool_output_bytes = value;
+    }
+
+    /// Get max final output size in bytes.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_output_bytes(&self) -> usize {
+        self.inner.max_output_bytes
+    }
+
+    /// Set max final output size in bytes.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_output_bytes(&mut self, value: usize) {
+        self.inner.max_output_bytes = value;
+    }
+
+    /// Get the oversized output policy (`"truncate"` or `"error"`).
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn output_policy(&self) -> String {
+        match self.inner.output_policy {
+            OutputPolicy::Truncate => "truncate".to_string(),
+            OutputPolicy::Error => "error".to_string(),
+        }
+    }
+
+    /// Set the oversized output policy (`"truncate"` or `"error"`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other policy name.
+    #[wasm_bindgen(setter)]
+    pub fn set_output_policy(&mut self, value: &str) -> Result<(), JsValue> {
+        self.inner.output_policy = match value {
+            "truncate" => OutputPolicy::Truncate,
+            "error" => OutputPolicy::Error,
+            other => return Err(JsValue::from_str(&format!("Unknown output policy: {other}"))),
+        };
+        Ok(())
+    }
+
+    /// Get the oversized collection behavior (`"abort"` or `"truncate_with_warning"`).
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn size_limit_behavior(&self) -> String {
+        match self.inner.size_limit_behavior {
+            SizeLimitBehavior::Abort => "abort".to_string(),
+            SizeLimitBehavior::TruncateWithWarning => "truncate_with_warning".to_string(),
+        }
+    }
+
+    /// Set the oversized collection behavior (`"abort"` or `"truncate_with_warning"`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other behavior name.
+    #[wasm_bindgen(setter)]
+    pub fn set_size_limit_behavior(&mut self, value: &str) -> Result<(), JsValue> {
+        self.inner.size_limit_behavior = match value {
+            "abort" => SizeLimitBehavior::Abort,
+            "truncate_with_warning" => SizeLimitBehavior::TruncateWithWarning,
+            other => {
+                return Err(JsValue::from_str(&format!("Unknown size limit behavior: {other}")));
+            }
+        };
+        Ok(())
+    }
+
+    /// Get whether deterministic mode is enabled.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn deterministic(&self) -> bool {
+        self.inner.deterministic
+    }
+
+    /// Enable or disable deterministic mode (timing fields reported as zero).
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_deterministic(&mut self, value: bool) {
+        self.inner.deterministic = value;
+    }
+
+    /// Get max script size in bytes.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_script_bytes(&self) -> usize {
+        self.inner.max_script_bytes
+    }
+
+    /// Set max script size in bytes.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_script_bytes(&mut self, value: usize) {
+        self.inner.max_script_bytes = value;
+    }
+
+    /// Get max script lines.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_script_lines(&self) -> usize {
+        self.inner.max_script_lines
+    }
+
+    /// Set max script lines.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_script_lines(&mut self, value: usize) {
+        self.inner.max_script_lines = value;
+    }
+
+    /// Get max user-defined functions.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_functions(&self) -> usize {
+        self.inner.max_functions
+    }
+
+    /// Set max user-defined functions.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_functions(&mut self, value: usize) {
+        self.inner.max_functions = value;
+    }
+
+    /// Get max compiled statements.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_statements(&self) -> usize {
+        self.inner.max_statements
+    }
+
+    /// Set max compiled statements.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_statements(&mut self, value: usize) {
+        self.inner.max_statements = value;
+    }
+
+    /// Get the time a tool call needs left before the timeout to start, in milliseconds.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn min_remaining_ms_for_tool_call(&self) -> u64 {
+        self.inner.min_remaining_ms_for_tool_call
+    }
+
+    /// Set the time a tool call needs left before the timeout to start (0 to disable).
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_min_remaining_ms_for_tool_call(&mut self, value: u64) {
+        self.inner.min_remaining_ms_for_tool_call = value;
+    }
+
+    /// Get the operations charged for each tool call.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn tool_call_operation_cost(&self) -> u64 {
+        self.inner.tool_call_operation_cost
+    }
+
+    /// Set the operations charged for each tool call (0 to charge nothing).
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_tool_call_operation_cost(&mut self, value: u64) {
+        self.inner.tool_call_operation_cost = value;
+    }
+
+    /// Get max named outputs a script may emit.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_outputs(&self) -> usize {
+        self.inner.max_outputs
+    }
+
+    /// Set max named outputs a script may emit.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_outputs(&mut self, value: usize) {
+        self.inner.max_outputs = value;
+    }
+
+    /// Get max serialized size of the named outputs in bytes.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_outputs_bytes(&self) -> usize {
+        self.inner.max_outputs_bytes
+    }
+
+    /// Set max serialized size of the named outputs in bytes.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_outputs_bytes(&mut self, value: usize) {
+        self.inner.max_outputs_bytes = value;
+    }
+
+    /// Get max keys in the script's key-value store.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_kv_entries(&self) -> usize {
+        self.inner.max_kv_entries
+    }
+
+    /// Set max keys in the script's key-value store.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_kv_entries(&mut self, value: usize) {
+        self.inner.max_kv_entries = value;
+    }
+
+    /// Get max serialized size of the key-value store in bytes.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_kv_bytes(&self) -> usize {
+        self.inner.max_kv_bytes
+    }
+
+    /// Set max serialized size of the key-value store in bytes.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_kv_bytes(&mut self, value: usize) {
+        self.inner.max_kv_bytes = value;
+    }
+
+    /// Get max bytes of state held for one execution: tool calls,
+    /// outputs and the key-value store.
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn max_total_state_bytes(&self) -> usize {
+        self.inner.max_total_state_bytes
+    }
+
+    /// Set max bytes of state held for one execution.
+    #[wasm_bindgen(setter)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_max_total_state_bytes(&mut self, value: usize) {
+        self.inner.max_total_state_bytes = value;
+    }
+
+    /// Stop scripts after `max_repeated_calls` identical consecutive tool
+    /// calls or `max_failed_calls` consecutive failed ones (0 disables either).
+    #[wasm_bindgen(js_name = setNoProgressGuard)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_no_progress_guard(&mut self, max_repeated_calls: usize, max_failed_calls: usize) {
+        self.inner.no_progress_guard =
+            Some(NoProgressConfig { max_repeated_calls, max_failed_calls });
+    }
+
+    /// Remove the no-progress guard.
+    #[wasm_bindgen(js_name = clearNoProgressGuard)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn clear_no_progress_guard(&mut self) {
+        self.inner.no_progress_guard = None;
+    }
+
+    /// Allow or forbid `while`/`loop`/`do` loops, closures, `fn` definitions
+    /// and `eval` in scripts; all are allowed by default.
+    #[wasm_bindgen(js_name = setLanguageRestrictions)]
+    #[allow(clippy::missing_const_for_fn)] // wasm_bindgen doesn't support const fn
+    pub fn set_language_restrictions(
+        &mut self,
+        allow_unbounded_loops: bool,
+        allow_closures: bool,
+        allow_function_definitions: bool,
+        allow_eval: bool,
+    ) {
+        self.inner.language = LanguageRestrictions {
+            allow_unbounded_loops,
+            allow_closures,
+            allow_function_definitions,
+            allow_eval,
+        };
+    }
 }
 
 impl Default for ExecutionLimits {
@@ -146,12 +543,154 @@ impl Default for ExecutionLimits {
     }
 }
 
+/// Limits from a partial JSON object whose keys may be camelCase, starting
+/// from the limits of its `profile`, if it names one in `profiles`.
+fn limits_from_json(
+    mut options: serde_json::Value,
+    profiles: &ProfileRegistry,
+) -> Result<CoreExecutionLimits, String> {
+    if options.is_null() {
+        return Ok(CoreExecutionLimits::default());
+    }
+    let Some(map) = options.as_object_mut() else {
+        return Err("Invalid limits: expected an object".to_string());
+    };
+    let base = match map.remove("profile") {
+        None => CoreExecutionLimits::default(),
+        Some(serde_json::Value::String(name)) => {
+            profiles.resolve(&name).map_err(|e| format!("Invalid limits: {e}"))?
+        }
+        Some(_) => return Err("Invalid limits: profile must be a string".to_string()),
+    };
+    // Optional sections are present here so their keys can be checked too
+    let known = CoreExecutionLimits {
+        no_progress_guard: Some(NoProgressConfig::default()),
+        ..CoreExecutionLimits::default()
+    };
+    let known = serde_json::to_value(known).map_err(|e| e.to_string())?;
+    to_field_names(&mut options, &known, "")?;
+    let mut limits = serde_json::to_value(base).map_err(|e| e.to_string())?;
+    merge_json(&mut limits, options);
+    serde_json::from_value(limits).map_err(|e| format!("Invalid limits: {e}"))
+}
+
+/// Overwrite the members of `base` with those of `overrides`, merging
+/// nested objects instead of replacing them.
+fn merge_json(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge_json(base.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Rename the keys of `value`, recursively, to the snake_case fields of
+/// `known`, failing on keys that match none of them.
+fn to_field_names(
+    value: &mut serde_json::Value,
+    known: &serde_json::Value,
+    path: &str,
+) -> Result<(), String> {
+    let (serde_json::Value::Object(map), serde_json::Value::Object(fields)) = (value, known) else {
+        return Ok(());
+    };
+    for (key, mut member) in std::mem::take(map) {
+        let field: String = key
+            .chars()
+            .flat_map(|c| {
+                let separator = c.is_ascii_uppercase().then_some('_');
+                separator.into_iter().chain(std::iter::once(c.to_ascii_lowercase()))
+            })
+            .collect();
+        let Some(default) = fields.get(&field) else {
+            let names: Vec<String> = fields.keys().map(|name| camel_case(name)).collect();
+            let hint = did_you_mean(&key, names.iter().map(String::as_str));
+            return Err(format!("Unknown limit '{path}{key}'{hint}"));
+        };
+        to_field_names(&mut member, default, &format!("{path}{key}."))?;
+        map.insert(field, member);
+    }
+    Ok(())
+}
+
+/// `snake_case` as `camelCase`.
+fn camel_case(name: &str) -> String {
+    let mut words = name.split('_');
+    let first = words.next().unwrap_or_default().to_string();
+    words.fold(first, |mut camel, word| {
+        let mut chars = word.chars();
+        camel.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        camel.push_str(chars.as_str());
+        camel
+    })
+}
+
+// ============================================================================
+// Execution Events
+// ============================================================================
+
+/// Event delivered to the JavaScript event callback during execution.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExecutionEvent<'a> {
+    /// A tool is about to be invoked.
+    ToolCallStart {
+        tool_name: &'a str,
+        input_preview: String,
+    },
+    /// A tool invocation has finished.
+    ToolCallEnd {
+        tool_name: &'a str,
+        duration_ms: u64,
+        success: bool,
+    },
+    /// Periodic progress report from the Rhai engine.
+    Progress { operations: u64, elapsed_ms: u64 },
+}
+
+/// Deliver an event to the callback (if any) as a JSON string.
+///
+/// Errors thrown by the callback are deliberately ignored so that a faulty
+/// listener can never abort the running script.
+fn emit_event(callback: Option<&js_sys::Function>, event: &ExecutionEvent<'_>) {
+    let Some(callback) = callback else {
+        return;
+    };
+    if let Ok(json) = serde_json::to_string(event) {
+        let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&json));
+    }
+}
+
+/// Truncate a tool input to a short preview suitable for progress UIs.
+fn input_preview(input: &str) -> String {
+    match input.char_indices().nth(EVENT_INPUT_PREVIEW_CHARS) {
+        Some((idx, _)) => format!("{}…", &input[..idx]),
+        None => input.to_string(),
+    }
+}
+
 // ============================================================================
 // WASM Orchestrator
 // ============================================================================
 
+/// A registered JavaScript tool.
+#[derive(Clone)]
+struct JsTool {
+    callback: js_sys::Function,
+    /// Pass and return JS values instead of JSON strings
+    objects: bool,
+    /// Overrides `max_tool_input_bytes` for this tool
+    max_input_bytes: Option<usize>,
+}
+
 /// Tool executor function type (JavaScript callback)
-type JsToolExecutor = Rc<RefCell<js_sys::Function>>;
+type JsToolExecutor = Rc<JsTool>;
+
+/// Filter rewriting the final output; `Err` carries why a JS filter failed
+type WasmOutputFilter = Rc<dyn Fn(String) -> Result<String, String>>;
 
 /// WASM-compatible tool orchestrator.
 ///
@@ -161,6 +700,22 @@ type JsToolExecutor = Rc<RefCell<js_sys::Function>>;
 pub struct WasmOrchestrator {
     /// JavaScript tool executors (separate from core orchestrator)
     js_executors: HashMap<String, JsToolExecutor>,
+    /// Optional listener for execution events
+    event_callback: Option<Rc<js_sys::Function>>,
+    /// Standard library function names, collected on the first `check_script`
+    std_functions: OnceCell<HashSet<String>>,
+    /// Filters applied to the final output, in order
+    output_filters: Vec<WasmOutputFilter>,
+    /// Whether the output filters also rewrite the recorded tool call outputs
+    filter_tool_call_outputs: bool,
+    /// Whether results carry the script's source
+    include_script: bool,
+    /// How a final value that isn't a string is rendered
+    output_format: OutputFormat,
+    /// Decimal places floats are written with, if not Rhai's default
+    float_precision: Option<usize>,
+    /// Named limits for `execute_with_options`, besides the presets
+    profiles: ProfileRegistry,
 }
 
 #[wasm_bindgen]
@@ -174,16 +729,208 @@ impl WasmOrchestrator {
 
         Self {
             js_executors: HashMap::new(),
+            event_callback: None,
+            std_functions: OnceCell::new(),
+            output_filters: Vec::new(),
+            filter_tool_call_outputs: false,
+            include_script: false,
+            output_format: OutputFormat::Json,
+            float_precision: None,
+            profiles: ProfileRegistry::new(),
         }
     }
 
     /// Register a tool executor function
     ///
     /// The function should accept a JSON string and return a string result.
+    /// Returns `true` if this replaced a tool already registered under `name`.
+    #[wasm_bindgen]
+    pub fn register_tool(&mut self, name: &str, callback: js_sys::Function) -> bool {
+        let tool = JsTool {
+            callback,
+            objects: false,
+            max_input_bytes: None,
+        };
+        self.js_executors
+            .insert(name.to_string(), Rc::new(tool))
+            .is_some()
+    }
+
+    /// Register a tool that takes and returns JS values.
+    ///
+    /// Unlike [`register_tool`](Self::register_tool), the callback receives
+    /// the script's argument as a plain JS value (objects, arrays, numbers,
+    /// ...) and may return any JSON-serializable value. A returned string is
+    /// passed to the script as-is; anything else is passed as its
+    /// `JSON.stringify` text. Returning `undefined`, a function or another
+    /// value JSON cannot represent fails the call with a tool error.
+    /// Returns `true` if this replaced a tool already registered under `name`.
     #[wasm_bindgen]
-    pub fn register_tool(&mut self, name: &str, callback: js_sys::Function) {
+    pub fn register_tool_object(&mut self, name: &str, callback: js_sys::Function) -> bool {
+        let tool = JsTool {
+            callback,
+            objects: true,
+            max_input_bytes: None,
+        };
         self.js_executors
-            .insert(name.to_string(), Rc::new(RefCell::new(callback)));
+            .insert(name.to_string(), Rc::new(tool))
+            .is_some()
+    }
+
+    /// Give the tool registered under `name` its own input size limit.
+    ///
+    /// Calls to it are refused when their input serializes to more than
+    /// `max_bytes` of JSON, whatever `max_tool_input_bytes` says. Returns
+    /// `false` if no tool is registered under `name`.
+    #[wasm_bindgen]
+    pub fn set_tool_max_input_bytes(&mut self, name: &str, max_bytes: usize) -> bool {
+        let Some(tool) = self.js_executors.get_mut(name) else {
+            return false;
+        };
+        Rc::make_mut(tool).max_input_bytes = Some(max_bytes);
+        true
+    }
+
+    /// Remove a registered tool.
+    ///
+    /// Later executions can no longer call it. Returns `true` if a tool was
+    /// registered under `name`.
+    #[wasm_bindgen]
+    pub fn unregister_tool(&mut self, name: &str) -> bool {
+        self.js_executors.remove(name).is_some()
+    }
+
+    /// Remove all registered tools.
+    #[wasm_bindgen]
+    pub fn clear_tools(&mut self) {
+        self.js_executors.clear();
+    }
+
+    /// Set a callback that receives execution events.
+    ///
+    /// The callback is invoked synchronously with a JSON string for each
+    /// event: `tool_call_start` (tool name and input preview), `tool_call_end`
+    /// (tool name, duration, success) and a periodic `progress` event carrying
+    /// the operation count and elapsed time. Exceptions thrown by the callback
+    /// are ignored and never abort the script.
+    #[wasm_bindgen]
+    pub fn set_event_callback(&mut self, callback: js_sys::Function) {
+        self.event_callback = Some(Rc::new(callback));
+    }
+
+    /// Remove the event callback, if one is set.
+    #[wasm_bindgen]
+    pub fn clear_event_callback(&mut self) {
+        self.event_callback = None;
+    }
+
+    /// Add a filter that rewrites the output of every execution.
+    ///
+    /// The callback receives the output string and must return a string.
+    /// Filters run in the order they were added, after the output size limit
+    /// was applied, on successful executions only. If a filter throws or
+    /// returns anything else, the execution fails rather than returning the
+    /// unfiltered output.
+    ///
+    /// ```javascript
+    /// orchestrator.add_output_filter((text) => text.replaceAll(apiKey, '<key>'));
+    /// ```
+    #[wasm_bindgen]
+    pub fn add_output_filter(&mut self, callback: js_sys::Function) {
+        self.output_filters.push(Rc::new(move |text: String| {
+            match callback.call1(&JsValue::NULL, &JsValue::from_str(&text)) {
+                Ok(value) => value
+                    .as_string()
+                    .ok_or_else(|| "output filter returned a non-string value".to_string()),
+                Err(e) => Err(e.as_string().unwrap_or_else(|| "output filter threw".to_string())),
+            }
+        }));
+    }
+
+    /// Add a built-in output filter: `"strip_ansi"` or `"collapse_whitespace"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other filter name.
+    #[wasm_bindgen]
+    pub fn add_builtin_output_filter(&mut self, name: &str) -> Result<(), JsValue> {
+        let filter: fn(String) -> String = match name {
+            "strip_ansi" => output_filters::strip_ansi,
+            "collapse_whitespace" => output_filters::collapse_whitespace,
+            other => return Err(JsValue::from_str(&format!("Unknown output filter: {other}"))),
+        };
+        self.output_filters.push(Rc::new(move |text| Ok(filter(text))));
+        Ok(())
+    }
+
+    /// Add a filter replacing every match of `pattern` with `[REDACTED]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    #[wasm_bindgen]
+    pub fn add_redact_filter(&mut self, pattern: &str) -> Result<(), JsValue> {
+        let filter = output_filters::redact_pattern(pattern)
+            .map_err(|e| JsValue::from_str(&format!("Invalid pattern: {e}")))?;
+        self.output_filters.push(Rc::new(move |text| Ok(filter(text))));
+        Ok(())
+    }
+
+    /// Also apply the output filters to each recorded tool call's output.
+    ///
+    /// Off by default. Scripts always see what the tool returned; a call
+    /// whose output a filter rejects is recorded with an empty output.
+    #[wasm_bindgen]
+    pub fn set_filter_tool_call_outputs(&mut self, enabled: bool) {
+        self.filter_tool_call_outputs = enabled;
+    }
+
+    /// Also store the script's source in each result's `script` field.
+    ///
+    /// Off by default, since the source may hold sensitive data the caller
+    /// interpolated; `script_hash` identifies the script either way.
+    #[wasm_bindgen]
+    pub fn set_include_script(&mut self, enabled: bool) {
+        self.include_script = enabled;
+    }
+
+    /// Set how a final value that isn't a string is rendered: `"json"` (the
+    /// default), `"pretty_json"` or `"debug"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other format name.
+    #[wasm_bindgen]
+    pub fn set_output_format(&mut self, format: &str) -> Result<(), JsValue> {
+        self.output_format = match format {
+            "json" => OutputFormat::Json,
+            "pretty_json" => OutputFormat::PrettyJson,
+            "debug" => OutputFormat::Debug,
+            other => return Err(JsValue::from_str(&format!("Unknown output format: {other}"))),
+        };
+        Ok(())
+    }
+
+    /// Write floats with `precision` decimal places, rounded half to even, in
+    /// the output and in `${...}` interpolation; `undefined` restores Rhai's
+    /// formatting.
+    #[wasm_bindgen]
+    pub fn set_float_precision(&mut self, precision: Option<usize>) {
+        self.float_precision = precision;
+    }
+
+    /// Name a set of limits, so `execute_with_options` can refer to it as
+    /// `{ profile: name }`. Registering a preset's name replaces the preset.
+    #[wasm_bindgen]
+    pub fn register_profile(&mut self, name: &str, limits: &ExecutionLimits) {
+        self.profiles.register_profile(name, limits.inner);
+    }
+
+    /// Remove all output filters.
+    #[wasm_bindgen]
+    pub fn clear_output_filters(&mut self) {
+        self.output_filters.clear();
     }
 
     /// Get list of registered tool names.
@@ -193,74 +940,418 @@ impl WasmOrchestrator {
         self.js_executors.keys().cloned().collect()
     }
 
+    /// Check a script for errors without running it, e.g. while it is typed.
+    ///
+    /// Compiles `script` and resolves every function it calls against the
+    /// registered tools, the script's own functions and the built-in ones.
+    /// Nothing runs and no JS callback is invoked. Returns
+    /// `{ valid, errors: [{ message, line, column }] }`: at most one syntax
+    /// error, since compilation stops there, or one error per unknown
+    /// function called.
+    ///
+    /// ```javascript
+    /// const check = orchestrator.check_script(editor.getValue());
+    /// markers = check.errors.map((e) => ({ line: e.line, column: e.column, text: e.message }));
+    /// ```
+    #[wasm_bindgen]
+    #[must_use]
+    pub fn check_script(&self, script: &str) -> JsValue {
+        let mut engine = rhai::Engine::new_raw();
+        engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_CALL_DEPTH);
+        let diagnostic = |message: String, position: rhai::Position| ScriptDiagnostic {
+            message,
+            line: position.line(),
+            column: position.position(),
+        };
+
+        let mut errors = Vec::new();
+        match engine.compile(script) {
+            Err(e) => errors.push(diagnostic(e.err_type().to_string(), e.position())),
+            Ok(ast) => {
+                let std_functions = self.std_functions.get_or_init(|| {
+                    let std_lib = StandardPackage::new().as_shared_module();
+                    module_function_names(&std_lib).collect()
+                });
+                let defined: HashSet<String> =
+                    ast.iter_functions().map(|f| f.name.to_string()).collect();
+                let extra = execution_function_names();
+                for_each_call(&ast, |name, position| {
+                    let known = self.js_executors.contains_key(&name)
+                        || is_builtin_call(&name)
+                        || std_functions.contains(&name)
+                        || defined.contains(&name)
+                        || extra.contains(&name.as_str());
+                    if !known {
+                        let tools = self.js_executors.keys().map(String::as_str);
+                        let hint = did_you_mean(&name, tools);
+                        let message = format!("unknown function '{name}'{hint}");
+                        errors.push(diagnostic(message, position));
+                    }
+                    true
+                });
+            }
+        }
+
+        let check = ScriptCheck { valid: errors.is_empty(), errors };
+        check
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .unwrap_or(JsValue::NULL)
+    }
+
     /// Execute a Rhai script with the registered tools.
     ///
-    /// Returns a `JsValue` containing the `OrchestratorResult`.
+    /// Returns a `JsValue` containing the `OrchestratorResult`. Each
+    /// execution gets a random UUID as its `execution_id` (the nil UUID in
+    /// deterministic mode).
     ///
     /// # Errors
     ///
     /// Returns `JsValue` error if serialization fails.
     #[wasm_bindgen]
-    #[allow(clippy::too_many_lines)] // Execute function is inherently complex
     pub fn execute(&self, script: &str, limits: &ExecutionLimits) -> Result<JsValue, JsValue> {
+        let execution_id = new_execution_id(limits.inner.deterministic);
+        self.execute_with_id(script, limits, &execution_id)
+    }
+
+    /// Execute a Rhai script with limits given as a plain object.
+    ///
+    /// Like [`execute`](Self::execute), with the limits read as by
+    /// [`ExecutionLimits::from_object`], so one-off calls don't need an
+    /// `ExecutionLimits` instance. The `profile` key may also name a profile
+    /// from [`register_profile`](Self::register_profile); the other keys
+    /// override its limits:
+    ///
+    /// ```javascript
+    /// const limits = { profile: "batch", maxToolCalls: 5, timeoutMs: 3000 };
+    /// const result = orchestrator.execute_with_options(script, limits);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an unknown key or profile or a value of the
+    /// wrong type, before the script runs, or if serialization fails.
+    #[wasm_bindgen]
+    pub fn execute_with_options(&self, script: &str, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: serde_json::Value = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid limits: {e}")))?;
+        let inner = limits_from_json(options, &self.profiles).map_err(|e| JsValue::from_str(&e))?;
+        self.execute(script, &ExecutionLimits { inner })
+    }
+
+    /// Execute a Rhai script, reporting `execution_id` as its id.
+    ///
+    /// Like [`execute`](Self::execute), but the caller chooses the id
+    /// recorded on the result and every tool call, e.g. to match a request id.
+    ///
+    /// # Errors
+    ///
+    /// Returns `JsValue` error if serialization fails.
+    #[wasm_bindgen]
+    pub fn execute_with_id(
+        &self,
+        script: &str,
+        limits: &ExecutionLimits,
+        execution_id: &str,
+    ) -> Result<JsValue, JsValue> {
+        match self.run(script, limits, execution_id, None) {
+            Ok(result) => result_to_js(&result),
+            Err(aborted) => Err(aborted.into_js()),
+        }
+    }
+
+    /// Execute a Rhai script that `signal` can abort, returning a `Promise`.
+    ///
+    /// The signal is checked at every Rhai operation and before every tool
+    /// call; once it is aborted no further tool is called and the script
+    /// stops. The promise resolves to the same result object as
+    /// [`execute`](Self::execute), or rejects with an `Error` named
+    /// `AbortError` that carries the `execution_id` and the `tool_calls`
+    /// made before the abort.
+    ///
+    /// The script still runs synchronously, so the signal can only change
+    /// while it runs if a tool aborts it. To keep a page responsive and be
+    /// able to stop a script from outside, run the orchestrator in a Web
+    /// Worker and terminate the worker when the caller's signal fires; see
+    /// the README for a wrapper doing that.
+    ///
+    /// ```javascript
+    /// const controller = new AbortController();
+    /// orchestrator.register_tool('fetch_page', (input) => {
+    ///   if (quotaExhausted()) controller.abort();
+    ///   return fetchPageSync(JSON.parse(input));
+    /// });
+    /// try {
+    ///   const result = await orchestrator.execute_abortable(script, limits, controller.signal);
+    /// } catch (e) {
+    ///   if (e.name === 'AbortError') console.log('aborted after', e.tool_calls.length, 'calls');
+    /// }
+    /// ```
+    #[wasm_bindgen]
+    pub fn execute_abortable(
+        &self,
+        script: &str,
+        limits: &ExecutionLimits,
+        signal: web_sys::AbortSignal,
+    ) -> js_sys::Promise {
+        let execution_id = new_execution_id(limits.inner.deterministic);
+        match self.run(script, limits, &execution_id, Some(signal)) {
+            Ok(result) => match result_to_js(&result) {
+                Ok(value) => js_sys::Promise::resolve(&value),
+                Err(e) => js_sys::Promise::reject(&e),
+            },
+            Err(aborted) => js_sys::Promise::reject(&aborted.into_js()),
+        }
+    }
+
+    /// Execute a Rhai script without freezing the page, returning a `Promise`.
+    ///
+    /// [`execute`](Self::execute) runs the whole script before the browser
+    /// can render or handle input again. This runs it one top-level
+    /// statement at a time instead, and once `ops_per_chunk` Rhai operations
+    /// have passed since the last break, gives the event loop a turn (a
+    /// zero-delay `setTimeout`) before the next statement. The promise
+    /// resolves to the same result object as `execute`.
+    ///
+    /// Rhai can't pause in the middle of a statement, so this has limits:
+    ///
+    /// - A single statement, such as a long `for` loop, runs without a
+    ///   break, however many operations it takes. Splitting the work into
+    ///   several top-level statements gives the page more turns.
+    /// - From the first top-level statement containing a `return` on, the
+    ///   rest of the script runs without a break.
+    /// - Tool callbacks are still called synchronously, within the statement
+    ///   that calls them.
+    /// - The breaks count towards `timeout_ms` and the result's
+    ///   `execution_time_ms`, which measure wall-clock time from start to
+    ///   finish.
+    ///
+    /// Variables, constants and functions carry over between statements as
+    /// usual, and `max_operations` applies to the whole script.
+    ///
+    /// ```javascript
+    /// const result = await orchestrator.execute_chunked(script, limits, 10000);
+    /// ```
+    #[wasm_bindgen]
+    pub fn execute_chunked(
+        &self,
+        script: &str,
+        limits: &ExecutionLimits,
+        ops_per_chunk: u32,
+    ) -> js_sys::Promise {
+        let execution_id = new_execution_id(limits.inner.deterministic);
+        let run = match self.prepare(script, limits, &execution_id, None) {
+            Prepared::Ready(run) => run,
+            Prepared::Done(Ok(result)) => {
+                return match result_to_js(&result) {
+                    Ok(value) => js_sys::Promise::resolve(&value),
+                    Err(e) => js_sys::Promise::reject(&e),
+                };
+            }
+            Prepared::Done(Err(aborted)) => return js_sys::Promise::reject(&aborted.into_js()),
+        };
+        wasm_bindgen_futures::future_to_promise(async move {
+            match run.evaluate_in_chunks(u64::from(ops_per_chunk)).await {
+                Ok(result) => result_to_js(&result),
+                Err(aborted) => Err(aborted.into_js()),
+            }
+        })
+    }
+}
+
+impl WasmOrchestrator {
+    /// Run a script, stopping early if `signal` is aborted.
+    fn run(
+        &self,
+        script: &str,
+        limits: &ExecutionLimits,
+        execution_id: &str,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<CoreOrchestratorResult, AbortedRun> {
+        match self.prepare(script, limits, execution_id, signal) {
+            Prepared::Ready(run) => run.evaluate(),
+            Prepared::Done(outcome) => outcome,
+        }
+    }
+
+    /// Set up an engine for `script` and compile it.
+    #[allow(clippy::too_many_lines)] // Execute function is inherently complex
+    fn prepare(
+        &self,
+        script: &str,
+        limits: &ExecutionLimits,
+        execution_id: &str,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Prepared {
         use web_time::Instant;
 
         let start_time = Instant::now();
-        let tool_calls: Rc<RefCell<Vec<CoreToolCall>>> = Rc::new(RefCell::new(Vec::new()));
+        let abort = Rc::new(AbortState { signal, aborted: Cell::new(false) });
+        let mut state = RunState {
+            execution_id: execution_id.to_string(),
+            script_hash: script_hash(script),
+            script: self.include_script.then(|| script.to_string()),
+            limits: limits.inner,
+            output_filters: self.output_filters.clone(),
+            output_format: self.output_format,
+            float_precision: self.float_precision,
+            filter_tool_call_outputs: self.filter_tool_call_outputs,
+            tool_names: self.js_executors.keys().cloned().collect(),
+            start_time,
+            compile_ms: 0,
+            abort: Rc::clone(&abort),
+            tool_calls: Rc::new(RefCell::new(Vec::new())),
+            output_bytes: Rc::new(RefCell::new(0)),
+            outputs: SharedOutputs::default(),
+            warnings: SharedWarnings::default(),
+            operations: Rc::default(),
+            operations_before: Rc::default(),
+        };
+        if abort.is_aborted() {
+            return Prepared::Done(Err(AbortedRun::new(execution_id, Vec::new())));
+        }
+
+        // Reject limits that would not let the script run
+        if let Err(e) = limits.inner.validate() {
+            let result =
+                CoreOrchestratorResult::error(OrchestratorError::from(e).to_string(), Vec::new(), 0);
+            return Prepared::Done(state.finish(result));
+        }
+
+        // Reject oversized scripts before spending time parsing them
+        if let Err(e) = check_script_size(script, &limits.inner) {
+            let result = CoreOrchestratorResult::error(e.to_string(), Vec::new(), 0);
+            return Prepared::Done(state.finish(result));
+        }
+
+        let tool_calls = Rc::clone(&state.tool_calls);
         let call_count: Rc<RefCell<usize>> = Rc::new(RefCell::new(0));
+        let output_bytes = Rc::clone(&state.output_bytes);
+        let warnings = Rc::clone(&state.warnings);
+        let operations = Rc::clone(&state.operations);
+        let watch = Rc::new(RefCell::new(ProgressWatch::new(limits.inner.no_progress_guard)));
+        let state_bytes: Rc<StateMeter> = Rc::default();
+        let dynamic_call: Rc<AtomicBool> = Rc::default();
 
         // Create a new Rhai engine with limits
         let mut engine = rhai::Engine::new();
-
-        // Apply resource limits from ExecutionLimits
-        engine.set_max_operations(limits.inner.max_operations);
-        engine.set_max_string_size(limits.inner.max_string_size);
-        engine.set_max_array_size(limits.inner.max_array_size);
-        engine.set_max_map_size(limits.inner.max_map_size);
-        engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_CALL_DEPTH);
+        configure_engine(&mut engine, &limits.inner);
+        // Before the tools, so a tool named `finish` or `fail` still wins
+        engine.register_global_module(exit_module().into());
 
         // Set up real-time timeout via on_progress callback
-        let timeout_ms = limits.inner.timeout_ms;
+        let progress_limits = limits.inner;
         let progress_start = Instant::now();
-        engine.on_progress(move |_ops| {
+        let progress_events = self.event_callback.clone();
+        let progress_abort = Rc::clone(&abort);
+        let progress_operations = Rc::clone(&operations);
+        let operations_before = Rc::clone(&state.operations_before);
+        engine.on_progress(move |ops| {
+            if progress_abort.is_aborted() {
+                return Some(rhai::Dynamic::from(Aborted));
+            }
+            // Rhai counts from zero again for each slice of a chunked execution
+            let ops = operations_before.get().saturating_add(ops);
             // Use saturating conversion - elapsed time exceeding u64::MAX is always a timeout
             let elapsed = u64::try_from(progress_start.elapsed().as_millis()).unwrap_or(u64::MAX);
-            if elapsed > timeout_ms {
-                Some(rhai::Dynamic::from("timeout"))
-            } else {
-                None
+            if ops % PROGRESS_EVENT_INTERVAL == 0 {
+                emit_event(
+                    progress_events.as_deref(),
+                    &ExecutionEvent::Progress {
+                        operations: ops,
+                        elapsed_ms: elapsed,
+                    },
+                );
             }
+            exhausted_limit(&progress_operations, ops, elapsed, &progress_limits)
         });
 
         // Register each JS tool as a Rhai function
+        let mut tools = rhai::Module::new();
         for (name, executor) in &self.js_executors {
             let exec = Rc::clone(executor);
             let calls = Rc::clone(&tool_calls);
             let count = Rc::clone(&call_count);
-            let max_calls = limits.inner.max_tool_calls;
+            let bytes = Rc::clone(&output_bytes);
+            let call_limits = limits.inner;
             let tool_name = name.clone();
+            let events = self.event_callback.clone();
+            let id = execution_id.to_string();
+            let warnings = Rc::clone(&warnings);
+            let abort = Rc::clone(&abort);
+            let operations = Rc::clone(&operations);
+            let guard = Rc::clone(&watch);
+            let watch = Rc::clone(&watch);
+            let held = Rc::clone(&state_bytes);
+            let meter = Rc::clone(&state_bytes);
+            let dynamic = Rc::clone(&dynamic_call);
 
-            engine.register_fn(name.as_str(), move |input: rhai::Dynamic| -> String {
+            let invoke = move |_: &rhai::NativeCallContext, input: rhai::Dynamic| -> String {
                 let call_start = Instant::now();
 
-                // Check call limit
-                {
-                    let mut c = count.borrow_mut();
-                    if *c >= max_calls {
-                        return format!("ERROR: Maximum tool calls ({max_calls}) exceeded");
-                    }
-                    *c += 1;
+                // The script stops at its next operation; don't start another call
+                if abort.is_aborted() {
+                    return "Tool error: execution aborted".to_string();
                 }
 
+                // Count the call against the call limit and the output budget
+                let counted = count_tool_call(&count, &bytes, &operations, &warnings, &call_limits);
+
                 // Convert Dynamic to JSON
                 let json_input = dynamic_to_json(&input);
+                if counted.is_ok() && has_non_finite(&input) {
+                    let context = format!("input to '{tool_name}'");
+                    add_warning(&warnings, ExecutionWarning::NonFiniteNumber { context });
+                }
+
+                // Don't start a call over the limits, with oversized input or
+                // that can't finish before the timeout
+                let max_input = exec.max_input_bytes.unwrap_or(call_limits.max_tool_input_bytes);
+                let refusal = counted
+                    .err()
+                    .map(|(status, output)| (output, status))
+                    .or_else(|| {
+                        tool_input_refusal(&json_input, max_input).map(|reason| {
+                            (format!("Tool error: {reason}"), ToolCallStatus::LimitExceeded)
+                        })
+                    })
+                    .or_else(|| {
+                        deadline_refusal(start_time, 0, &call_limits).map(|reason| {
+                            (format!("Tool error: {reason}"), ToolCallStatus::DeadlineExceeded)
+                        })
+                    });
+                if let Some((output, status)) = refusal {
+                    let mut call =
+                        CoreToolCall::new(tool_name.clone(), json_input, output.clone(), false, 0)
+                            .with_status(status);
+                    call.execution_id.clone_from(&id);
+                    call.refused = true;
+                    call.invoked_dynamically = dynamic.load(Ordering::Relaxed);
+                    watch.borrow_mut().observe(&call);
+                    held.retain(call_state_bytes(&call));
+                    calls.borrow_mut().push(call);
+                    return output;
+                }
                 let json_str = serde_json::to_string(&json_input).unwrap_or_default();
 
+                emit_event(
+                    events.as_deref(),
+                    &ExecutionEvent::ToolCallStart {
+                        tool_name: &tool_name,
+                        input_preview: input_preview(&json_str),
+                    },
+                );
+
                 // Call the JavaScript function
-                let callback = exec.borrow();
-                let js_input = JsValue::from_str(&json_str);
+                let js_input = if exec.objects {
+                    json_input
+                        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+                        .unwrap_or(JsValue::NULL)
+                } else {
+                    JsValue::from_str(&json_str)
+                };
 
-                let (output, success) = match callback.call1(&JsValue::NULL, &js_input) {
+                let (mut output, success) = match exec.callback.call1(&JsValue::NULL, &js_input) {
+                    Ok(result) if exec.objects => js_value_to_output(&result),
                     Ok(result) => result.as_string().map_or_else(
                         || ("Tool returned non-string result".to_string(), false),
                         |s| (s, true),
@@ -272,83 +1363,469 @@ impl WasmOrchestrator {
                         (err_msg, false)
                     }
                 };
+                let mut status = if success { ToolCallStatus::Ok } else { ToolCallStatus::Error };
+                let size = output.len();
+                if success {
+                    if let Some((error, warning)) =
+                        oversized_output(&tool_name, size, size, false, &call_limits)
+                    {
+                        add_warning(&warnings, warning);
+                        (output, status) = (error, ToolCallStatus::LimitExceeded);
+                    }
+                }
+                if let Some(error) = charge_tool_output(output.len(), &bytes, &call_limits) {
+                    (output, status) = (error, ToolCallStatus::LimitExceeded);
+                }
 
                 // Record the call (saturate to u64::MAX for extremely long-running calls)
                 {
-                    let duration_ms = u64::try_from(call_start.elapsed().as_millis()).unwrap_or(u64::MAX);
-                    let call = CoreToolCall::new(
+                    let duration_ms = if call_limits.deterministic {
+                        0
+                    } else {
+                        u64::try_from(call_start.elapsed().as_millis()).unwrap_or(u64::MAX)
+                    };
+                    emit_event(
+                        events.as_deref(),
+                        &ExecutionEvent::ToolCallEnd {
+                            tool_name: &tool_name,
+                            duration_ms,
+                            success: status.is_success(),
+                        },
+                    );
+                    let mut call = CoreToolCall::new(
                         tool_name.clone(),
                         json_input,
                         output.clone(),
                         success,
                         duration_ms,
-                    );
+                    )
+                    .with_status(status);
+                    call.execution_id.clone_from(&id);
+                    call.invoked_dynamically = dynamic.load(Ordering::Relaxed);
+                    watch.borrow_mut().observe(&call);
+                    held.retain(call_state_bytes(&call));
                     calls.borrow_mut().push(call);
                 }
 
                 output
+            };
+            set_tool_overloads(&mut tools, name, move |ctx, input| {
+                let output = invoke(ctx, input);
+                // Uncatchable, so the script stops even inside `try`
+                let token = guard.borrow().stalled().map(rhai::Dynamic::from).or_else(|| {
+                    meter.over_budget(call_limits.max_total_state_bytes).map(rhai::Dynamic::from)
+                });
+                if let Some(token) = token {
+                    let position = ctx.call_position();
+                    return Err(rhai::EvalAltResult::ErrorTerminated(token, position).into());
+                }
+                Ok(output.into())
             });
         }
+        engine.register_global_module(tools.into());
+        let tool_names: Rc<HashSet<String>> = Rc::new(self.js_executors.keys().cloned().collect());
+        engine.register_global_module(map_with_tool_module(Rc::clone(&tool_names)).into());
+        let names = self.js_executors.keys().cloned().collect();
+        engine.register_global_module(sequential_call_parallel_module(names).into());
+        let names = self.js_executors.keys().cloned().collect();
+        engine.register_global_module(sequential_spawn_tool_module(names).into());
+        engine.register_global_module(task_module().into());
+        engine.register_type_with_name::<TaskHandle>("TaskHandle");
+        engine.register_global_module(call_tool_module(tool_names, &dynamic_call).into());
+        let outputs = outputs_module(&state.outputs, &warnings, &state_bytes, limits.inner);
+        engine.register_global_module(outputs.into());
+        let kv = kv_module(&SharedKvStore::default(), &state_bytes, limits.inner);
+        engine.register_global_module(kv.into());
+        engine.register_global_module(history_module(Vec::new()).into());
+        if let Some(precision) = self.float_precision {
+            engine.register_global_module(float_format_module(precision).into());
+        }
+        if limits.inner.size_limit_behavior == SizeLimitBehavior::TruncateWithWarning {
+            engine.register_global_module(size_limit_module(&warnings, limits.inner).into());
+        }
+        let budget = budget_module(&call_count, &operations, limits.inner, start_time);
+        engine.register_global_module(budget.into());
 
         // Compile the script
-        let ast = match engine.compile(script) {
+        let compile_start = Instant::now();
+        let compiled = engine.compile(script);
+        if !limits.inner.deterministic {
+            let elapsed = compile_start.elapsed().as_millis();
+            state.compile_ms = u64::try_from(elapsed).unwrap_or(u64::MAX);
+        }
+        let ast = match compiled {
             Ok(ast) => ast,
             Err(e) => {
+                let message = compile_error_message(&e, &limits.inner);
                 let result = CoreOrchestratorResult::error(
-                    format!("Compilation error: {e}"),
+                    OrchestratorError::CompilationError(message).to_string(),
                     tool_calls.borrow().clone(),
                     u64::try_from(start_time.elapsed().as_millis()).unwrap_or(u64::MAX),
                 );
-                return serde_wasm_bindgen::to_value(&result)
-                    .map_err(|e| JsValue::from_str(&e.to_string()));
+                return Prepared::Done(state.finish(result));
             }
         };
 
-        // Execute the script
+        if let Err(e) = check_script_complexity(&ast, &limits.inner)
+            .and_then(|()| check_language_restrictions(&ast, &limits.inner))
+        {
+            let result = CoreOrchestratorResult::error(e.to_string(), Vec::new(), 0);
+            return Prepared::Done(state.finish(result));
+        }
+
+        // A script that needs tools can never succeed without tool calls
+        if limits.inner.max_tool_calls == 0 {
+            if let Some(tool) = first_tool_call(&ast, |name| self.js_executors.contains_key(name)) {
+                let error = OrchestratorError::from(LimitsError::ToolCallsDisabled { tool });
+                let result = CoreOrchestratorResult::error(error.to_string(), Vec::new(), 0);
+                return Prepared::Done(state.finish(result));
+            }
+        }
+
+        Prepared::Ready(ReadyRun { engine, ast, state })
+    }
+}
+
+/// Run every filter over `text`, in order.
+fn apply_filters(filters: &[WasmOutputFilter], text: String) -> Result<String, String> {
+    filters.iter().try_fold(text, |text, filter| filter(text))
+}
+
+/// An execution ready to evaluate its script, or its outcome if it ended
+/// before the script could run.
+#[allow(clippy::large_enum_variant)] // One per execution, never stored
+enum Prepared {
+    Ready(ReadyRun),
+    Done(Result<CoreOrchestratorResult, AbortedRun>),
+}
+
+/// A compiled script with the engine to run it.
+struct ReadyRun {
+    engine: rhai::Engine,
+    ast: rhai::AST,
+    state: RunState,
+}
+
+impl ReadyRun {
+    /// Evaluate the script in one go.
+    fn evaluate(self) -> Result<CoreOrchestratorResult, AbortedRun> {
+        let mut scope = rhai::Scope::new();
+        let eval_result = self.engine.eval_ast_with_scope::<rhai::Dynamic>(&mut scope, &self.ast);
+        self.state.conclude(eval_result)
+    }
+
+    /// Evaluate the script one top-level statement at a time, letting the
+    /// event loop run whenever `ops_per_chunk` operations have passed since
+    /// it last did.
+    ///
+    /// The statements share one scope and the script's functions, so they
+    /// behave as in [`evaluate`](Self::evaluate). From the first statement
+    /// that may `return` on, the rest runs as one slice, so the `return`
+    /// still ends the script.
+    async fn evaluate_in_chunks(
+        self,
+        ops_per_chunk: u64,
+    ) -> Result<CoreOrchestratorResult, AbortedRun> {
+        let Self { engine, ast, state } = self;
+        let statements = ast.statements();
+        let split = statements.iter().position(may_return).unwrap_or(statements.len());
+        let mut slices: Vec<Vec<rhai::Stmt>> =
+            statements[..split].iter().map(|stmt| vec![stmt.clone()]).collect();
+        if split < statements.len() {
+            slices.push(statements[split..].to_vec());
+        }
+
         let mut scope = rhai::Scope::new();
-        let eval_result = engine.eval_ast_with_scope::<rhai::Dynamic>(&mut scope, &ast);
+        let mut eval_result = Ok(rhai::Dynamic::UNIT);
+        let mut yielded_at = 0;
+        for slice in slices {
+            let slice = rhai::AST::new(slice, ast.shared_lib().clone());
+            eval_result = engine.eval_ast_with_scope::<rhai::Dynamic>(&mut scope, &slice);
+            let operations = state.operations.script();
+            state.operations_before.set(operations);
+            if eval_result.is_err() {
+                break;
+            }
+            if operations - yielded_at >= ops_per_chunk {
+                yield_to_event_loop().await;
+                yielded_at = operations;
+            }
+        }
+        state.conclude(eval_result)
+    }
+}
 
-        let execution_time_ms = u64::try_from(start_time.elapsed().as_millis()).unwrap_or(u64::MAX);
-        let calls = tool_calls.borrow().clone();
+/// Whether `stmt` contains a `return`, which must end the whole script
+/// rather than just its slice.
+fn may_return(stmt: &rhai::Stmt) -> bool {
+    let mut found = false;
+    stmt.walk(&mut Vec::new(), &mut |path: &[rhai::ASTNode]| {
+        found = matches!(path.last(), Some(rhai::ASTNode::Stmt(rhai::Stmt::Return(..))));
+        !found
+    });
+    found
+}
+
+/// Let the JavaScript event loop run, e.g. to render a frame or handle
+/// input, through a zero-delay `setTimeout`.
+async fn yield_to_event_loop() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let set_timeout = js_sys::Reflect::get(&js_sys::global(), &"setTimeout".into())
+            .ok()
+            .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+        // Without timers, at least let pending promise callbacks run
+        let _ = match set_timeout {
+            Some(set_timeout) => set_timeout.call2(&JsValue::NULL, &resolve, &0.into()),
+            None => resolve.call0(&JsValue::NULL),
+        };
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
 
+/// What an execution needs to build its result once the script has stopped.
+struct RunState {
+    execution_id: String,
+    script_hash: String,
+    /// The script's source, if results should carry it
+    script: Option<String>,
+    limits: CoreExecutionLimits,
+    output_filters: Vec<WasmOutputFilter>,
+    output_format: OutputFormat,
+    float_precision: Option<usize>,
+    filter_tool_call_outputs: bool,
+    /// Registered tools, to suggest one for an unknown function
+    tool_names: Vec<String>,
+    start_time: web_time::Instant,
+    compile_ms: u64,
+    abort: Rc<AbortState>,
+    tool_calls: Rc<RefCell<Vec<CoreToolCall>>>,
+    output_bytes: Rc<RefCell<usize>>,
+    outputs: SharedOutputs,
+    warnings: SharedWarnings,
+    operations: Rc<OperationMeter>,
+    /// Operations used by the earlier slices of a chunked execution
+    operations_before: Rc<Cell<u64>>,
+}
+
+impl RunState {
+    /// Fill in what every result carries, whether or not the script ran.
+    fn finish(
+        &self,
+        mut result: CoreOrchestratorResult,
+    ) -> Result<CoreOrchestratorResult, AbortedRun> {
+        result.execution_id.clone_from(&self.execution_id);
+        result.script_hash.clone_from(&self.script_hash);
+        result.script.clone_from(&self.script);
+        if self.filter_tool_call_outputs {
+            for call in &mut result.tool_calls {
+                let output = std::mem::take(&mut call.output);
+                call.output = apply_filters(&self.output_filters, output).unwrap_or_default();
+            }
+        }
+        if !self.output_filters.is_empty() {
+            let failure = RefCell::new(None);
+            let filter = |text| {
+                apply_filters(&self.output_filters, text).unwrap_or_else(|e| {
+                    failure.borrow_mut().get_or_insert(e);
+                    String::new()
+                })
+            };
+            if let Some(json) = &mut result.structured_output {
+                filter_strings(json, &filter);
+            }
+            result.outputs.values_mut().for_each(|json| filter_strings(json, &filter));
+            // A filter that throws fails the execution, as it does on the output
+            if let Some(e) = failure.into_inner() {
+                result.success = false;
+                result.error = Some(format!("Output filter failed: {e}"));
+                result.output.clear();
+                result.structured_output = None;
+                result.outputs.clear();
+            }
+        }
+        let total_ms = result.execution_time_ms;
+        result.timing = ExecutionTiming::new(total_ms, self.compile_ms, &result.tool_calls);
+        Ok(result)
+    }
+
+    /// Build the result of a script that stopped with `eval_result`.
+    fn conclude(
+        &self,
+        eval_result: Result<rhai::Dynamic, Box<rhai::EvalAltResult>>,
+    ) -> Result<CoreOrchestratorResult, AbortedRun> {
+        let limits = &self.limits;
+        let warnings = &self.warnings;
+        let elapsed_ms = || {
+            if limits.deterministic {
+                0
+            } else {
+                u64::try_from(self.start_time.elapsed().as_millis()).unwrap_or(u64::MAX)
+            }
+        };
+        let execution_time_ms = elapsed_ms();
+        let calls = self.tool_calls.borrow().clone();
+        let total_tool_output_bytes = *self.output_bytes.borrow();
+
+        // Also when the script ended right after a refused call
+        if self.abort.aborted.get() {
+            return Err(AbortedRun::new(&self.execution_id, calls));
+        }
+
+        let eval_result = match eval_result {
+            Err(e) => match early_exit(&e) {
+                Some(EarlyExit::Finish(value)) => Ok(value),
+                Some(EarlyExit::Fail(message)) => {
+                    let mut result =
+                        CoreOrchestratorResult::error(message, calls, execution_time_ms);
+                    result.total_tool_output_bytes = total_tool_output_bytes;
+                    result.outputs = read_outputs(&self.outputs);
+                    result.warnings = warnings.borrow().clone();
+                    result.operations = self.operations.total();
+                    return self.finish(result);
+                }
+                None => Err(e),
+            },
+            ok => ok,
+        };
         match eval_result {
             Ok(result) => {
-                let output = if result.is_string() {
-                    result.into_string().unwrap_or_default()
-                } else if result.is_unit() {
-                    String::new()
-                } else {
-                    format!("{result:?}")
+                if has_non_finite(&result) {
+                    let context = "the script output".to_string();
+                    add_warning(warnings, ExecutionWarning::NonFiniteNumber { context });
+                }
+                let format = self.output_format;
+                let (output, structured_output) =
+                    script_output(&result, format, self.float_precision);
+                let output_size = output.len();
+                let timeout = std::time::Duration::from_millis(limits.timeout_ms);
+                let deadline = self.start_time.checked_add(timeout);
+                let fitted = fit_output(output, &structured_output, format, limits, deadline);
+                // Fitting the output to its limit counts as execution time
+                let execution_time_ms = elapsed_ms();
+                let mut result = match fitted {
+                    Ok((output, truncated)) => {
+                        if let Some(warning) = output_size_warning(output_size, limits) {
+                            add_warning(warnings, warning);
+                        }
+                        match apply_filters(&self.output_filters, output) {
+                            Ok(output) => {
+                                let mut result = CoreOrchestratorResult::success(
+                                    output,
+                                    calls,
+                                    execution_time_ms,
+                                );
+                                result.structured_output =
+                                    (!truncated).then_some(structured_output);
+                                result
+                            }
+                            Err(e) => {
+                                let message = format!("Output filter failed: {e}");
+                                CoreOrchestratorResult::error(message, calls, execution_time_ms)
+                            }
+                        }
+                    }
+                    Err(e) => CoreOrchestratorResult::error(e.to_string(), calls, execution_time_ms),
                 };
-
-                let result = CoreOrchestratorResult::success(output, calls, execution_time_ms);
-                serde_wasm_bindgen::to_value(&result)
-                    .map_err(|e| JsValue::from_str(&e.to_string()))
+                result.total_tool_output_bytes = total_tool_output_bytes;
+                result.outputs = read_outputs(&self.outputs);
+                result.warnings = warnings.borrow().clone();
+                result.operations = self.operations.total();
+                self.finish(result)
             }
             Err(e) => {
-                let error_msg = match *e {
-                    rhai::EvalAltResult::ErrorTooManyOperations(_) => {
-                        format!(
-                            "Script exceeded maximum operations ({})",
-                            limits.inner.max_operations
-                        )
-                    }
-                    rhai::EvalAltResult::ErrorTerminated(_, _) => {
-                        format!(
-                            "Script execution timed out after {}ms",
-                            limits.inner.timeout_ms
-                        )
+                if let rhai::EvalAltResult::ErrorTerminated(token, _) = &*e {
+                    if token.is::<Aborted>() {
+                        return Err(AbortedRun::new(&self.execution_id, calls));
                     }
-                    _ => format!("Execution error: {e}"),
-                };
+                }
+                let tools = self.tool_names.iter().map(String::as_str);
+                let error_msg = eval_error(&e, limits, tools).to_string();
 
-                let result = CoreOrchestratorResult::error(error_msg, calls, execution_time_ms);
-                serde_wasm_bindgen::to_value(&result)
-                    .map_err(|e| JsValue::from_str(&e.to_string()))
+                let mut result = CoreOrchestratorResult::error(error_msg, calls, execution_time_ms);
+                result.total_tool_output_bytes = total_tool_output_bytes;
+                result.warnings = warnings.borrow().clone();
+                result.operations = self.operations.total();
+                self.finish(result)
             }
         }
     }
 }
+/// Termination token for a script stopped by its abort signal
+#[derive(Clone)]
+struct Aborted;
+
+/// The abort signal of one execution.
+struct AbortState {
+    signal: Option<web_sys::AbortSignal>,
+    /// Set once the signal has been seen aborted
+    aborted: Cell<bool>,
+}
+
+impl AbortState {
+    fn is_aborted(&self) -> bool {
+        if !self.aborted.get() && self.signal.as_ref().is_some_and(web_sys::AbortSignal::aborted) {
+            self.aborted.set(true);
+        }
+        self.aborted.get()
+    }
+}
+
+/// What is known about an execution that was aborted.
+struct AbortedRun {
+    execution_id: String,
+    tool_calls: Vec<CoreToolCall>,
+}
+
+impl AbortedRun {
+    fn new(execution_id: &str, tool_calls: Vec<CoreToolCall>) -> Self {
+        Self { execution_id: execution_id.to_string(), tool_calls }
+    }
+
+    /// An `Error` named `AbortError`, with `execution_id` and `tool_calls` set.
+    fn into_js(self) -> JsValue {
+        let error = js_sys::Error::new("The execution was aborted");
+        error.set_name("AbortError");
+        let calls = self
+            .tool_calls
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .unwrap_or(JsValue::NULL);
+        // Setting properties on a fresh Error can't fail
+        let _ = js_sys::Reflect::set(&error, &"execution_id".into(), &self.execution_id.into());
+        let _ = js_sys::Reflect::set(&error, &"tool_calls".into(), &calls);
+        error.into()
+    }
+}
+
+/// Convert the return value of an object tool to the script's string.
+///
+/// Strings are used as-is and other values are JSON-stringified; values
+/// JSON cannot represent are reported as a failed call.
+fn js_value_to_output(value: &JsValue) -> (String, bool) {
+    if let Some(s) = value.as_string() {
+        return (s, true);
+    }
+    match js_sys::JSON::stringify(value).ok().and_then(|s| s.as_string()) {
+        Some(json) => (json, true),
+        None => ("Tool error: returned a value that cannot be converted to JSON".to_string(), false),
+    }
+}
+
+/// What [`WasmOrchestrator::check_script`] returns.
+#[derive(Serialize)]
+struct ScriptCheck {
+    valid: bool,
+    errors: Vec<ScriptDiagnostic>,
+}
+
+/// Convert a result to a plain JS object.
+///
+/// Uses the JSON-compatible serializer so JSON objects (tool inputs and
+/// `structured_output`) become plain objects rather than JS `Map`s.
+fn result_to_js(result: &CoreOrchestratorResult) -> Result<JsValue, JsValue> {
+    result
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
 
 impl Default for WasmOrchestrator {
     fn default() -> Self {
@@ -378,6 +1855,51 @@ mod tests {
         assert_eq!(limits.max_tool_calls(), 10);
     }
 
+    #[test]
+    fn test_limits_from_json_applies_overrides_to_profile() {
+        let mut profiles = ProfileRegistry::new();
+        let batch = CoreExecutionLimits::extended()
+            .with_language_restrictions(LanguageRestrictions::new().with_eval(false));
+        profiles.register_profile("batch", batch);
+
+        let options = serde_json::json!({
+            "profile": "batch",
+            "maxToolCalls": 500,
+            "language": {"allowClosures": false},
+        });
+        let limits = limits_from_json(options, &profiles).unwrap();
+        assert_eq!(limits.max_tool_calls, 500);
+        assert_eq!(limits.timeout_ms, CoreExecutionLimits::extended().timeout_ms);
+        // Nested objects are merged, not replaced
+        assert!(!limits.language.allow_eval);
+        assert!(!limits.language.allow_closures);
+
+        let err = limits_from_json(serde_json::json!({"profile": 1}), &profiles).unwrap_err();
+        assert_eq!(err, "Invalid limits: profile must be a string");
+    }
+
+    #[test]
+    fn test_input_preview_truncates_on_char_boundary() {
+        let long = "é".repeat(EVENT_INPUT_PREVIEW_CHARS + 10);
+        let preview = input_preview(&long);
+        assert_eq!(preview.chars().count(), EVENT_INPUT_PREVIEW_CHARS + 1);
+        assert!(preview.ends_with('…'));
+        assert_eq!(input_preview("short"), "short");
+    }
+
+    #[test]
+    fn test_execution_event_serialization() {
+        let event = ExecutionEvent::ToolCallEnd {
+            tool_name: "search",
+            duration_ms: 12,
+            success: true,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "tool_call_end");
+        assert_eq!(json["tool_name"], "search");
+        assert_eq!(json["duration_ms"], 12);
+    }
+
     #[test]
     fn test_wasm_orchestrator_creation() {
         let orchestrator = WasmOrchestrator::new();
@@ -0,0 +1,458 @@
+//! Integration tests for the `tool-orchestrator-cli` binary.
+//!
+//! Run with: `cargo test --features cli --test cli`
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run the CLI with `script` piped in as the script file (`-`).
+fn run_cli(args: &[&str], script: &str) -> Output {
+    run_cli_with_env(args, script, &[])
+}
+
+/// Like [`run_cli`], with extra variables in the CLI's own environment.
+fn run_cli_with_env(args: &[&str], script: &str, env: &[(&str, &str)]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tool-orchestrator-cli"))
+        .args(args)
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start CLI");
+    // The CLI may exit on a usage error before reading its stdin
+    let _ = child.stdin.take().unwrap().write_all(script.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn json_result(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).expect("stdout is a JSON result")
+}
+
+#[test]
+fn test_run_plain_script_as_json() {
+    let output = run_cli(&["run", "-", "--format", "json"], "let x = 20; x + 22");
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(result["success"], true);
+    assert_eq!(result["output"], "42");
+}
+
+#[test]
+fn test_run_text_summary() {
+    let output = run_cli(&["run", "-"], r#""hello""#);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("status: success"));
+    assert!(stdout.contains("output: hello"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_shell_tool_receives_json_on_stdin() {
+    let output = run_cli(
+        &["run", "-", "--tool", "upper=tr a-z A-Z", "--format", "json"],
+        r#"upper("shout")"#,
+    );
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(result["output"], "\"SHOUT\"");
+    assert_eq!(result["tool_calls"][0]["tool_name"], "upper");
+    assert_eq!(result["tool_calls"][0]["input"], "shout");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_binary_shell_tool_output_is_passed_through() {
+    let output = run_cli(
+        &["run", "-", "--tool", r"raw=printf '\377\000\376'", "--format", "json"],
+        "let b = raw(); [b.len(), b[0], b[1], b[2]]",
+    );
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let result = json_result(&output);
+    assert_eq!(result["output"], "[3,255,0,254]");
+    assert_eq!(result["tool_calls"][0]["output"], "/wD+");
+    assert_eq!(result["tool_calls"][0]["binary_len"], 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failing_shell_tool_is_a_tool_error() {
+    let output = run_cli(
+        &["run", "-", "--tool", "broken=echo nope >&2; exit 3", "--format", "json"],
+        "broken(1)",
+    );
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(result["output"], "Tool error: nope");
+    assert_eq!(result["tool_calls"][0]["success"], false);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hanging_shell_tool_is_killed() {
+    let start = std::time::Instant::now();
+    let output = run_cli(
+        &["run", "-", "--tool", "hang=sleep 60", "--tool-timeout-ms", "200", "--format", "json"],
+        r#"hang(1) + " / " + hang(2)"#,
+    );
+    assert!(output.status.success());
+    assert!(start.elapsed() < std::time::Duration::from_secs(30));
+
+    let result = json_result(&output);
+    let error = "Tool error: `sleep 60` timed out after 200ms";
+    assert_eq!(result["output"], format!("{error} / {error}"));
+    assert_eq!(result["tool_calls"][1]["success"], false);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_backgrounded_child_does_not_hold_up_the_tool() {
+    let start = std::time::Instant::now();
+    let tool = "x=sleep 20 >/dev/null & echo hi";
+    let args = ["run", "-", "--tool", tool, "--tool-timeout-ms", "1000", "--format", "json"];
+    let output = run_cli(&args, "x(1)");
+    assert!(output.status.success());
+    // The child still holds stderr open, but the tool returns once the shell exits
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    assert_eq!(json_result(&output)["output"], "hi");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_oversized_shell_tool_output_is_rejected() {
+    let args = ["run", "-", "--tool", "flood=yes", "--tool-max-output-bytes", "1000"];
+    let output = run_cli(&[&args[..], &["--format", "json"]].concat(), "flood(1)");
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(result["output"], "Tool error: `yes` printed more than 1000 bytes");
+
+    // Output right at the cap is fine
+    let args = ["run", "-", "--tool", "ok=printf 12345", "--tool-max-output-bytes", "5"];
+    let output = run_cli(&args, "ok(1)");
+    assert!(String::from_utf8(output.stdout).unwrap().contains("output: 12345"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_structured_tool_output_reports_exit_code_and_stderr() {
+    let args = ["run", "-", "--tool", "probe=echo out; echo err >&2; exit 3"];
+    let output = run_cli(
+        &[&args[..], &["--tool-structured-output", "--format", "json"]].concat(),
+        "let r = parse_json(probe(1)); if r.exit_code == 3 { r } else { () }",
+    );
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(
+        result["structured_output"],
+        serde_json::json!({"exit_code": 3, "stdout": "out", "stderr": "err", "timed_out": false})
+    );
+    assert_eq!(result["tool_calls"][0]["success"], true);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_structured_tool_output_keeps_output_of_timed_out_command() {
+    let args = ["run", "-", "--tool", "hang=echo partial; sleep 60", "--tool-timeout-ms", "500"];
+    let output = run_cli(
+        &[&args[..], &["--tool-structured-output", "--format", "json"]].concat(),
+        "parse_json(hang(1))",
+    );
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(
+        result["structured_output"],
+        serde_json::json!({"exit_code": null, "stdout": "partial", "stderr": "", "timed_out": true})
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_structured_tool_output_holds_stderr_to_the_cap() {
+    let args = ["run", "-", "--tool", "noisy=echo 1234567 >&2", "--tool-max-output-bytes", "5"];
+    let output = run_cli(
+        &[&args[..], &["--tool-structured-output", "--format", "json"]].concat(),
+        "noisy(1)",
+    );
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    let error = "Tool error: `echo 1234567 >&2` printed more than 5 bytes to stderr";
+    assert_eq!(result["output"], error);
+}
+
+/// Environment variable names seen by a tool command running `env`.
+#[cfg(unix)]
+fn tool_env_names(extra_args: &[&str], cli_env: &[(&str, &str)]) -> Vec<String> {
+    let mut args = vec!["run", "-", "--tool", "show_env=env", "--format", "json"];
+    args.extend_from_slice(extra_args);
+    let output = run_cli_with_env(&args, "show_env()", cli_env);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let result = json_result(&output);
+    let listing = result["output"].as_str().unwrap();
+    listing.lines().filter_map(|line| line.split_once('=')).map(|(n, _)| n.to_string()).collect()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shell_tools_get_only_path_and_tool_env() {
+    let cli_env = [("AWS_SECRET_ACCESS_KEY", "s3cret"), ("API_TOKEN", "t0ken")];
+    let names = tool_env_names(&["--tool-env", "GREETING=hi"], &cli_env);
+    assert!(names.contains(&"PATH".to_string()), "{names:?}");
+    assert!(names.contains(&"GREETING".to_string()), "{names:?}");
+    assert!(!names.iter().any(|n| n == "AWS_SECRET_ACCESS_KEY" || n == "API_TOKEN"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_inherited_env_respects_denylist() {
+    let cli_env = [("AWS_SECRET_ACCESS_KEY", "s3cret"), ("API_TOKEN", "t0ken"), ("KEEP", "1")];
+    let args = ["--inherit-env", "--deny-env", "AWS_*", "--deny-env", "API_TOKEN"];
+    let names = tool_env_names(&args, &cli_env);
+    assert!(names.contains(&"KEEP".to_string()), "{names:?}");
+    assert!(!names.iter().any(|n| n.starts_with("AWS_") || n == "API_TOKEN"), "{names:?}");
+}
+
+#[test]
+fn test_denied_tool_env_is_rejected() {
+    let args = ["run", "-", "--tool-env", "AWS_KEY=x", "--deny-env", "AWS_*"];
+    let output = run_cli(&args, "1");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("denied by --deny-env"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shell_tools_run_in_tool_cwd() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).canonicalize().unwrap();
+    let dir = dir.to_str().unwrap();
+    let args = ["run", "-", "--tool", "where=pwd -P", "--tool-cwd", dir, "--format", "json"];
+    let output = run_cli(&args, "where()");
+    assert!(output.status.success());
+    assert_eq!(json_result(&output)["output"], dir);
+
+    let output = run_cli(&["run", "-", "--tool-cwd", "/nonexistent/dir"], "1");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("is not a directory"));
+}
+
+#[test]
+fn test_dry_run_with_namespaced_stub() {
+    let output = run_cli(
+        &["dry-run", "-", "--stub", "fs::read=contents", "--format", "json"],
+        r#"fs::read("a.txt")"#,
+    );
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(result["output"], "contents");
+    assert_eq!(result["tool_calls"][0]["tool_name"], "fs::read");
+}
+
+#[test]
+fn test_vars_are_bound_in_script() {
+    let output = run_cli(
+        &["run", "-", "--vars", r#"{"x": 1, "name": "Ada"}"#, "--format", "json"],
+        "`${name}:${x + 1}`",
+    );
+    assert!(output.status.success());
+    assert_eq!(json_result(&output)["output"], "Ada:2");
+
+    // Values reach the script as they are, dry runs included; integers past
+    // i64 arrive as strings, with or without floats
+    let vars = r#"{"s": "a \"q\"\n", "m": {"k v": [true, null]}, "big": 18446744073709551615}"#;
+    let output = run_cli(&["dry-run", "-", "--vars", vars, "--format", "json"], "[s, m, big]");
+    let result = json_result(&output);
+    assert_eq!(result["dry_run"], true);
+    let expected = serde_json::json!(["a \"q\"\n", {"k v": [true, null]}, "18446744073709551615"]);
+    assert_eq!(result["structured_output"], expected);
+
+    // The script's own lines keep their numbers
+    let output = run_cli(&["run", "-", "--vars", vars, "--format", "json"], "s\nthrow m");
+    let error = json_result(&output)["error"].as_str().unwrap().to_string();
+    assert!(error.contains("line 2"), "{error}");
+}
+
+#[test]
+fn test_limits_preset_is_applied() {
+    let output = run_cli(
+        &["run", "-", "--limits", "quick", "--format", "json"],
+        "let i = 0; loop { i += 1; }",
+    );
+    assert!(!output.status.success());
+
+    let error = json_result(&output)["error"].as_str().unwrap().to_string();
+    assert!(error.contains("10000"), "unexpected error: {error}");
+}
+
+#[test]
+fn test_compile_error_exits_non_zero() {
+    let output = run_cli(&["run", "-", "--format", "json"], "let x = ;");
+    assert!(!output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(result["success"], false);
+    assert!(result["error"].as_str().unwrap().contains("compilation failed"));
+}
+
+#[test]
+fn test_runtime_error_exits_non_zero() {
+    let output = run_cli(&["run", "-"], r#"throw "boom""#);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("boom"));
+}
+
+#[test]
+fn test_dry_run_answers_from_stubs() {
+    let output = run_cli(
+        &[
+            "dry-run",
+            "-",
+            "--tool",
+            "delete_user=exit 1",
+            "--stub",
+            "delete_user=deleted",
+            "--format",
+            "json",
+        ],
+        "delete_user(7)",
+    );
+    assert!(output.status.success());
+
+    let result = json_result(&output);
+    assert_eq!(result["dry_run"], true);
+    assert_eq!(result["output"], "deleted");
+    assert_eq!(result["tool_calls"][0]["input"], 7);
+}
+
+#[test]
+fn test_invalid_tool_spec_is_rejected() {
+    let output = run_cli(&["run", "-", "--tool", "no-equals-sign"], "1");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("NAME=VALUE"));
+}
+
+#[test]
+fn test_invalid_vars_are_rejected() {
+    let output = run_cli(&["run", "-", "--vars", "[1, 2]"], "1");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("JSON object"));
+}
+
+#[test]
+fn test_unknown_limits_preset_is_rejected() {
+    let output = run_cli(&["run", "-", "--limits", "huge"], "1");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_missing_script_file_exits_non_zero() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tool-orchestrator-cli"))
+        .args(["run", "/nonexistent/script.rhai"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("failed to read"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_test_tool_runs_before_the_script() {
+    let output = run_cli(
+        &["run", "-", "--tool", "upper=tr a-z A-Z", "--test-tool", r#"upper="ok""#],
+        r#"upper("shout")"#,
+    );
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(r#"tool upper passed its test: "OK""#), "{stderr}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#"output: "SHOUT""#), "{stdout}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failed_tool_test_stops_the_run() {
+    let output = run_cli(
+        &[
+            "run",
+            "-",
+            "--tool",
+            "lookup=no-such-binary-for-tool-orchestrator",
+            "--test-tool",
+            "lookup=1",
+            "--format",
+            "json",
+        ],
+        "lookup(1)",
+    );
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("error: tool lookup failed its test: "), "{stderr}");
+
+    let output = run_cli(&["run", "-", "--test-tool", "lookup=1"], "1");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--test-tool lookup: no --tool lookup=COMMAND"), "{stderr}");
+}
+
+#[test]
+fn test_tool_commands_are_checked_up_front() {
+    let output = run_cli(&["run", "-", "--tool", "empty=  "], "1");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("tool `empty` has an empty command"), "{stderr}");
+
+    let output = run_cli(&["run", "-", "--tool", "greet=echo hi $input"], "1");
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("warning: tool greet uses $input"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_validate_valid_script_runs_nothing() {
+    let marker = std::env::temp_dir().join(format!("validate-marker-{}", std::process::id()));
+    let tool = format!("upper=touch {}", marker.display());
+    let output = run_cli(&["validate", "-", "--tool", &tool], r#"upper("x")"#);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let validation = json_result(&output);
+    assert_eq!(validation["valid"], true);
+    assert_eq!(validation["errors"], serde_json::json!([]));
+    assert_eq!(validation["referenced_tools"], serde_json::json!(["upper"]));
+    assert_eq!(validation["unknown_tools"], serde_json::json!([]));
+    assert!(!marker.exists(), "validate ran the tool command");
+}
+
+#[test]
+fn test_validate_reports_syntax_errors() {
+    let output = run_cli(&["validate", "-"], "let x = ;");
+    assert!(!output.status.success());
+
+    let validation = json_result(&output);
+    assert_eq!(validation["valid"], false);
+    assert_eq!(validation["errors"][0]["line"], 1);
+    assert!(validation["errors"][0]["message"].is_string());
+}
+
+#[test]
+fn test_validate_reports_unknown_tools() {
+    let output = run_cli(&["validate", "-", "--tool", "upper=tr a-z A-Z"], r#"uper("x")"#);
+    assert!(!output.status.success());
+
+    let validation = json_result(&output);
+    assert_eq!(validation["valid"], false);
+    assert_eq!(validation["unknown_tools"], serde_json::json!(["uper"]));
+    assert_eq!(validation["suggestions"]["uper"][0], "upper");
+}
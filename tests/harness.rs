@@ -0,0 +1,121 @@
+//! Scenario tests modelled on the examples, written with the public test
+//! harness in `tool_orchestrator::testing`.
+//!
+//! Run with: `cargo test --features testing --test harness`
+
+use serde_json::json;
+use tool_orchestrator::assert_calls;
+use tool_orchestrator::testing::{ExpectedCall, MockTool, OrchestratorTestBuilder};
+use tool_orchestrator::{ExecutionLimits, ExecutionOptions, ToolCallStatus};
+
+/// `examples/expense_aggregation.rs`: loop over employees, return a summary.
+#[test]
+fn test_expense_aggregation_script() {
+    let names = MockTool::sequence(["Alice", "Bob", "Carol"]);
+    let expenses = MockTool::sequence([
+        r#"[{"amount": 150}, {"amount": 300}]"#,
+        r#"[{"amount": 1250}, {"amount": 450}]"#,
+        "[]",
+    ]);
+    let result = OrchestratorTestBuilder::new()
+        .with_tool("get_employee_name", &names)
+        .with_tool("get_expenses", &expenses)
+        .run_ok(
+            r#"
+            let total = 0;
+            let high = [];
+            for id in [1, 2, 3] {
+                let name = get_employee_name(id);
+                let spent = 0;
+                for item in parse_json(get_expenses(id)) { spent += item.amount; }
+                total += spent;
+                if spent > 1000 { high.push(name); }
+            }
+            `total ${total}, high spenders: ${high}`
+            "#,
+        );
+
+    assert_eq!(result.output, r#"total 2150, high spenders: ["Bob"]"#);
+    assert_calls!(result.tool_calls, [
+        ExpectedCall::new("get_employee_name").with_input(json!(1)),
+        ExpectedCall::new("get_expenses").with_input(json!(1)),
+        ExpectedCall::new("get_employee_name").with_input(json!(2)),
+        ExpectedCall::new("get_expenses").with_input(json!(2)),
+        ExpectedCall::new("get_employee_name").with_input(json!(3)),
+        ExpectedCall::new("get_expenses").with_input(json!(3)),
+    ]);
+    assert_eq!(expenses.calls(), [json!(1), json!(2), json!(3)]);
+}
+
+/// `examples/multi_api.rs`: chain APIs and carry on when one of them fails.
+#[test]
+fn test_multi_api_script_survives_a_failing_tool() {
+    let notifications = MockTool::returning("sent");
+    let result = OrchestratorTestBuilder::new()
+        .with_tool("get_user_preferences", &MockTool::returning(r#"{"city": "Oslo"}"#))
+        .with_tool("get_weather", &MockTool::failing("weather service unavailable"))
+        .with_tool("send_notification", &notifications)
+        .run_ok(
+            r#"
+            let prefs = parse_json(get_user_preferences("u1"));
+            let weather = get_weather(prefs.city);
+            let message = if weather.starts_with("Tool error") {
+                `No forecast for ${prefs.city}`
+            } else {
+                `${prefs.city}: ${weather}`
+            };
+            send_notification(#{ user: "u1", message: message })
+            "#,
+        );
+
+    assert_eq!(result.output, "sent");
+    assert_calls!(result.tool_calls, [
+        "get_user_preferences",
+        ExpectedCall::new("get_weather").with_input(json!("Oslo")).failed(),
+        ExpectedCall::new("send_notification")
+            .with_input_containing(json!({"message": "No forecast for Oslo"}))
+            .succeeded(),
+    ]);
+    assert_eq!(notifications.call_count(), 1);
+}
+
+/// `examples/file_operations.rs`: real executors next to mocks, under limits.
+#[test]
+fn test_file_operations_script_within_limits() {
+    let reads = MockTool::sequence(["fn main() {}", "# Notes\nTODO: tests"]);
+    let builder = OrchestratorTestBuilder::new()
+        .with_executor("list_directory", |_| Ok("main.rs,notes.md".to_string()))
+        .with_tool("read_file", &reads)
+        .with_limits(ExecutionLimits::default().with_max_tool_calls(3))
+        .with_options(ExecutionOptions::new().with_execution_id("files-1"));
+
+    let result = builder.run_ok(
+        r#"
+        let todos = [];
+        for file in list_directory(".").split(",") {
+            if read_file(file).contains("TODO") { todos.push(file); }
+        }
+        todos
+        "#,
+    );
+
+    assert_eq!(result.execution_id, "files-1");
+    assert_eq!(result.structured_output, Some(json!(["notes.md"])));
+    assert_calls!(result.tool_calls, [
+        ExpectedCall::new("list_directory").with_input(json!(".")),
+        ExpectedCall::new("read_file").with_input(json!("main.rs")),
+        ExpectedCall::new("read_file").with_input_matching(|input| input == "notes.md"),
+    ]);
+
+    // The mock's sequence is used up, so further reads fail; the fourth is
+    // over the call limit and never reaches the tool
+    let result = builder.run(r#"for f in ["w", "x", "y", "z"] { read_file(f); }"#).unwrap();
+    assert_calls!(result.tool_calls, [
+        ExpectedCall::new("read_file").failed(),
+        ExpectedCall::new("read_file").failed(),
+        ExpectedCall::new("read_file").failed(),
+        ExpectedCall::new("read_file").failed(),
+    ]);
+    assert_eq!(result.tool_calls[3].status, ToolCallStatus::CallLimitExceeded);
+    assert_eq!(reads.call_count(), 5);
+}
@@ -0,0 +1,66 @@
+//! Integration tests for the slim Rhai build (`rhai-only-i64`, `rhai-no-float`,
+//! `rhai-no-closure`, `rhai-no-custom-syntax`).
+//!
+//! Run with:
+//! `cargo test --features rhai-only-i64,rhai-no-float,rhai-no-closure,rhai-no-custom-syntax`
+//!
+//! The rest of the suite also builds under these features, with float-specific
+//! tests compiled out.
+
+#![cfg(all(
+    feature = "native",
+    feature = "rhai-only-i64",
+    feature = "rhai-no-float",
+    feature = "rhai-no-closure",
+    feature = "rhai-no-custom-syntax"
+))]
+
+use tool_orchestrator::{
+    ExecutionLimits, OrchestratorError, ToolOrchestrator, dynamic_to_json, json_to_dynamic,
+};
+
+#[test]
+fn test_integer_scripts_and_tools_run() {
+    let mut orchestrator = ToolOrchestrator::new();
+    orchestrator.register_executor("price", |input| {
+        Ok(match input.as_str() {
+            Some("apple") => "3",
+            _ => "5",
+        }
+        .to_string())
+    });
+
+    let result = orchestrator
+        .execute(
+            r#"
+            let total = 0;
+            for item in ["apple", "pear", "apple"] {
+                total += parse_int(price(item));
+            }
+            #{ total: total, items: 3 }
+            "#,
+            ExecutionLimits::default(),
+        )
+        .unwrap();
+
+    assert!(result.success);
+    assert_eq!(result.tool_calls.len(), 3);
+    assert_eq!(result.structured_output, Some(serde_json::json!({"total": 11, "items": 3})));
+}
+
+#[test]
+fn test_float_literals_do_not_compile() {
+    let err = ToolOrchestrator::new()
+        .execute("1.5 + 1", ExecutionLimits::default())
+        .unwrap_err();
+    assert!(matches!(err, OrchestratorError::CompilationError(_)), "{err:?}");
+}
+
+#[test]
+fn test_fractional_json_numbers_become_strings() {
+    let value = json_to_dynamic(&serde_json::json!({"price": 2.5, "big": u64::MAX}));
+    assert_eq!(
+        dynamic_to_json(&value),
+        serde_json::json!({"price": "2.5", "big": "18446744073709551615"})
+    );
+}
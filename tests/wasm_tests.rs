@@ -6,6 +6,7 @@
 
 #![cfg(target_arch = "wasm32")]
 
+use wasm_bindgen::JsValue;
 use wasm_bindgen_test::*;
 
 // Note: Not configuring run_in_browser to allow both browser and Node.js testing
@@ -60,6 +61,97 @@ fn test_execution_limits_setters() {
     assert_eq!(limits.max_array_size(), 5_000);
 }
 
+fn object(json: &str) -> JsValue {
+    js_sys::JSON::parse(json).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_execution_limits_from_partial_object() {
+    let limits = WasmExecutionLimits::from_object(object(
+        r#"{"maxToolCalls": 5, "timeout_ms": 3000, "outputPolicy": "error",
+            "language": {"allowEval": false}, "noProgressGuard": {"maxFailedCalls": 2}}"#,
+    ))
+    .unwrap();
+    assert_eq!(limits.max_tool_calls(), 5);
+    assert_eq!(limits.timeout_ms(), 3_000);
+    assert_eq!(limits.output_policy(), "error");
+
+    // Everything left out keeps its default
+    assert_eq!(limits.max_operations(), 100_000);
+    assert_eq!(limits.max_output_bytes(), WasmExecutionLimits::new().max_output_bytes());
+    assert!(!limits.deterministic());
+    let defaults = WasmExecutionLimits::from_object(JsValue::UNDEFINED).unwrap();
+    assert_eq!(defaults.max_tool_calls(), 50);
+    let defaults = WasmExecutionLimits::from_object(object("{}")).unwrap();
+    assert_eq!(defaults.timeout_ms(), 30_000);
+}
+
+#[wasm_bindgen_test]
+fn test_execution_limits_from_object_rejects_unknown_keys() {
+    let err = WasmExecutionLimits::from_object(object(r#"{"timoutMs": 3000}"#)).unwrap_err();
+    let message = err.as_string().unwrap();
+    assert_eq!(message, "Unknown limit 'timoutMs'; did you mean 'timeoutMs'?");
+
+    let err = WasmExecutionLimits::from_object(object(r#"{"language": {"allowGoto": true}}"#))
+        .unwrap_err();
+    assert!(err.as_string().unwrap().starts_with("Unknown limit 'language.allowGoto'"));
+
+    let err = WasmExecutionLimits::from_object(object(r#"{"maxToolCalls": "five"}"#)).unwrap_err();
+    assert!(err.as_string().unwrap().starts_with("Invalid limits:"));
+    assert!(WasmExecutionLimits::from_object(object("[1]")).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_execute_with_options() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("ping", js_sys::Function::new_no_args("return 'pong'"));
+
+    let options = object(r#"{"maxToolCalls": 1}"#);
+    let result = orchestrator.execute_with_options("ping(); ping()", options).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#"{"kind":"tool_call_limit_reached","limit":1}"#));
+
+    let options = object(r#"{"maxToolCals": 1}"#);
+    assert!(orchestrator.execute_with_options("ping()", options).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_execute_with_profile() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("ping", js_sys::Function::new_no_args("return 'pong'"));
+    let mut batch = WasmExecutionLimits::extended();
+    batch.set_max_tool_calls(1);
+    orchestrator.register_profile("batch", &batch);
+
+    let options = object(r#"{"profile": "batch"}"#);
+    let result = orchestrator.execute_with_options("ping(); ping()", options).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#"{"kind":"tool_call_limit_reached","limit":1}"#));
+
+    // The other keys override the profile's limits
+    let options = object(r#"{"profile": "batch", "maxToolCalls": 2}"#);
+    let result = orchestrator.execute_with_options("ping(); ping()", options).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(!result_string.contains("tool_call_limit_reached"));
+
+    let err = orchestrator.execute_with_options("ping()", object(r#"{"profile": "bacth"}"#));
+    assert_eq!(
+        err.unwrap_err().as_string().unwrap(),
+        "Invalid limits: unknown limits profile `bacth`; known profiles: quick, default, \
+         extended, unlimited, batch"
+    );
+
+    // Presets only, outside an orchestrator
+    let limits = WasmExecutionLimits::from_object(object(
+        r#"{"profile": "quick", "timeoutMs": 1000, "language": {"allowEval": false}}"#,
+    ))
+    .unwrap();
+    assert_eq!(limits.max_tool_calls(), 10);
+    assert_eq!(limits.timeout_ms(), 1_000);
+    assert_eq!(WasmExecutionLimits::from_profile("extended").unwrap().max_tool_calls(), 100);
+    assert!(WasmExecutionLimits::from_profile("batch").is_err());
+}
+
 // ============================================================================
 // WasmOrchestrator Tests
 // ============================================================================
@@ -173,7 +265,7 @@ fn test_compilation_error() {
     let result_string: String = result_str.into();
 
     assert!(result_string.contains("\"success\":false"));
-    assert!(result_string.contains("Compilation error"));
+    assert!(result_string.contains("Script compilation failed"));
 }
 
 #[wasm_bindgen_test]
@@ -444,8 +536,9 @@ fn test_max_tool_calls_limit() {
     let result_str = js_sys::JSON::stringify(&result_js).unwrap();
     let result_string: String = result_str.into();
 
-    // Fourth call should hit the limit
+    // Fourth call should hit the limit, and is logged as refused
     assert!(result_string.contains("Maximum tool calls"));
+    assert!(result_string.contains(r#""status":"call_limit_exceeded""#));
 }
 
 #[wasm_bindgen_test]
@@ -534,3 +627,920 @@ fn test_nested_data_structures() {
     // 90+85+92+78+88+95 = 528
     assert!(result_string.contains("528"));
 }
+
+#[wasm_bindgen_test]
+fn test_max_total_tool_output_bytes() {
+    let mut orchestrator = WasmOrchestrator::new();
+
+    let big_fn = js_sys::Function::new_with_args("input", r#"return "x".repeat(1000000)"#);
+    orchestrator.register_tool("big", big_fn);
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_total_tool_output_bytes(2_500_000);
+
+    let result = orchestrator.execute(
+        r#"
+            let received = 0;
+            for i in 0..5 {
+                if !big(i).starts_with("ERROR") { received += 1; }
+            }
+            received
+        "#,
+        &limits
+    );
+
+    assert!(result.is_ok());
+
+    let result_js = result.unwrap();
+    let result_str = js_sys::JSON::stringify(&result_js).unwrap();
+    let result_string: String = result_str.into();
+
+    assert!(result_string.contains("\"output\":\"2\""));
+    assert!(result_string.contains("\"total_tool_output_bytes\":3000000"));
+}
+
+#[wasm_bindgen_test]
+fn test_max_total_state_bytes() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let fetch = js_sys::Function::new_with_args("input", r#"return "x".repeat(1000)"#);
+    orchestrator.register_tool("fetch", fetch);
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_total_state_bytes(5_000);
+
+    let script = "let pages = []; for i in 0..50 { try { pages.push(fetch(i)); } catch {} } \
+                  pages.len()";
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains("\"success\":false"));
+    assert!(result_string.contains("Script exceeded its memory budget (5000 bytes)"));
+    // The calls made up to the one over budget are kept
+    assert_eq!(result_string.matches("\"tool_name\":\"fetch\"").count(), 5);
+}
+
+#[wasm_bindgen_test]
+fn test_tool_output_held_to_max_string_size() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let repeat = js_sys::Function::new_with_args("input", "return 'x'.repeat(Number(input))");
+    orchestrator.register_tool("text", repeat);
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_string_size(100);
+    // Exactly at the limit is fine; one byte over fails the call, not the script
+    let result = orchestrator.execute("text(101); text(100).len()", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains("\"output\":\"100\""), "{result_string}");
+    assert!(result_string.contains("ERROR: Tool output of 101 bytes exceeds 100 bytes"));
+    assert!(result_string.contains("\"status\":\"limit_exceeded\""));
+    let warning = r#"{"kind":"tool_output_too_large","tool":"text","bytes":101,"limit":100}"#;
+    assert!(result_string.contains(warning));
+}
+
+#[wasm_bindgen_test]
+fn test_max_tool_input_bytes() {
+    let mut orchestrator = WasmOrchestrator::new();
+
+    let echo_fn = js_sys::Function::new_with_args("input", r#"return input"#);
+    orchestrator.register_tool("echo", echo_fn);
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_tool_input_bytes(10);
+    assert_eq!(limits.max_tool_input_bytes(), 10);
+
+    // {"a":{"b":"xx"}} is 16 bytes
+    let result = orchestrator.execute(r#"[echo("aaaaaaaa"), echo(#{a: #{b: "xx"}})]"#, &limits);
+
+    let result_js = result.unwrap();
+    let result_str = js_sys::JSON::stringify(&result_js).unwrap();
+    let result_string: String = result_str.into();
+
+    assert!(result_string.contains("\"status\":\"ok\""));
+    assert!(result_string.contains("\"status\":\"limit_exceeded\""));
+    assert!(result_string.contains("tool input of 16 bytes exceeds max_tool_input_bytes (10)"));
+
+    // A tool's own limit takes precedence
+    assert!(orchestrator.set_tool_max_input_bytes("echo", 100));
+    assert!(!orchestrator.set_tool_max_input_bytes("missing", 100));
+    let result = orchestrator.execute(r#"echo(#{a: #{b: "xx"}})"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(!result_string.contains("limit_exceeded"), "{result_string}");
+}
+
+#[wasm_bindgen_test]
+fn test_event_callback_ordering() {
+    let mut orchestrator = WasmOrchestrator::new();
+
+    orchestrator.register_tool("first", js_sys::Function::new_with_args("input", r#"return "1""#));
+    orchestrator.register_tool("second", js_sys::Function::new_with_args("input", r#"return "2""#));
+
+    // Collect tool events into a JS array via a closure-producing factory
+    let events = js_sys::Array::new();
+    let factory = js_sys::Function::new_with_args(
+        "events",
+        r#"return function(e) {
+            var ev = JSON.parse(e);
+            if (ev.type !== "progress") { events.push(ev.type + ":" + ev.tool_name); }
+        }"#,
+    );
+    let callback: js_sys::Function = factory.call1(&JsValue::NULL, &events).unwrap().into();
+    orchestrator.set_event_callback(callback);
+
+    let limits = WasmExecutionLimits::new();
+    let result = orchestrator.execute(r#"let a = first("x"); second(a)"#, &limits);
+    assert!(result.is_ok());
+
+    let collected: Vec<String> = events.iter().filter_map(|v| v.as_string()).collect();
+    assert_eq!(
+        collected,
+        vec![
+            "tool_call_start:first",
+            "tool_call_end:first",
+            "tool_call_start:second",
+            "tool_call_end:second",
+        ]
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_event_callback_errors_do_not_abort() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return input"));
+    orchestrator.set_event_callback(js_sys::Function::new_with_args("e", r#"throw new Error("boom")"#));
+
+    let limits = WasmExecutionLimits::new();
+    let result = orchestrator.execute(r#"echo("hi")"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains("\"success\":true"));
+}
+
+#[wasm_bindgen_test]
+fn test_multi_argument_tool_call() {
+    let mut orchestrator = WasmOrchestrator::new();
+
+    let join_fn = js_sys::Function::new_with_args(
+        "input",
+        r#"var args = JSON.parse(input); return args.join("-")"#,
+    );
+    orchestrator.register_tool("join", join_fn);
+
+    let limits = WasmExecutionLimits::new();
+    let result = orchestrator.execute(r#"join("a", "b", "c")"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains("\"output\":\"a-b-c\""));
+}
+
+#[wasm_bindgen_test]
+fn test_execution_limits_unlimited() {
+    let limits = WasmExecutionLimits::unlimited();
+    assert_eq!(limits.max_operations(), u64::MAX);
+    assert_eq!(limits.max_tool_calls(), usize::MAX);
+    assert_eq!(limits.timeout_ms(), u64::MAX);
+}
+
+#[wasm_bindgen_test]
+fn test_invalid_limits_return_error_result() {
+    let orchestrator = WasmOrchestrator::new();
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_timeout_ms(0);
+    let result = orchestrator.execute("1 + 1", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains("\"success\":false"));
+    assert!(result_string.contains("timeout_ms"));
+}
+
+#[wasm_bindgen_test]
+fn test_zero_tool_calls_with_tool_using_script() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("fetch", js_sys::Function::new_with_args("input", "return input"));
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_tool_calls(0);
+    let result = orchestrator.execute(r#"fetch("x")"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains("\"success\":false"));
+    assert!(result_string.contains("`fetch` tool"));
+}
+
+#[wasm_bindgen_test]
+fn test_script_size_and_complexity_limits() {
+    let orchestrator = WasmOrchestrator::new();
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_script_bytes(10);
+    assert_eq!(limits.max_script_bytes(), 10);
+    let result = orchestrator.execute("let x = 1; x + 1", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"success\":false"));
+    assert!(result_string.contains("Script is too large"));
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_functions(0);
+    let result = orchestrator.execute("fn f() { 1 } f()", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("too many functions"));
+}
+
+#[wasm_bindgen_test]
+fn test_emitted_outputs() {
+    let orchestrator = WasmOrchestrator::new();
+    let mut limits = WasmExecutionLimits::new();
+
+    let script = r#"emit("summary", #{ n: 2 }); emit("ids", [1, 2]); emit("score", 9); "ok""#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#""output":"ok""#));
+    assert!(result_string.contains(r#""outputs":{"ids":[1,2],"score":9,"summary":{"n":2}}"#));
+
+    limits.set_max_outputs(1);
+    assert_eq!(limits.max_outputs(), 1);
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("Maximum outputs (1) exceeded"));
+}
+
+#[wasm_bindgen_test]
+fn test_map_with_tool_and_closure_inputs() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool(
+        "shout",
+        js_sys::Function::new_with_args("input", "return JSON.parse(input).toUpperCase()"),
+    );
+    let limits = WasmExecutionLimits::new();
+
+    let result = orchestrator
+        .execute(r#"map_with_tool(["a", "b"], "shout")"#, &limits)
+        .unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#""structured_output":["A","B"]"#));
+
+    let script = r#"let e = ""; try { shout(Fn("to_upper")); } catch (err) { e = err; } e"#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("closures cannot be passed to tools"));
+}
+
+#[wasm_bindgen_test]
+fn test_kv_store() {
+    let orchestrator = WasmOrchestrator::new();
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_kv_entries(2);
+    assert_eq!(limits.max_kv_entries(), 2);
+
+    let script = r#"
+        let e = "";
+        kv_set("a", [1, 2]);
+        kv_set("b", #{ n: 3 });
+        try { kv_set("c", 4); } catch (err) { e = err; }
+        [kv_get("a"), kv_get("b").n, kv_keys(), kv_delete("a"), kv_get("a") == (), e]
+    "#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#""structured_output":[[1,2],3,["a","b"],true,true,"#));
+    assert!(result_string.contains("kv_set: Maximum key-value entries (2) exceeded by 'c'"));
+}
+
+#[wasm_bindgen_test]
+fn test_size_limit_behavior() {
+    let orchestrator = WasmOrchestrator::new();
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_array_size(5);
+    let script = "let rows = []; for i in 0..10 { rows.push(i); } rows.len()";
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"success\":false"));
+
+    assert_eq!(limits.size_limit_behavior(), "abort");
+    limits.set_size_limit_behavior("truncate_with_warning").unwrap();
+    assert!(limits.set_size_limit_behavior("drop").is_err());
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"output\":\"5\""));
+    assert!(result_string.contains("\"kind\":\"collection_truncated\""));
+}
+
+#[wasm_bindgen_test]
+fn test_script_hash() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let limits = WasmExecutionLimits::new();
+    let sha256 = "0fcd24935d76f7d5eda64ffde9e33f08079ca2c7e398557915a7e25d60bae721";
+
+    let result = orchestrator.execute("40 + 2", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(&format!("\"script_hash\":\"{sha256}\"")));
+    assert!(!result_string.contains("\"script\":"));
+
+    orchestrator.set_include_script(true);
+    let result = orchestrator.execute("40 + 2", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"script\":\"40 + 2\""));
+}
+
+#[wasm_bindgen_test]
+fn test_output_format() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let limits = WasmExecutionLimits::new();
+    let output = |orchestrator: &mut WasmOrchestrator| {
+        let result = orchestrator.execute("[1, 2]", &limits).unwrap();
+        property(&result, "output").as_string().unwrap()
+    };
+
+    assert_eq!(output(&mut orchestrator), "[1,2]");
+    orchestrator.set_output_format("pretty_json").unwrap();
+    assert_eq!(output(&mut orchestrator), "[\n  1,\n  2\n]");
+    orchestrator.set_output_format("debug").unwrap();
+    assert_eq!(output(&mut orchestrator), "[1, 2]");
+    assert!(orchestrator.set_output_format("yaml").is_err());
+}
+
+// wasm-slim builds have no floats
+#[cfg(not(feature = "rhai-no-float"))]
+#[wasm_bindgen_test]
+fn test_float_precision() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let limits = WasmExecutionLimits::new();
+    let script = "let total = 0.1 + 0.2; [`${total}`, total]";
+
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let output = property(&result, "output").as_string().unwrap();
+    assert_eq!(output, r#"["0.30000000000000004",0.30000000000000004]"#);
+    orchestrator.set_float_precision(Some(2));
+    let result = orchestrator.execute(script, &limits).unwrap();
+    assert_eq!(property(&result, "output").as_string().unwrap(), r#"["0.30",0.3]"#);
+}
+
+#[wasm_bindgen_test]
+fn test_finish_and_fail_end_script_early() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let echo_fn = js_sys::Function::new_with_args("input", "return String(input)");
+    orchestrator.register_tool("echo", echo_fn);
+    let limits = WasmExecutionLimits::new();
+
+    let script = r#"fn stop(n) { for i in 0..n { if echo(i) == "2" { finish(i); } } } stop(9); 0"#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    assert_eq!(property(&result, "success"), JsValue::TRUE);
+    assert_eq!(property(&result, "output").as_string().unwrap(), "2");
+
+    let script = r#"for i in 0..3 { echo(i); if i == 1 { fail("gave up"); } } 0"#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    assert_eq!(property(&result, "success"), JsValue::FALSE);
+    assert_eq!(property(&result, "error").as_string().unwrap(), "gave up");
+    let calls = js_sys::Array::from(&property(&result, "tool_calls"));
+    assert_eq!(calls.length(), 2);
+}
+
+#[wasm_bindgen_test]
+fn test_call_tool_routes_by_computed_name() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool(
+        "shout",
+        js_sys::Function::new_with_args("input", "return JSON.parse(input).toUpperCase()"),
+    );
+    let limits = WasmExecutionLimits::new();
+
+    let script = r#"let routes = #{ loud: "shout" }; call_tool(routes.loud, "hi")"#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#""output":"HI""#));
+    assert!(result_string.contains(r#""invoked_dynamically":true"#));
+
+    let script = r#"let e = ""; try { call_tool("whisper", "hi"); } catch (err) { e = err; } e"#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("call_tool: 'whisper' is not a registered tool"));
+}
+
+#[wasm_bindgen_test]
+fn test_call_parallel_keeps_order_and_errors_in_place() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let echo = js_sys::Function::new_with_args("input", "return String(input)");
+    orchestrator.register_tool("echo", echo);
+    orchestrator.register_tool("fail", js_sys::Function::new_with_args("input", "throw 'down'"));
+    let limits = WasmExecutionLimits::new();
+
+    let script = r#"
+        call_parallel([
+            #{ tool: "echo", input: 1 },
+            #{ tool: "fail", input: 2 },
+            #{ tool: "echo", input: 3 },
+        ])
+    "#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    assert_eq!(property(&result, "success"), JsValue::TRUE);
+    let output = property(&result, "output").as_string().unwrap();
+    assert_eq!(output, r#"["1","Tool error: down","3"]"#);
+    let calls = js_sys::Array::from(&property(&result, "tool_calls"));
+    let names: Vec<_> =
+        calls.iter().map(|c| property(&c, "tool_name").as_string().unwrap()).collect();
+    assert_eq!(names, ["echo", "fail", "echo"]);
+    assert_eq!(property(&calls.get(1), "success"), JsValue::FALSE);
+}
+
+#[wasm_bindgen_test]
+fn test_call_parallel_counts_each_call_against_limits() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let echo = js_sys::Function::new_with_args("input", "return String(input)");
+    orchestrator.register_tool("echo", echo);
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_tool_calls(2);
+
+    let script = r#"call_parallel([1, 2, 3, 4].map(|n| #{ tool: "echo", input: n }))"#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let exceeded = "ERROR: Maximum tool calls (2) exceeded";
+    let output = property(&result, "output").as_string().unwrap();
+    assert_eq!(output, format!(r#"["1","2","{exceeded}","{exceeded}"]"#));
+    let calls = js_sys::Array::from(&property(&result, "tool_calls"));
+    let statuses: Vec<_> =
+        calls.iter().map(|c| property(&c, "status").as_string().unwrap()).collect();
+    assert_eq!(statuses, ["ok", "ok", "call_limit_exceeded", "call_limit_exceeded"]);
+}
+
+#[wasm_bindgen_test]
+fn test_join_all_returns_spawned_outputs_in_order() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let echo = js_sys::Function::new_with_args("input", "return String(input)");
+    orchestrator.register_tool("echo", echo);
+    orchestrator.register_tool("fail", js_sys::Function::new_with_args("input", "throw 'down'"));
+    let limits = WasmExecutionLimits::new();
+
+    let script = r#"
+        let tasks = [spawn_tool("echo", 1), spawn_tool("fail", 2), spawn_tool("echo", 3)];
+        join_all(tasks)
+    "#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let output = property(&result, "output").as_string().unwrap();
+    assert_eq!(output, r#"["1","Tool error: down","3"]"#);
+
+    let script = r#"let task = spawn_tool("echo", 1); join(task); join(task)"#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    assert_eq!(property(&result, "success"), JsValue::FALSE);
+    let error = property(&result, "error").as_string().unwrap();
+    assert!(error.contains("the task was already joined"), "{error}");
+}
+
+#[wasm_bindgen_test]
+fn test_spawned_tools_are_limited_when_spawned() {
+    let mut orchestrator = WasmOrchestrator::new();
+    let echo = js_sys::Function::new_with_args("input", "return String(input)");
+    orchestrator.register_tool("echo", echo);
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_tool_calls(2);
+
+    // The budget is spent before anything is joined
+    let script = r#"
+        let tasks = [spawn_tool("echo", 1), spawn_tool("echo", 2), spawn_tool("echo", 3)];
+        remaining_tool_calls() + ": " + join_all(tasks)
+    "#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let output = property(&result, "output").as_string().unwrap();
+    assert_eq!(output, r#"0: ["1", "2", "ERROR: Maximum tool calls (2) exceeded"]"#);
+    let calls = js_sys::Array::from(&property(&result, "tool_calls"));
+    let statuses: Vec<_> =
+        calls.iter().map(|c| property(&c, "status").as_string().unwrap()).collect();
+    assert_eq!(statuses, ["ok", "ok", "call_limit_exceeded"]);
+}
+
+#[wasm_bindgen_test]
+fn test_warnings_are_reported() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return input"));
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_tool_calls(1);
+
+    let script = r#"echo(1); echo(2); emit("a", 1); emit("a", 2); "done""#;
+    let result = orchestrator.execute(script, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#"{"kind":"tool_call_limit_reached","limit":1}"#));
+    assert!(result_string.contains(r#"{"kind":"output_replaced","name":"a"}"#));
+}
+
+#[wasm_bindgen_test]
+fn test_map_result_is_structured_json() {
+    let orchestrator = WasmOrchestrator::new();
+    let limits = WasmExecutionLimits::new();
+
+    let result = orchestrator
+        .execute(r#"#{ total: 3, items: [1, 2] }"#, &limits)
+        .unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains(r#""output":"{\"items\":[1,2],\"total\":3}""#));
+    assert!(result_string.contains(r#""structured_output":{"items":[1,2],"total":3}"#));
+}
+
+#[wasm_bindgen_test]
+fn test_output_limit_policies() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool(
+        "big",
+        js_sys::Function::new_with_args("input", "return 'x'.repeat(500)"),
+    );
+
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_output_bytes(100);
+    let result = orchestrator.execute("big(1)", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"success\":true"));
+    assert!(result_string.contains("bytes]"));
+    assert!(result_string.contains("\"tool_name\":\"big\""));
+
+    assert_eq!(limits.output_policy(), "truncate");
+    limits.set_output_policy("error").unwrap();
+    assert!(limits.set_output_policy("drop").is_err());
+    let result = orchestrator.execute("big(1)", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"success\":false"));
+    assert!(result_string.contains("exceeded maximum size"));
+    assert!(result_string.contains("\"tool_name\":\"big\""));
+}
+
+#[wasm_bindgen_test]
+fn test_register_tool_reports_replacement() {
+    let mut orchestrator = WasmOrchestrator::new();
+
+    assert!(!orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return 'a'")));
+    assert!(orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return 'b'")));
+    assert_eq!(orchestrator.registered_tools(), vec!["echo".to_string()]);
+
+    let limits = WasmExecutionLimits::new();
+    let result = orchestrator.execute(r#"echo("x")"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"output\":\"b\""));
+}
+
+#[wasm_bindgen_test]
+fn test_unregister_tool() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return input"));
+
+    let limits = WasmExecutionLimits::new();
+    let result = orchestrator.execute(r#"echo("x")"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"success\":true"));
+
+    assert!(orchestrator.unregister_tool("echo"));
+    assert!(!orchestrator.unregister_tool("echo"));
+    assert!(orchestrator.registered_tools().is_empty());
+
+    let result = orchestrator.execute(r#"echo("x")"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"success\":false"));
+    assert!(result_string.contains("Function not found"));
+}
+
+#[wasm_bindgen_test]
+fn test_clear_tools() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("a", js_sys::Function::new_with_args("input", "return 'a'"));
+    orchestrator.register_tool("b", js_sys::Function::new_with_args("input", "return 'b'"));
+
+    orchestrator.clear_tools();
+    assert!(orchestrator.registered_tools().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn test_execution_id_on_result_and_tool_calls() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return input"));
+    let limits = WasmExecutionLimits::new();
+
+    let result = orchestrator.execute_with_id(r#"echo("x")"#, &limits, "req-7").unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert_eq!(result_string.matches(r#""execution_id":"req-7""#).count(), 2);
+
+    let result = orchestrator.execute("1", &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains(r#""execution_id":""#));
+    assert!(!result_string.contains(r#""execution_id":"""#));
+}
+
+#[wasm_bindgen_test]
+fn test_object_tool_passes_js_values_both_ways() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool_object(
+        "enrich",
+        js_sys::Function::new_with_args(
+            "input",
+            "return { user: input.user, tags: input.tags.concat(['new']), next: input.user.id + 1 }",
+        ),
+    );
+    let limits = WasmExecutionLimits::new();
+
+    let result = orchestrator
+        .execute(r#"enrich(#{ user: #{ id: 1, name: "Ada" }, tags: ["a"] })"#, &limits)
+        .unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+
+    assert!(result_string.contains("\"success\":true"));
+    // The script receives the JSON text of the returned object
+    assert!(result_string.contains(
+        r#""output":"{\"user\":{\"id\":1,\"name\":\"Ada\"},\"tags\":[\"a\",\"new\"],\"next\":2}""#
+    ));
+    // The call log keeps the structured input
+    assert!(result_string.contains(r#""input":{"tags":["a"],"user":{"id":1,"name":"Ada"}}"#));
+}
+
+#[wasm_bindgen_test]
+fn test_object_tool_string_return_is_verbatim() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool_object("name", js_sys::Function::new_with_args("input", "return input.name"));
+    let limits = WasmExecutionLimits::new();
+
+    let result = orchestrator.execute(r#"name(#{ name: "Ada" })"#, &limits).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(result_string.contains("\"output\":\"Ada\""));
+}
+
+#[wasm_bindgen_test]
+fn test_object_tool_unserializable_return_is_tool_error() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool_object("nothing", js_sys::Function::new_with_args("input", "return undefined"));
+    orchestrator.register_tool_object("func", js_sys::Function::new_with_args("input", "return () => 1"));
+    let limits = WasmExecutionLimits::new();
+
+    for script in ["nothing(1)", "func(1)"] {
+        let result = orchestrator.execute(script, &limits).unwrap();
+        let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+        assert!(result_string.contains("cannot be converted to JSON"), "{script}");
+        assert!(result_string.contains("\"success\":false"), "{script}");
+    }
+}
+
+// ============================================================================
+// Abortable Execution Tests
+// ============================================================================
+
+/// Await an `execute_abortable` promise, as `Ok(result)` or `Err(rejection)`.
+async fn settle(promise: js_sys::Promise) -> Result<JsValue, JsValue> {
+    wasm_bindgen_futures::JsFuture::from(promise).await
+}
+
+fn property(value: &JsValue, name: &str) -> JsValue {
+    js_sys::Reflect::get(value, &JsValue::from_str(name)).unwrap()
+}
+
+#[wasm_bindgen_test]
+async fn test_abortable_execution_resolves_to_result() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return input"));
+    let controller = web_sys::AbortController::new().unwrap();
+
+    let limits = WasmExecutionLimits::new();
+    let promise = orchestrator.execute_abortable("echo(1); 2", &limits, controller.signal());
+    let result = settle(promise).await.unwrap();
+    assert_eq!(property(&result, "success"), JsValue::TRUE);
+    assert_eq!(property(&result, "output"), JsValue::from_str("2"));
+}
+
+#[wasm_bindgen_test]
+fn test_check_script_accepts_valid_script() {
+    let mut orchestrator = WasmOrchestrator::new();
+    // Checking must never call the tool
+    let tool = js_sys::Function::new_no_args("throw new Error('tool was called')");
+    orchestrator.register_tool("fetch", tool);
+
+    let check = orchestrator.check_script(
+        "fn twice(x) { x * 2 }\nlet page = fetch(\"a\");\nemit(\"n\", twice(page.len()))",
+    );
+    assert_eq!(property(&check, "valid"), JsValue::TRUE);
+    assert_eq!(js_sys::Array::from(&property(&check, "errors")).length(), 0);
+}
+
+#[wasm_bindgen_test]
+fn test_check_script_reports_syntax_error_position() {
+    let orchestrator = WasmOrchestrator::new();
+    let check = orchestrator.check_script("let a = 1;\nlet b = (a + ;\na + b");
+    assert_eq!(property(&check, "valid"), JsValue::FALSE);
+    let errors = js_sys::Array::from(&property(&check, "errors"));
+    assert_eq!(errors.length(), 1);
+    assert_eq!(property(&errors.get(0), "line"), JsValue::from_f64(2.0));
+    assert!(property(&errors.get(0), "column").as_f64().is_some());
+}
+
+#[wasm_bindgen_test]
+fn test_check_script_reports_unknown_tool() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("get_weather", js_sys::Function::new_no_args("return ''"));
+
+    let check = orchestrator.check_script("let w = get_weather(\"Oslo\");\nget_wether(\"Rome\")");
+    assert_eq!(property(&check, "valid"), JsValue::FALSE);
+    let errors = js_sys::Array::from(&property(&check, "errors"));
+    assert_eq!(errors.length(), 1);
+    let error = errors.get(0);
+    assert_eq!(
+        property(&error, "message"),
+        JsValue::from_str("unknown function 'get_wether'; did you mean 'get_weather'?")
+    );
+    assert_eq!(property(&error, "line"), JsValue::from_f64(2.0));
+    assert_eq!(property(&error, "column"), JsValue::from_f64(1.0));
+}
+
+#[wasm_bindgen_test]
+fn test_result_reports_timing_breakdown() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("echo", js_sys::Function::new_with_args("input", "return input"));
+
+    let result = orchestrator.execute("echo(1); echo(2); 3", &WasmExecutionLimits::new()).unwrap();
+    let timing = property(&result, "timing");
+    assert_eq!(property(&timing, "total_ms"), property(&result, "execution_time_ms"));
+    assert!(property(&timing, "tool_time_ms").as_f64().is_some());
+    assert!(property(&timing, "compile_ms").as_f64().is_some());
+    let slowest = js_sys::Array::from(&property(&timing, "slowest_calls"));
+    assert_eq!(slowest.length(), 2);
+    let name = js_sys::Array::from(&slowest.get(0)).get(0);
+    assert_eq!(name, JsValue::from_str("echo"));
+}
+
+#[wasm_bindgen_test]
+fn test_output_filters_chain_and_skip_tool_calls() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("secret", js_sys::Function::new_no_args("return 'key=abc  '"));
+    orchestrator.add_output_filter(js_sys::Function::new_with_args(
+        "text",
+        "return text.replace('abc', '***')",
+    ));
+    orchestrator.add_builtin_output_filter("collapse_whitespace").unwrap();
+    assert!(orchestrator.add_builtin_output_filter("shout").is_err());
+
+    let result = orchestrator.execute("secret()", &WasmExecutionLimits::new()).unwrap();
+    assert_eq!(property(&result, "output"), JsValue::from_str("key=***"));
+    let call = js_sys::Array::from(&property(&result, "tool_calls")).get(0);
+    assert_eq!(property(&call, "output"), JsValue::from_str("key=abc  "));
+
+    orchestrator.set_filter_tool_call_outputs(true);
+    let result = orchestrator.execute("secret()", &WasmExecutionLimits::new()).unwrap();
+    let call = js_sys::Array::from(&property(&result, "tool_calls")).get(0);
+    assert_eq!(property(&call, "output"), JsValue::from_str("key=***"));
+}
+
+#[wasm_bindgen_test]
+fn test_output_filters_rewrite_structured_output_and_outputs() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.add_output_filter(js_sys::Function::new_with_args(
+        "text",
+        "return text.replace('abc', '***')",
+    ));
+
+    let script = r#"emit("raw", "key=abc"); ["key=abc"]"#;
+    let result = orchestrator.execute(script, &WasmExecutionLimits::new()).unwrap();
+    let result_string: String = js_sys::JSON::stringify(&result).unwrap().into();
+    assert!(!result_string.contains("abc"), "{result_string}");
+    assert!(result_string.contains(r#""raw":"key=***""#), "{result_string}");
+}
+
+#[wasm_bindgen_test]
+fn test_throwing_output_filter_fails_execution() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.add_output_filter(js_sys::Function::new_no_args("throw 'nope'"));
+
+    let result = orchestrator.execute("\"secret\"", &WasmExecutionLimits::new()).unwrap();
+    assert_eq!(property(&result, "success"), JsValue::FALSE);
+    let error = property(&result, "error").as_string().unwrap();
+    assert_eq!(error, "Output filter failed: nope");
+}
+
+#[wasm_bindgen_test]
+async fn test_already_aborted_signal_rejects() {
+    let orchestrator = WasmOrchestrator::new();
+    let controller = web_sys::AbortController::new().unwrap();
+    controller.abort();
+
+    let promise =
+        orchestrator.execute_abortable("1 + 1", &WasmExecutionLimits::new(), controller.signal());
+    let error = settle(promise).await.unwrap_err();
+    assert_eq!(property(&error, "name"), JsValue::from_str("AbortError"));
+    assert_eq!(js_sys::Array::from(&property(&error, "tool_calls")).length(), 0);
+}
+
+#[wasm_bindgen_test]
+async fn test_abort_during_execution_stops_tool_calls() {
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+
+    let controller = web_sys::AbortController::new().unwrap();
+    let ctl = controller.clone();
+    // The first call aborts, as a tool that detects a fatal condition would
+    let step = Closure::<dyn Fn(String) -> String>::new(move |input: String| {
+        ctl.abort();
+        format!("step {input}")
+    });
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("step", step.as_ref().unchecked_ref::<js_sys::Function>().clone());
+
+    let script = "for i in 0..10 { step(i); } \"finished\"";
+    let limits = WasmExecutionLimits::new();
+    let promise = orchestrator.execute_abortable(script, &limits, controller.signal());
+    let error = settle(promise).await.unwrap_err();
+
+    assert!(error.is_instance_of::<js_sys::Error>());
+    assert_eq!(property(&error, "name"), JsValue::from_str("AbortError"));
+    assert!(property(&error, "execution_id").is_string());
+    // Only the call made before the abort is reported
+    let calls = js_sys::Array::from(&property(&error, "tool_calls"));
+    assert_eq!(calls.length(), 1);
+    assert_eq!(property(&calls.get(0), "output"), JsValue::from_str("step 0"));
+}
+
+#[wasm_bindgen_test]
+fn test_errors_match_native_messages() {
+    // Same scripts and messages as the native test_limit_errors_match_wasm
+    let mut orchestrator = WasmOrchestrator::new();
+    let weather = js_sys::Function::new_with_args("input", "return 'sunny'");
+    orchestrator.register_tool("get_weather", weather);
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_operations(1_000);
+    limits.set_max_tool_calls(1);
+    let run = |script: &str| orchestrator.execute(script, &limits).unwrap();
+    let error = |script: &str| property(&run(script), "error").as_string().unwrap();
+
+    assert_eq!(error("loop {}"), "Script exceeded maximum operations (1000)");
+    assert!(error("let x = ;").starts_with("Script compilation failed: "));
+    let unknown = error("get_wether()");
+    assert!(unknown.starts_with("Script execution failed: Function not found: get_wether"));
+    assert!(unknown.ends_with("; did you mean 'get_weather'?"));
+    let output = property(&run("get_weather(); get_weather()"), "output");
+    assert_eq!(output, JsValue::from_str("ERROR: Maximum tool calls (1) exceeded"));
+}
+
+// ============================================================================
+// Chunked Execution Tests
+// ============================================================================
+
+/// A tool that blocks for `ms` milliseconds before answering.
+fn slow_tool(ms: u32) -> js_sys::Function {
+    // performance.now, the clock the timings are measured with; Date.now's
+    // whole milliseconds can end the wait early
+    let body = format!(
+        "const end = performance.now() + {ms}; while (performance.now() < end); return 'done'"
+    );
+    js_sys::Function::new_with_args("input", &body)
+}
+
+/// Clear a flag and set a timer that sets it once the event loop gets a turn.
+fn arm_tick_flag() {
+    js_sys::eval("globalThis.__ticked = false; setTimeout(() => { globalThis.__ticked = true; })")
+        .unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn test_chunked_execution_yields_between_statements() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("slow", slow_tool(20));
+    orchestrator.register_tool(
+        "ticked",
+        js_sys::Function::new_with_args("input", "return String(globalThis.__ticked)"),
+    );
+
+    arm_tick_flag();
+    let script = r#"
+        fn total(list) { list.reduce(|sum, x| sum + x, 0) }
+        let n = 0;
+        for i in 0..50 { n += i; }
+        const LABEL = slow(1);
+        let seen = ticked(1);
+        let more = total([n, 1]);
+        if more > 0 { return `${LABEL} ${seen} ${more}`; }
+        "unreachable"
+    "#;
+    let limits = WasmExecutionLimits::new();
+    let result = settle(orchestrator.execute_chunked(script, &limits, 10)).await.unwrap();
+    assert_eq!(property(&result, "success"), JsValue::TRUE);
+    assert_eq!(property(&result, "output"), JsValue::from_str("done true 1226"));
+
+    // The same script run in one go never gives the timer a turn
+    arm_tick_flag();
+    let result = orchestrator.execute(script, &limits).unwrap();
+    assert_eq!(property(&result, "output"), JsValue::from_str("done false 1226"));
+}
+
+#[wasm_bindgen_test]
+async fn test_chunked_execution_keeps_timing_and_limits() {
+    let mut orchestrator = WasmOrchestrator::new();
+    orchestrator.register_tool("slow", slow_tool(20));
+
+    let limits = WasmExecutionLimits::new();
+    let script = "let a = slow(1); let b = slow(2); a + b";
+    let result = settle(orchestrator.execute_chunked(script, &limits, 1)).await.unwrap();
+    assert_eq!(property(&result, "output"), JsValue::from_str("donedone"));
+    let timing = property(&result, "timing");
+    let total_ms = property(&timing, "total_ms").as_f64().unwrap();
+    let tool_time_ms = property(&timing, "tool_time_ms").as_f64().unwrap();
+    assert_eq!(property(&timing, "total_ms"), property(&result, "execution_time_ms"));
+    assert!(tool_time_ms >= 40.0, "{tool_time_ms}");
+    assert!(total_ms >= tool_time_ms, "{total_ms} < {tool_time_ms}");
+    assert_eq!(js_sys::Array::from(&property(&result, "tool_calls")).length(), 2);
+
+    // max_operations covers the whole script, not each statement
+    let mut limits = WasmExecutionLimits::new();
+    limits.set_max_operations(300);
+    let count = "for i in 0..40 { n += 1; }";
+    let script = format!("let n = 0; {count} {count} {count} n");
+    let result = settle(orchestrator.execute_chunked(&script, &limits, 10)).await.unwrap();
+    assert_eq!(property(&result, "success"), JsValue::FALSE);
+}